@@ -0,0 +1,141 @@
+//! Admin endpoint — control a running daemon over HTTP
+//!
+//! Disabled unless an admin port is configured, bound to loopback by default,
+//! and protected by a bearer token when one is set (Prometheus can send it
+//! with `authorization.credentials` in the scrape config). The health checks
+//! stay open so probes don't need the token; they reveal nothing but state.
+
+use anyhow::Result;
+use axum::{
+    Router,
+    extract::State,
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Json},
+    routing::{get, post},
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::metrics::DaemonMetrics;
+use crate::reload::ReloadHandle;
+use crate::vault::{RevealHandle, RevealParams};
+use crate::viewer::bearer_auth;
+
+#[derive(Clone)]
+struct AdminState {
+    reload: ReloadHandle,
+    reveal: RevealHandle,
+    metrics: Arc<DaemonMetrics>,
+}
+
+/// Serve the admin API until `ct` is cancelled.
+///
+/// - `POST /admin/reload` — re-read the configuration (same as SIGHUP) and
+///   return the resulting `CONFIG_CHANGE` payload
+/// - `POST /admin/reveal` — record a `REVEAL` event for `clawprint reveal`
+///   and return the event it covers (see [`crate::vault::reveal_via_admin`])
+/// - `GET /metrics` — daemon metrics in the Prometheus text format
+/// - `GET /healthz` — 200 while the process is serving requests
+/// - `GET /readyz` — 200 while recording (connected to the gateway, ledger
+///   writable, last flush recent), 503 otherwise
+pub async fn start_admin_with_shutdown(
+    host: [u8; 4],
+    port: u16,
+    token: Option<String>,
+    reload: ReloadHandle,
+    reveal: RevealHandle,
+    metrics: Arc<DaemonMetrics>,
+    ct: tokio_util::sync::CancellationToken,
+) -> Result<()> {
+    let app = Router::new()
+        .route("/admin/reload", post(reload_handler))
+        .route("/admin/reveal", post(reveal_handler))
+        .route("/metrics", get(metrics_handler))
+        .with_state(AdminState {
+            reload,
+            reveal,
+            metrics: metrics.clone(),
+        });
+
+    let app = if let Some(tok) = token {
+        app.layer(middleware::from_fn_with_state(Arc::new(tok), bearer_auth))
+    } else {
+        app
+    };
+    let app = app.merge(probe_routes(Some(metrics)));
+
+    let addr = SocketAddr::from((host, port));
+    info!("Admin API starting on http://{}", addr);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app.into_make_service())
+        .with_graceful_shutdown(async move {
+            ct.cancelled().await;
+        })
+        .await?;
+    Ok(())
+}
+
+async fn reload_handler(State(state): State<AdminState>) -> impl IntoResponse {
+    match state.reload.reload("admin").await {
+        Ok(change) => Json(change).into_response(),
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+async fn reveal_handler(
+    State(state): State<AdminState>,
+    Json(params): Json<RevealParams>,
+) -> impl IntoResponse {
+    match state.reveal.reveal(params).await {
+        Ok(event) => Json(event).into_response(),
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": e.to_string() })),
+        )
+            .into_response(),
+    }
+}
+
+async fn metrics_handler(State(state): State<AdminState>) -> impl IntoResponse {
+    (
+        [(
+            axum::http::header::CONTENT_TYPE,
+            "text/plain; version=0.0.4; charset=utf-8",
+        )],
+        state.metrics.render(chrono::Utc::now()),
+    )
+}
+
+/// `GET /healthz` and `GET /readyz`, open without a token. `serve` also
+/// mounts them on the viewer port. Without a daemon's `metrics` there is
+/// nothing to record, and `/readyz` is ready while the process serves.
+pub fn probe_routes(metrics: Option<Arc<DaemonMetrics>>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz_handler))
+        .route("/readyz", get(readyz_handler))
+        .with_state(metrics)
+}
+
+async fn healthz_handler() -> impl IntoResponse {
+    Json(serde_json::json!({ "status": "ok" }))
+}
+
+async fn readyz_handler(State(metrics): State<Option<Arc<DaemonMetrics>>>) -> impl IntoResponse {
+    let Some(metrics) = metrics else {
+        return (StatusCode::OK, Json(serde_json::json!({ "ready": true })));
+    };
+    let readiness = metrics.readiness(chrono::Utc::now());
+    let status = if readiness.ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(serde_json::json!(readiness)))
+}
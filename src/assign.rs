@@ -0,0 +1,407 @@
+//! Run assignment — derive which agent run an event belongs to
+//!
+//! Only agent events carry `data.runId`. Chat output, presence and other
+//! events usually don't, so grouping by that field alone leaves them
+//! orphaned. The `RunAssigner` tries an ordered list of strategies and
+//! remembers what it has seen (sessions, the active run, the last sequence
+//! number) so later events can be attributed too.
+//!
+//! The default policy is `data.runId` alone, as before the other strategies
+//! existed, so upgrading doesn't regroup a ledger; the others are opt-in.
+//!
+//! The derived `agent_run` is an index column, not part of an event's hashed
+//! form, so it can be recomputed for stored events without touching the chain.
+//! The assigner's state is saved with the ledger flush that changes it, so a
+//! restarted daemon keeps attributing an in-progress session to its run. A
+//! session is forgotten once its run's lifecycle ends, which keeps that
+//! state to the runs in progress.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::{Event, EventKind};
+
+/// One way of attributing an event to an agent run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "strategy", rename_all = "snake_case")]
+pub enum AssignStrategy {
+    /// Use `data.runId` from the gateway payload
+    RunId,
+    /// Map `data.sessionKey` to the latest run seen for that session
+    SessionKey,
+    /// Attach chat output to the run currently in progress
+    Chat,
+    /// Attach events within `window` gateway seq numbers of the last
+    /// attributed event to that event's run
+    SeqWindow { window: u64 },
+}
+
+impl FromStr for AssignStrategy {
+    type Err = anyhow::Error;
+
+    /// Parse `run-id`, `session-key`, `chat` or `seq-window:N`.
+    fn from_str(s: &str) -> Result<Self> {
+        let (name, arg) = match s.trim().split_once(':') {
+            Some((n, a)) => (n, Some(a)),
+            None => (s.trim(), None),
+        };
+        match (name, arg) {
+            ("run-id", None) => Ok(AssignStrategy::RunId),
+            ("session-key", None) => Ok(AssignStrategy::SessionKey),
+            ("chat", None) => Ok(AssignStrategy::Chat),
+            ("seq-window", Some(n)) => {
+                let window: u64 = n
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid window in assign strategy '{}'", s))?;
+                if window == 0 {
+                    return Err(anyhow!("Window must be greater than 0 in '{}'", s));
+                }
+                Ok(AssignStrategy::SeqWindow { window })
+            }
+            ("seq-window", None) => Err(anyhow!("Assign strategy 'seq-window' requires :N")),
+            _ => Err(anyhow!(
+                "Unknown assign strategy '{}'. Use run-id, session-key, chat or seq-window:N",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for AssignStrategy {
+    /// Inverse of `FromStr`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssignStrategy::RunId => write!(f, "run-id"),
+            AssignStrategy::SessionKey => write!(f, "session-key"),
+            AssignStrategy::Chat => write!(f, "chat"),
+            AssignStrategy::SeqWindow { window } => write!(f, "seq-window:{}", window),
+        }
+    }
+}
+
+/// Ordered list of strategies; the first one that yields a run wins.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssignPolicy {
+    pub strategies: Vec<AssignStrategy>,
+}
+
+impl Default for AssignPolicy {
+    /// `data.runId` only
+    fn default() -> Self {
+        Self {
+            strategies: vec![AssignStrategy::RunId],
+        }
+    }
+}
+
+impl AssignPolicy {
+    /// `data.runId`, then the session, then chat output to the active run.
+    pub fn correlated() -> Self {
+        Self {
+            strategies: vec![
+                AssignStrategy::RunId,
+                AssignStrategy::SessionKey,
+                AssignStrategy::Chat,
+            ],
+        }
+    }
+
+    /// Build a policy from strategies; an empty list means the default.
+    pub fn new(strategies: Vec<AssignStrategy>) -> Self {
+        if strategies.is_empty() {
+            Self::default()
+        } else {
+            Self { strategies }
+        }
+    }
+}
+
+/// What the assigner remembers between events
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AssignState {
+    /// sessionKey -> latest runId seen with it, while that run is open
+    pub sessions: HashMap<String, String>,
+    /// Run whose lifecycle has started but not ended
+    pub active_run: Option<String>,
+    /// (seq, run) of the last event attributed by a non-window strategy
+    pub last_seq: Option<(u64, String)>,
+}
+
+/// Stateful assigner. Feed it events in ledger order.
+#[derive(Debug, Clone, Default)]
+pub struct RunAssigner {
+    policy: AssignPolicy,
+    state: AssignState,
+    /// Runs whose lifecycle ended since the last `take_completed`
+    completed: Vec<String>,
+}
+
+impl RunAssigner {
+    pub fn new(policy: AssignPolicy) -> Self {
+        Self {
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn policy(&self) -> &AssignPolicy {
+        &self.policy
+    }
+
+    /// Switch strategies, keeping what has been seen so far.
+    pub fn set_policy(&mut self, policy: AssignPolicy) {
+        self.policy = policy;
+    }
+
+    pub fn state(&self) -> &AssignState {
+        &self.state
+    }
+
+    /// Continue from a saved state, e.g. after a restart.
+    pub fn restore(&mut self, state: AssignState) {
+        self.state = state;
+    }
+
+    /// Derive the agent run for `event`, updating the assigner's state.
+    pub fn assign(&mut self, event: &Event) -> Option<String> {
+        let data = event.payload.get("data");
+        let field = |name: &str| data.and_then(|d| d.get(name)).and_then(|v| v.as_str());
+        let explicit = field("runId");
+        let session = field("sessionKey");
+        let seq = event
+            .span_id
+            .as_deref()
+            .and_then(|s| s.strip_prefix("seq:"))
+            .and_then(|s| s.parse::<u64>().ok());
+
+        if let Some(run) = explicit {
+            if let Some(session) = session {
+                self.state
+                    .sessions
+                    .insert(session.to_string(), run.to_string());
+            }
+            self.state.active_run = Some(run.to_string());
+        }
+
+        let mut assigned = None;
+        let mut by_window = false;
+        for strategy in &self.policy.strategies {
+            assigned = match strategy {
+                AssignStrategy::RunId => explicit.map(str::to_string),
+                AssignStrategy::SessionKey => {
+                    session.and_then(|s| self.state.sessions.get(s).cloned())
+                }
+                AssignStrategy::Chat => {
+                    if event.kind == EventKind::OutputChunk {
+                        self.state.active_run.clone()
+                    } else {
+                        None
+                    }
+                }
+                AssignStrategy::SeqWindow { window } => match (seq, &self.state.last_seq) {
+                    (Some(seq), Some((last, run))) if seq.saturating_sub(*last) <= *window => {
+                        by_window = true;
+                        Some(run.clone())
+                    }
+                    _ => None,
+                },
+            };
+            if assigned.is_some() {
+                break;
+            }
+        }
+
+        if let (Some(seq), Some(run)) = (seq, &assigned)
+            && !by_window
+        {
+            self.state.last_seq = Some((seq, run.clone()));
+        }
+
+        // A finished run no longer collects chat or session events
+        let phase = data
+            .and_then(|d| d.pointer("/data/phase"))
+            .and_then(|v| v.as_str());
+        if field("stream") == Some("lifecycle")
+            && matches!(phase, Some("end") | Some("error"))
+            && let Some(run) = explicit
+        {
+            if self.state.active_run.as_deref() == Some(run) {
+                self.state.active_run = None;
+            }
+            self.state.sessions.retain(|_, r| r != run);
+            self.completed.push(run.to_string());
+        }
+
+        assigned
+    }
+
+    /// Runs whose lifecycle ended since the last call, in completion order.
+    pub fn take_completed(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.completed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventId, RunId};
+
+    fn ev(kind: EventKind, seq: u64, data: serde_json::Value) -> Event {
+        let mut e = Event::new(
+            RunId("daemon".into()),
+            EventId(seq),
+            kind,
+            serde_json::json!({ "data": data }),
+            None,
+        );
+        e.span_id = Some(format!("seq:{}", seq));
+        e
+    }
+
+    #[test]
+    fn test_parse_strategies() {
+        assert_eq!(
+            "run-id".parse::<AssignStrategy>().unwrap(),
+            AssignStrategy::RunId
+        );
+        assert_eq!(
+            "seq-window:5".parse::<AssignStrategy>().unwrap(),
+            AssignStrategy::SeqWindow { window: 5 }
+        );
+        assert!("seq-window".parse::<AssignStrategy>().is_err());
+        assert!("seq-window:0".parse::<AssignStrategy>().is_err());
+        assert!("bogus".parse::<AssignStrategy>().is_err());
+        assert_eq!(AssignPolicy::new(vec![]), AssignPolicy::default());
+        assert_eq!(AssignPolicy::default().strategies, [AssignStrategy::RunId]);
+    }
+
+    #[test]
+    fn test_correlated_policy_attributes_session_and_chat() {
+        let mut a = RunAssigner::new(AssignPolicy::correlated());
+        let start = ev(
+            EventKind::AgentEvent,
+            1,
+            serde_json::json!({"runId": "r1", "sessionKey": "main", "stream": "lifecycle", "data": {"phase": "start"}}),
+        );
+        assert_eq!(a.assign(&start).as_deref(), Some("r1"));
+
+        let by_session = ev(
+            EventKind::Custom,
+            2,
+            serde_json::json!({"sessionKey": "main"}),
+        );
+        assert_eq!(a.assign(&by_session).as_deref(), Some("r1"));
+
+        let chat = ev(
+            EventKind::OutputChunk,
+            3,
+            serde_json::json!({"state": "final", "text": "hi"}),
+        );
+        assert_eq!(a.assign(&chat).as_deref(), Some("r1"));
+
+        // Heartbeats are not attributed without a seq window
+        let tick = ev(EventKind::Tick, 4, serde_json::json!({}));
+        assert_eq!(a.assign(&tick), None);
+
+        let end = ev(
+            EventKind::AgentEvent,
+            5,
+            serde_json::json!({"runId": "r1", "stream": "lifecycle", "data": {"phase": "end"}}),
+        );
+        assert_eq!(a.assign(&end).as_deref(), Some("r1"));
+
+        let late_chat = ev(EventKind::OutputChunk, 6, serde_json::json!({"text": "x"}));
+        assert_eq!(a.assign(&late_chat), None);
+
+        assert_eq!(a.take_completed(), vec!["r1".to_string()]);
+        assert!(a.take_completed().is_empty());
+    }
+
+    #[test]
+    fn test_default_policy_is_run_id_only() {
+        let mut a = RunAssigner::new(AssignPolicy::default());
+        a.assign(&ev(
+            EventKind::AgentEvent,
+            1,
+            serde_json::json!({"runId": "r1", "sessionKey": "main"}),
+        ));
+        let chat = ev(
+            EventKind::OutputChunk,
+            2,
+            serde_json::json!({"sessionKey": "main", "text": "hi"}),
+        );
+        assert_eq!(a.assign(&chat), None);
+    }
+
+    #[test]
+    fn test_restored_state_continues_session() {
+        let mut a = RunAssigner::new(AssignPolicy::correlated());
+        a.assign(&ev(
+            EventKind::AgentEvent,
+            1,
+            serde_json::json!({"runId": "r1", "sessionKey": "main"}),
+        ));
+        let saved = serde_json::to_string(a.state()).unwrap();
+
+        let mut b = RunAssigner::new(AssignPolicy::correlated());
+        b.restore(serde_json::from_str(&saved).unwrap());
+        let chat = ev(EventKind::OutputChunk, 2, serde_json::json!({"text": "hi"}));
+        assert_eq!(b.assign(&chat).as_deref(), Some("r1"));
+    }
+
+    #[test]
+    fn test_sessions_forgotten_when_run_ends() {
+        let mut a = RunAssigner::new(AssignPolicy::correlated());
+        for (seq, run, session) in [(1, "r1", "s1"), (2, "r2", "s2")] {
+            a.assign(&ev(
+                EventKind::AgentEvent,
+                seq,
+                serde_json::json!({"runId": run, "sessionKey": session}),
+            ));
+        }
+        a.assign(&ev(
+            EventKind::AgentEvent,
+            3,
+            serde_json::json!({"runId": "r1", "stream": "lifecycle", "data": {"phase": "end"}}),
+        ));
+        assert_eq!(a.state().sessions.len(), 1);
+        assert_eq!(a.state().sessions["s2"], "r2");
+
+        let late = ev(
+            EventKind::Custom,
+            4,
+            serde_json::json!({"sessionKey": "s1"}),
+        );
+        assert_eq!(a.assign(&late), None);
+    }
+
+    #[test]
+    fn test_seq_window() {
+        let mut a = RunAssigner::new(AssignPolicy::new(vec![
+            AssignStrategy::RunId,
+            AssignStrategy::SeqWindow { window: 2 },
+        ]));
+        a.assign(&ev(
+            EventKind::AgentEvent,
+            10,
+            serde_json::json!({"runId": "r1"}),
+        ));
+        assert_eq!(
+            a.assign(&ev(EventKind::Presence, 11, serde_json::json!({})))
+                .as_deref(),
+            Some("r1")
+        );
+        // Window is measured from the last explicitly attributed event
+        assert_eq!(
+            a.assign(&ev(EventKind::Presence, 12, serde_json::json!({})))
+                .as_deref(),
+            Some("r1")
+        );
+        assert_eq!(
+            a.assign(&ev(EventKind::Presence, 13, serde_json::json!({}))),
+            None
+        );
+    }
+}
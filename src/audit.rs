@@ -0,0 +1,201 @@
+//! Self-audit — Clawprint's own actions, recorded as evidence
+//!
+//! Daemon start and stop, gateway connections, chain verifications, exports
+//! and every read of the evidence through the viewer or MCP are written to
+//! the ledger as `AUDIT` events, so "who looked at this and when" sits in
+//! the same hash chain as what the agents did.
+//!
+//! Only the ledger writer may append (see [`crate::lock`]). Inside the
+//! recording process, an [`AuditLog`] queues entries for the daemon. CLI
+//! commands use [`record_standalone`], which appends only when no daemon
+//! holds the ledger. Every entry is also logged under the
+//! `clawprint::audit` target.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::ledger::Ledger;
+use crate::lock::LedgerLocked;
+use crate::{Event, EventId, EventKind, RunId};
+
+/// Entries queued before the daemon writes them; beyond this they are only logged
+const QUEUE_CAPACITY: usize = 1024;
+
+/// One audited action.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AuditEntry {
+    /// What happened, e.g. "daemon_start", "viewer_access", "mcp_tool"
+    pub action: &'static str,
+    /// Who did it, e.g. "daemon", "viewer 10.0.0.5", "mcp", "cli"
+    pub actor: String,
+    pub at: DateTime<Utc>,
+    pub detail: serde_json::Value,
+}
+
+impl AuditEntry {
+    pub fn new(action: &'static str, actor: impl Into<String>, detail: serde_json::Value) -> Self {
+        Self {
+            action,
+            actor: actor.into(),
+            at: Utc::now(),
+            detail,
+        }
+    }
+
+    /// Build the AUDIT event recording this entry in the chain.
+    pub fn to_event(&self, run_id: &RunId, event_id: EventId) -> Event {
+        let payload = serde_json::to_value(self).expect("audit entry must be JSON-serializable");
+        let mut event = Event::new(run_id.clone(), event_id, EventKind::Audit, payload, None);
+        event.actor = Some(self.actor.clone());
+        event
+    }
+}
+
+/// Cloneable handle that queues audit entries for the ledger writer.
+/// The default handle only logs.
+#[derive(Clone, Default)]
+pub struct AuditLog {
+    tx: Option<mpsc::Sender<AuditEntry>>,
+}
+
+impl AuditLog {
+    /// Create a handle and the receiver to pass to the daemon.
+    pub fn channel() -> (Self, mpsc::Receiver<AuditEntry>) {
+        let (tx, rx) = mpsc::channel(QUEUE_CAPACITY);
+        (Self { tx: Some(tx) }, rx)
+    }
+
+    /// True if entries reach the ledger, not just the log.
+    pub fn is_recording(&self) -> bool {
+        self.tx.as_ref().is_some_and(|tx| !tx.is_closed())
+    }
+
+    pub fn record(
+        &self,
+        action: &'static str,
+        actor: impl Into<String>,
+        detail: serde_json::Value,
+    ) {
+        let entry = AuditEntry::new(action, actor, detail);
+        log_entry(&entry);
+        if let Some(tx) = &self.tx
+            && let Err(mpsc::error::TrySendError::Full(entry)) = tx.try_send(entry)
+        {
+            warn!(
+                "Audit queue full; {} by {} was only logged",
+                entry.action, entry.actor
+            );
+        }
+    }
+}
+
+/// Append `entry` to the ledger in `dir` from a CLI command. Returns false
+/// (the entry is only logged) if there is no ledger or a daemon is writing it.
+pub fn record_standalone(dir: &Path, entry: AuditEntry) -> Result<bool> {
+    log_entry(&entry);
+    if !dir.join("ledger.sqlite").exists() {
+        return Ok(false);
+    }
+    let mut ledger = match Ledger::open(dir, 1) {
+        Ok(ledger) => ledger,
+        Err(e) if e.downcast_ref::<LedgerLocked>().is_some() => {
+            info!("Ledger is in use; audit entry {} only logged", entry.action);
+            return Ok(false);
+        }
+        Err(e) => return Err(e),
+    };
+    ledger.append_event(entry.to_event(&RunId("daemon".to_string()), EventId(0)))?;
+    ledger.flush()?;
+    Ok(true)
+}
+
+fn log_entry(entry: &AuditEntry) {
+    info!(
+        target: "clawprint::audit",
+        action = entry.action,
+        actor = %entry.actor,
+        detail = %entry.detail,
+        "audit"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export_entry() -> AuditEntry {
+        AuditEntry::new("export", "cli", serde_json::json!({"run": "r1"}))
+    }
+
+    #[tokio::test]
+    async fn test_queued_entry_reaches_writer() {
+        let (audit, mut rx) = AuditLog::channel();
+        assert!(audit.is_recording());
+        audit.record(
+            "viewer_access",
+            "viewer 127.0.0.1",
+            serde_json::json!({"path": "/api/runs"}),
+        );
+        let entry = rx.recv().await.unwrap();
+        assert_eq!(entry.action, "viewer_access");
+        assert_eq!(entry.actor, "viewer 127.0.0.1");
+    }
+
+    #[test]
+    fn test_log_only_handles_are_not_recording() {
+        assert!(!AuditLog::default().is_recording());
+
+        let (audit, rx) = AuditLog::channel();
+        drop(rx);
+        assert!(!audit.is_recording());
+        // Still safe to call: the entry is only logged
+        audit.record("mcp_tool", "mcp", serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_full_queue_drops_instead_of_blocking() {
+        let (audit, mut rx) = AuditLog::channel();
+        for _ in 0..QUEUE_CAPACITY + 5 {
+            audit.record("viewer_access", "viewer", serde_json::json!({}));
+        }
+        let mut queued = 0;
+        while rx.try_recv().is_ok() {
+            queued += 1;
+        }
+        assert_eq!(queued, QUEUE_CAPACITY);
+    }
+
+    #[test]
+    fn test_standalone_without_ledger_only_logs() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(!record_standalone(dir.path(), export_entry()).unwrap());
+        assert!(!dir.path().join("ledger.sqlite").exists());
+    }
+
+    #[test]
+    fn test_standalone_skips_locked_ledger() {
+        let dir = tempfile::tempdir().unwrap();
+        let _daemon = Ledger::open(dir.path(), 10).unwrap();
+        assert!(!record_standalone(dir.path(), export_entry()).unwrap());
+    }
+
+    #[test]
+    fn test_standalone_appends_audit_event() {
+        let dir = tempfile::tempdir().unwrap();
+        drop(Ledger::open(dir.path(), 10).unwrap());
+
+        assert!(record_standalone(dir.path(), export_entry()).unwrap());
+        let ledger = Ledger::open_readonly(dir.path()).unwrap();
+        let events = ledger
+            .search_events("", Some("AUDIT"), None, None, 10)
+            .unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].actor.as_deref(), Some("cli"));
+        assert_eq!(events[0].payload["action"], "export");
+        assert!(ledger.verify_chain().unwrap().0);
+    }
+}
@@ -2,21 +2,33 @@
 //!
 //! Unlike `record` (session-based, Ctrl+C to stop), the daemon runs forever,
 //! auto-reconnects on disconnect, and writes to a single growing ledger.
+//! It never prints; progress goes to the [`Observer`] in [`DaemonOptions`].
 
 use anyhow::Result;
-use indicatif::{ProgressBar, ProgressStyle};
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
-use tokio::sync::Mutex;
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinSet;
 use tokio::time::interval;
 use tracing::{error, info, warn};
 
 use crate::{
     Config, EventId, RunId,
+    audit::{AuditEntry, AuditLog},
+    filter::{Admission, IngestFilter},
     gateway::{GatewayClient, GatewayEvent},
-    ledger::Ledger,
+    ledger::{ChainCheck, Ledger, VERIFY_CHECKPOINT_KEY, VerifyCheckpoint},
+    metrics::DaemonMetrics,
+    progress::{Headless, Observer, Progress},
     record::gateway_event_to_event,
+    redact::Redactor,
+    reload::{ConfigChange, ReloadRequest, config_hash},
+    sessionize::{self, CaseFileJob, Sessionizer},
+    sink::{self, Sink},
+    subscribe::EventBus,
+    systemd::Notifier,
+    vault::{self, RevealRequest},
 };
 
 /// Run the daemon: connect to gateway, record to ledger, auto-reconnect.
@@ -31,35 +43,171 @@ pub async fn run_daemon(config: Config) -> Result<()> {
 }
 
 /// Run the daemon with an external cancellation token for coordinated shutdown.
+///
+/// Under systemd (`Type=notify`), reports readiness and status and pings the
+/// watchdog from the recording loop.
 pub async fn run_daemon_with_shutdown(
     config: Config,
     ct: tokio_util::sync::CancellationToken,
 ) -> Result<()> {
-    let ledger_path = config.output_dir.clone();
-    let ledger = Ledger::open(&ledger_path, config.batch_size)?;
-    let ledger = Arc::new(Mutex::new(ledger));
+    run_daemon_with_options(config, ct, DaemonOptions::default()).await
+}
 
+/// How often the ledger hash chain is re-verified for metrics
+pub const VERIFY_INTERVAL: Duration = Duration::from_secs(600);
+
+/// Optional hooks into a running daemon.
+pub struct DaemonOptions {
+    /// Configuration reloads (see [`crate::reload::ReloadHandle`])
+    pub reload: Option<mpsc::Receiver<ReloadRequest>>,
+    /// Reveals sent through the admin API (see [`crate::vault::RevealHandle`])
+    pub reveal: Option<mpsc::Receiver<RevealRequest>>,
+    /// Metrics updated by the recording loop
+    pub metrics: Arc<DaemonMetrics>,
+    /// Audit queue shared with the viewer, MCP and admin API (see
+    /// [`AuditLog::channel`]); the daemon creates its own if not given
+    pub audit: Option<(AuditLog, mpsc::Receiver<AuditEntry>)>,
+    /// Progress reporting; [`Headless`] by default
+    pub observer: Arc<dyn Observer>,
+    /// Receives every event committed to the ledger
+    pub events: EventBus,
+    /// Sinks to feed in addition to those in [`Config::sinks`]
+    pub sinks: Vec<Box<dyn Sink>>,
+}
+
+impl Default for DaemonOptions {
+    fn default() -> Self {
+        Self {
+            reload: None,
+            reveal: None,
+            metrics: Arc::default(),
+            audit: None,
+            observer: Arc::new(Headless),
+            events: EventBus::new(),
+            sinks: Vec::new(),
+        }
+    }
+}
+
+/// Run the daemon with reload and metrics hooks.
+pub async fn run_daemon_with_options(
+    config: Config,
+    ct: tokio_util::sync::CancellationToken,
+    options: DaemonOptions,
+) -> Result<()> {
+    let DaemonOptions {
+        reload,
+        reveal,
+        metrics,
+        audit,
+        observer,
+        events,
+        sinks,
+    } = options;
+    // Fallible setup first, so an error leaves no tasks behind
     let auth_token = config.auth_token.clone()
         .ok_or_else(|| anyhow::anyhow!(
             "No auth token provided. Use --token or set gateway.auth.token in ~/.openclaw/openclaw.json"
         ))?;
+    let redactor = new_redactor(&config)?;
+    let mut configured = sink::build_all(&config.sinks)?;
+    configured.extend(sinks);
+    let (audit, audit_rx) = audit.unwrap_or_else(AuditLog::channel);
+    let ledger_path = config.output_dir.clone();
+    let mut ledger = Ledger::open_with_spool(&ledger_path, config.batch_size, config.spool_sync)?;
+    ledger.set_assign_policy(config.assign.clone());
+    ledger.set_metrics(metrics.clone());
+    ledger.set_events(events.clone());
+    metrics.set_flush_interval(config.flush_interval_ms);
+    metrics.set_ledger(
+        ledger.total_events(),
+        ledger.storage_size_bytes().unwrap_or(0),
+    );
+    let ledger = Arc::new(Mutex::new(ledger));
+    // Sinks outlive the recording loop so they see the final flush. Both
+    // guards stop the background tasks if the daemon returns early with an
+    // error; the sinks would otherwise keep the ledger (and its lock) open.
+    let sinks_ct = tokio_util::sync::CancellationToken::new();
+    let _sinks_guard = sinks_ct.clone().drop_guard();
+    let verifier_ct = ct.child_token();
+    let _verifier_guard = verifier_ct.clone().drop_guard();
+    let sink_tasks: Vec<_> = configured
+        .into_iter()
+        .map(|s| {
+            let name = s.name().to_string();
+            let task = sink::run_sink(
+                s,
+                ledger_path.clone(),
+                ledger.clone(),
+                events.stream(),
+                sinks_ct.clone(),
+            );
+            tokio::spawn(async move {
+                if let Err(e) = task.await {
+                    error!("Sink {} stopped: {:#}", name, e);
+                }
+            })
+        })
+        .collect();
+    spawn_verifier(
+        ledger_path.clone(),
+        ledger.clone(),
+        metrics.clone(),
+        audit.clone(),
+        verifier_ct,
+    );
 
     // Store start time in meta
     {
         let l = ledger.lock().await;
         l.set_meta("daemon_started_at", &chrono::Utc::now().to_rfc3339())?;
         l.set_meta("gateway_url", &config.gateway_url)?;
+        l.set_meta("config_hash", &config_hash(&config))?;
     }
-
-    // Progress spinner on stderr
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .unwrap(),
+    audit.record(
+        "daemon_start",
+        "daemon",
+        serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "pid": std::process::id(),
+            "gateway_url": config.gateway_url,
+            "config_hash": config_hash(&config),
+        }),
     );
-    pb.enable_steady_tick(Duration::from_millis(100));
-    pb.set_message("Connecting to gateway...");
+
+    // Record the ingest filter policy in the chain so omissions are auditable
+    if !config.filter.is_empty() {
+        let mut l = ledger.lock().await;
+        l.append_event(
+            config
+                .filter
+                .to_event(&RunId("daemon".to_string()), EventId(0)),
+        )?;
+        l.set_meta("filter_policy_hash", &config.filter.hash())?;
+        info!("Ingest filter active ({} rules)", config.filter.rules.len());
+    }
+
+    let mut state = DaemonState {
+        filter: IngestFilter::new(config.filter.clone()),
+        redactor,
+        // Optionally copy each completed agent run into its own case file
+        sessionizer: config.sessionize.then(|| new_sessionizer(&config)),
+        case_files: JoinSet::new(),
+        config,
+        auth_token,
+        reload,
+        reveal,
+        metrics,
+        notify: Notifier::from_env(),
+        audit,
+        audit_rx,
+        observer,
+    };
+    if let Some(w) = state.notify.watchdog() {
+        info!("systemd watchdog enabled ({:?})", w);
+    }
+
+    state.notify.ready(&Progress::Connecting.to_string());
 
     let mut backoff = Duration::from_secs(1);
     let max_backoff = Duration::from_secs(60);
@@ -80,43 +228,40 @@ pub async fn run_daemon_with_shutdown(
             break;
         }
 
-        match run_connection(
-            &config.gateway_url,
-            &auth_token,
-            config.redact_secrets,
-            config.flush_interval_ms,
-            ledger.clone(),
-            &pb,
-            &shutdown,
-        )
-        .await
-        {
+        state.report(&Progress::Connecting);
+        let outcome = run_connection(&mut state, ledger.clone(), &shutdown).await;
+        state.metrics.set_connected(false);
+        if !matches!(outcome, Ok(ShutdownReason::Signal)) {
+            state.metrics.record_reconnect();
+            let (action, detail) = match &outcome {
+                Ok(ShutdownReason::Reconfigured) => ("gateway_reconnect", serde_json::json!({})),
+                Err(e) => (
+                    "gateway_error",
+                    serde_json::json!({ "error": e.to_string() }),
+                ),
+                _ => ("gateway_disconnected", serde_json::json!({})),
+            };
+            state.audit.record(action, "daemon", detail);
+        }
+        match outcome {
             Ok(ShutdownReason::Signal) => {
                 break;
             }
+            Ok(ShutdownReason::Reconfigured) => {
+                // Gateway settings changed — reconnect right away
+                backoff = Duration::from_secs(1);
+                state.report(&Progress::Reconfigured);
+            }
             Ok(ShutdownReason::Disconnected) => {
                 // Connection was established then lost — reset backoff
                 backoff = Duration::from_secs(1);
                 warn!("Gateway disconnected, reconnecting in {:?}...", backoff);
-                pb.set_message(format!(
-                    "Disconnected. Reconnecting in {}s...",
-                    backoff.as_secs()
-                ));
-
-                // Wait for backoff duration, but check shutdown periodically
-                let sleep_until = tokio::time::Instant::now() + backoff;
-                loop {
-                    if shutdown.load(Ordering::SeqCst) {
-                        break;
-                    }
-                    let remaining =
-                        sleep_until.saturating_duration_since(tokio::time::Instant::now());
-                    if remaining.is_zero() {
-                        break;
-                    }
-                    tokio::time::sleep(remaining.min(Duration::from_millis(200))).await;
-                }
+                state.report(&Progress::Reconnecting {
+                    after: backoff,
+                    error: None,
+                });
 
+                wait_backoff(backoff, &mut state, &ledger, &shutdown).await;
                 if shutdown.load(Ordering::SeqCst) {
                     break;
                 }
@@ -126,21 +271,12 @@ pub async fn run_daemon_with_shutdown(
             }
             Err(e) => {
                 warn!("Connection error: {}. Reconnecting in {:?}...", e, backoff);
-                pb.set_message(format!("Error. Reconnecting in {}s...", backoff.as_secs()));
-
-                let sleep_until = tokio::time::Instant::now() + backoff;
-                loop {
-                    if shutdown.load(Ordering::SeqCst) {
-                        break;
-                    }
-                    let remaining =
-                        sleep_until.saturating_duration_since(tokio::time::Instant::now());
-                    if remaining.is_zero() {
-                        break;
-                    }
-                    tokio::time::sleep(remaining.min(Duration::from_millis(200))).await;
-                }
+                state.report(&Progress::Reconnecting {
+                    after: backoff,
+                    error: Some(&e.to_string()),
+                });
 
+                wait_backoff(backoff, &mut state, &ledger, &shutdown).await;
                 if shutdown.load(Ordering::SeqCst) {
                     break;
                 }
@@ -151,44 +287,379 @@ pub async fn run_daemon_with_shutdown(
     }
 
     // Graceful shutdown: flush and record stop time
-    pb.finish_and_clear();
+    state.notify.stopping();
     info!("Daemon shutting down gracefully");
+    while state.case_files.join_next().await.is_some() {}
 
     let mut l = ledger.lock().await;
+    let run_id = RunId("daemon".to_string());
+    state.audit.record(
+        "daemon_stop",
+        "daemon",
+        serde_json::json!({ "events": l.total_events() }),
+    );
+    state.drain_audit(&mut l);
+    for summary in state.filter.drain() {
+        l.append_event(summary.to_event(&run_id, EventId(0)))?;
+    }
     l.flush()?;
+    if let Some(s) = state.sessionizer.as_mut() {
+        s.drain(&mut l);
+    }
     l.set_meta("daemon_stopped_at", &chrono::Utc::now().to_rfc3339())?;
 
-    let total = l.total_events();
-    let size = l.storage_size_bytes().unwrap_or(0);
-    eprintln!(
-        "  Daemon stopped. {} events recorded, {} on disk.",
-        total,
-        format_bytes(size),
-    );
+    state.observer.on_progress(&Progress::Stopped {
+        total_events: l.total_events(),
+        size_bytes: l.storage_size_bytes().unwrap_or(0),
+    });
+    drop(l);
+
+    // Sinks deliver the final flush, then anything left waits for the next start
+    sinks_ct.cancel();
+    for task in sink_tasks {
+        let _ = task.await;
+    }
     Ok(())
 }
 
 enum ShutdownReason {
     Signal,
     Disconnected,
+    Reconfigured,
+}
+
+/// Daemon state that survives reconnects and may change on reload.
+struct DaemonState {
+    config: Config,
+    auth_token: String,
+    filter: IngestFilter,
+    /// None while redaction is off
+    redactor: Option<Redactor>,
+    sessionizer: Option<Sessionizer>,
+    /// Case files being written from ledger snapshots
+    case_files: JoinSet<()>,
+    reload: Option<mpsc::Receiver<ReloadRequest>>,
+    reveal: Option<mpsc::Receiver<RevealRequest>>,
+    metrics: Arc<DaemonMetrics>,
+    notify: Notifier,
+    audit: AuditLog,
+    audit_rx: mpsc::Receiver<AuditEntry>,
+    observer: Arc<dyn Observer>,
+}
+
+fn new_redactor(config: &Config) -> Result<Option<Redactor>> {
+    config
+        .redact_secrets
+        .then(|| Redactor::new(&config.redaction)?.with_vault(&config.output_dir))
+        .transpose()
+}
+
+fn new_sessionizer(config: &Config) -> Sessionizer {
+    Sessionizer::new(
+        &config.output_dir,
+        &config.gateway_url,
+        config.batch_size,
+        sessionize::DEFAULT_GRACE,
+    )
+}
+
+impl DaemonState {
+    /// Tell the observer and, for state changes, systemd.
+    fn report(&self, progress: &Progress<'_>) {
+        self.observer.on_progress(progress);
+        if !matches!(progress, Progress::Event { .. }) {
+            self.notify.status(&progress.to_string());
+        }
+    }
+
+    /// Write queued audit entries to the ledger.
+    fn drain_audit(&mut self, ledger: &mut Ledger) {
+        while let Ok(entry) = self.audit_rx.try_recv() {
+            write_audit(ledger, entry);
+        }
+    }
+
+    /// Apply a reloaded configuration and record it in the ledger.
+    /// Returns true if the gateway connection must be re-established.
+    fn apply_reload(&mut self, ledger: &mut Ledger, req: ReloadRequest) -> bool {
+        let ReloadRequest {
+            config: new,
+            source,
+            reply,
+        } = req;
+        let outcome = self.try_apply(ledger, new, source);
+        let reconnect = match &outcome {
+            Ok(change) => {
+                info!(
+                    "Configuration reloaded via {} (applied: {:?}, reconnect: {:?}, needs restart: {:?})",
+                    source, change.applied, change.reconnect, change.restart_required
+                );
+                !change.reconnect.is_empty()
+            }
+            Err(e) => {
+                warn!("Rejected configuration reload via {}: {}", source, e);
+                false
+            }
+        };
+        if let Some(reply) = reply {
+            let _ = reply.send(outcome);
+        }
+        reconnect
+    }
+
+    fn try_apply(
+        &mut self,
+        ledger: &mut Ledger,
+        mut new: Config,
+        source: &str,
+    ) -> Result<ConfigChange> {
+        let auth_token = new
+            .auth_token
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("new configuration has no gateway auth token"))?;
+
+        let mut change = ConfigChange::between(&self.config, &new, source);
+        // Compile before changing anything, so bad rules reject the reload
+        let redactor = new_redactor(&new)?;
+
+        // Storage layout can't change under an open ledger; those fields keep
+        // their running values and the hash describes what is in effect
+        new.output_dir = self.config.output_dir.clone();
+        new.batch_size = self.config.batch_size;
+        new.spool_sync = self.config.spool_sync;
+        // Sink tasks are started once; a change needs a restart
+        new.sinks = self.config.sinks.clone();
+        change.config_hash = config_hash(&new);
+
+        if change.is_empty() {
+            return Ok(change);
+        }
+
+        let run_id = RunId("daemon".to_string());
+
+        // Hand completed runs to the sessionizer before the assigner is reset
+        if let Some(s) = self.sessionizer.as_mut() {
+            ledger.flush()?;
+            s.collect(ledger, std::time::Instant::now());
+        }
+
+        if new.filter != self.config.filter {
+            for summary in self.filter.drain() {
+                ledger.append_event(summary.to_event(&run_id, EventId(0)))?;
+            }
+            self.filter = IngestFilter::new(new.filter.clone());
+            ledger.append_event(new.filter.to_event(&run_id, EventId(0)))?;
+            ledger.set_meta("filter_policy_hash", &new.filter.hash())?;
+        }
+
+        self.redactor = redactor;
+
+        if new.assign != self.config.assign {
+            ledger.flush()?;
+            ledger.set_assign_policy(new.assign.clone());
+        }
+
+        match (self.sessionizer.as_mut(), new.sessionize) {
+            (Some(s), false) => {
+                ledger.flush()?;
+                s.drain(ledger);
+                self.sessionizer = None;
+            }
+            (None, true) => self.sessionizer = Some(new_sessionizer(&new)),
+            _ => {}
+        }
+
+        ledger.append_event(change.to_event(&run_id, EventId(0)))?;
+        ledger.flush()?;
+        ledger.set_meta("config_hash", &change.config_hash)?;
+        ledger.set_meta("gateway_url", &new.gateway_url)?;
+
+        self.metrics.set_flush_interval(new.flush_interval_ms);
+        self.auth_token = auth_token;
+        self.config = new;
+        Ok(change)
+    }
+}
+
+/// Wait before reconnecting. Returns early on shutdown or after a reload,
+/// so a corrected gateway URL or token is tried immediately.
+async fn wait_backoff(
+    backoff: Duration,
+    state: &mut DaemonState,
+    ledger: &Arc<Mutex<Ledger>>,
+    shutdown: &Arc<AtomicBool>,
+) {
+    state.metrics.set_backoff(backoff);
+    let sleep_until = tokio::time::Instant::now() + backoff;
+    loop {
+        if shutdown.load(Ordering::SeqCst) {
+            break;
+        }
+        if !state.audit_rx.is_empty() {
+            state.drain_audit(&mut *ledger.lock().await);
+        }
+        if let Some(req) = state.reveal.as_mut().and_then(|rx| rx.try_recv().ok()) {
+            handle_reveal(&mut *ledger.lock().await, req);
+        }
+        if let Some(req) = state.reload.as_mut().and_then(|rx| rx.try_recv().ok()) {
+            let mut l = ledger.lock().await;
+            state.apply_reload(&mut l, req);
+            break;
+        }
+        let remaining = sleep_until.saturating_duration_since(tokio::time::Instant::now());
+        if remaining.is_zero() {
+            break;
+        }
+        state.notify.heartbeat(&format!(
+            "Disconnected. Reconnecting in {}s | {} events recorded",
+            remaining.as_secs_f64().ceil(),
+            state.metrics.ledger_events()
+        ));
+        tokio::time::sleep(remaining.min(Duration::from_millis(200))).await;
+    }
+}
+
+/// Re-verify the ledger hash chain every `VERIFY_INTERVAL` on a separate
+/// read-only connection, so the writer is never blocked. Each pass resumes
+/// from the checkpoint in `meta` and only checks events appended since.
+/// Failures and status changes are audited; repeated successes are not.
+fn spawn_verifier(
+    ledger_path: std::path::PathBuf,
+    ledger: Arc<Mutex<Ledger>>,
+    metrics: Arc<DaemonMetrics>,
+    audit: AuditLog,
+    ct: tokio_util::sync::CancellationToken,
+) {
+    tokio::spawn(async move {
+        let mut every = interval(VERIFY_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = every.tick() => {
+                    let path = ledger_path.clone();
+                    let result = tokio::task::spawn_blocking(move || verify_since_checkpoint(&path)).await;
+                    match result {
+                        Ok(Ok((previous, check))) => {
+                            if !check.valid {
+                                error!("Ledger hash chain verification FAILED ({} events checked)", check.checked);
+                            }
+                            metrics.set_verification(check.valid, chrono::Utc::now());
+                            if !check.valid || previous.as_ref().map(|p| p.valid) != Some(true) {
+                                audit.record(
+                                    "verification",
+                                    "daemon",
+                                    serde_json::json!({
+                                        "valid": check.valid,
+                                        "checked": check.checked,
+                                        "from_event": previous.as_ref().map(|p| p.event_id),
+                                    }),
+                                );
+                            }
+                            let next = next_checkpoint(previous.as_ref(), &check);
+                            if next.as_ref() != previous.as_ref()
+                                && let Some(next) = next
+                            {
+                                let json = serde_json::to_string(&next).expect("checkpoint must be JSON-serializable");
+                                if let Err(e) = ledger.lock().await.set_meta(VERIFY_CHECKPOINT_KEY, &json) {
+                                    warn!("Could not save verification checkpoint: {}", e);
+                                }
+                            }
+                        }
+                        Ok(Err(e)) => warn!("Ledger verification skipped: {}", e),
+                        Err(e) => warn!("Ledger verification task failed: {}", e),
+                    }
+                }
+                _ = ct.cancelled() => break,
+            }
+        }
+    });
+}
+
+/// Verify the ledger at `path` from its saved checkpoint (after a failure,
+/// from the last good checkpoint again). Returns the checkpoint and the result.
+fn verify_since_checkpoint(
+    path: &std::path::Path,
+) -> Result<(Option<VerifyCheckpoint>, ChainCheck)> {
+    let ledger = Ledger::open_readonly(path)?;
+    let previous: Option<VerifyCheckpoint> = ledger
+        .get_meta(VERIFY_CHECKPOINT_KEY)?
+        .and_then(|json| serde_json::from_str(&json).ok());
+    let check =
+        ledger.verify_chain_from(previous.as_ref().map(|p| (p.event_id, p.hash.as_str())))?;
+    Ok((previous, check))
+}
+
+/// The checkpoint to save after `check`: it only advances past events
+/// that verified, and records whether the chain is currently valid.
+fn next_checkpoint(
+    previous: Option<&VerifyCheckpoint>,
+    check: &ChainCheck,
+) -> Option<VerifyCheckpoint> {
+    let (event_id, hash) = match (&check.last, previous) {
+        (Some((event_id, hash)), _) => (*event_id, hash.clone()),
+        (None, Some(p)) => (p.event_id, p.hash.clone()),
+        // Empty ledger, or genesis itself failed
+        (None, None) => return None,
+    };
+    Some(VerifyCheckpoint {
+        event_id,
+        hash,
+        valid: check.valid,
+    })
+}
+
+fn write_audit(ledger: &mut Ledger, entry: AuditEntry) {
+    let event = entry.to_event(&RunId("daemon".to_string()), EventId(0));
+    if let Err(e) = ledger.append_event(event) {
+        error!("Failed to write audit entry {}: {}", entry.action, e);
+    }
+}
+
+/// Record a reveal sent through the admin API and reply with the event.
+fn handle_reveal(ledger: &mut Ledger, req: RevealRequest) {
+    let p = &req.params;
+    let outcome = vault::record_reveal(ledger, p.event_id, &p.investigator_key, &p.actor);
+    match &outcome {
+        Ok(_) => info!("Reveal of event {} recorded for {}", p.event_id, p.actor),
+        Err(e) => warn!(
+            "Reveal of event {} for {} failed: {}",
+            p.event_id, p.actor, e
+        ),
+    }
+    req.reply.send(outcome).ok();
+}
+
+/// Next request on `rx`, or never if that hook is not enabled.
+async fn next_request<T>(rx: &mut Option<mpsc::Receiver<T>>) -> Option<T> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
+    }
 }
 
 /// Run a single gateway connection session, writing events to the ledger.
 /// Returns the shutdown reason so the caller can decide whether to reconnect.
 async fn run_connection(
-    gateway_url: &str,
-    auth_token: &str,
-    redact: bool,
-    flush_interval_ms: u64,
+    state: &mut DaemonState,
     ledger: Arc<Mutex<Ledger>>,
-    pb: &ProgressBar,
     shutdown: &Arc<AtomicBool>,
 ) -> Result<ShutdownReason> {
-    let mut client = GatewayClient::new(gateway_url, auth_token)?;
+    let mut client = GatewayClient::new(&state.config.gateway_url, &state.auth_token)?;
     let conn_id = client.connect().await?;
 
     info!("Daemon connected to gateway, connId: {}", conn_id);
-    pb.set_message("Connected. Recording...");
+    state
+        .observer
+        .on_progress(&Progress::Connected { conn_id: &conn_id });
+    state.metrics.set_connected(true);
+    state.audit.record(
+        "gateway_connected",
+        "daemon",
+        serde_json::json!({ "gateway_url": state.config.gateway_url, "conn_id": conn_id }),
+    );
+    state.notify.status(&format!(
+        "Connected. Recording | {} events recorded",
+        state.metrics.ledger_events()
+    ));
 
     // Spawn gateway event reader, track the task handle
     let (event_tx, mut event_rx) = tokio::sync::mpsc::channel::<GatewayEvent>(1000);
@@ -199,15 +670,34 @@ async fn run_connection(
     });
 
     let run_id = RunId("daemon".to_string());
-    let mut flush_interval = interval(Duration::from_millis(flush_interval_ms));
+    let mut flush_interval = interval(Duration::from_millis(state.config.flush_interval_ms));
     // Poll shutdown flag every second
     let mut shutdown_check = interval(Duration::from_secs(1));
+    // Last gateway seq number, to spot skipped events
+    let mut last_seq: Option<u64> = None;
 
     let result = loop {
         tokio::select! {
             msg = event_rx.recv() => {
                 match msg {
                     Some(gw_event) => {
+                        state.metrics.set_channel_depth(event_rx.len());
+                        if let Some(seq) = gw_event.seq {
+                            if let Some(last) = last_seq
+                                && seq > last + 1
+                            {
+                                warn!("Gateway seq jumped from {} to {}", last, seq);
+                                state.metrics.record_seq_gap(seq - last - 1);
+                            }
+                            last_seq = Some(seq);
+                        }
+
+                        let now = chrono::Utc::now();
+                        if state.filter.admit(&gw_event, now) == Admission::Omit {
+                            state.metrics.record_omitted();
+                            continue;
+                        }
+
                         let kind_name = match gw_event.event.as_str() {
                             "agent" => "AGENT_EVENT",
                             "chat" => "OUTPUT_CHUNK",
@@ -221,13 +711,15 @@ async fn run_connection(
                             &run_id,
                             EventId(0), // ledger assigns the real ID
                             gw_event,
-                            redact,
+                            state.redactor.as_ref(),
                         );
+                        state.metrics.record_ingested(kind_name, now);
 
                         {
                             let mut l = ledger.lock().await;
                             if let Err(e) = l.append_event(event) {
                                 error!("Failed to write event: {}", e);
+                                state.metrics.record_write_error();
                             }
                         }
 
@@ -236,10 +728,10 @@ async fn run_connection(
                             l.total_events()
                         };
 
-                        pb.set_message(format!(
-                            "{} events | Last: {}",
-                            total, kind_name
-                        ));
+                        state.observer.on_progress(&Progress::Event {
+                            total,
+                            kind: kind_name,
+                        });
                     }
                     None => {
                         break ShutdownReason::Disconnected;
@@ -249,8 +741,61 @@ async fn run_connection(
 
             _ = flush_interval.tick() => {
                 let mut l = ledger.lock().await;
-                if let Err(e) = l.flush() {
-                    error!("Failed to flush: {}", e);
+                for summary in state.filter.take_summaries(chrono::Utc::now()) {
+                    if let Err(e) = l.append_event(summary.to_event(&run_id, EventId(0))) {
+                        error!("Failed to write filter summary: {}", e);
+                    }
+                }
+                // Keeps spooled events durable even if the flush fails
+                if let Err(e) = l.sync_spool() {
+                    error!("Failed to sync spool: {}", e);
+                }
+                match l.flush() {
+                    Ok(()) => state.metrics.record_flushed(chrono::Utc::now()),
+                    Err(e) => {
+                        error!("Failed to flush: {}", e);
+                        state.metrics.record_write_error();
+                    }
+                }
+                state.metrics.set_ledger(l.total_events(), l.storage_size_bytes().unwrap_or(0));
+                state.metrics.set_channel_depth(event_rx.len());
+                let jobs = match state.sessionizer.as_mut() {
+                    Some(s) => s.take_due(&mut l, std::time::Instant::now()),
+                    None => {
+                        l.take_completed_agent_runs();
+                        Vec::new()
+                    }
+                };
+                drop(l);
+                // Case files are written off the ledger lock so ingest keeps going
+                for job in jobs {
+                    state.case_files.spawn_blocking(move || {
+                        CaseFileJob::write_all(vec![job]);
+                    });
+                }
+                while state.case_files.try_join_next().is_some() {}
+            }
+
+            Some(entry) = state.audit_rx.recv() => {
+                let mut l = ledger.lock().await;
+                write_audit(&mut l, entry);
+            }
+
+            Some(req) = next_request(&mut state.reveal) => {
+                handle_reveal(&mut *ledger.lock().await, req);
+            }
+
+            Some(req) = next_request(&mut state.reload) => {
+                let previous_flush_ms = state.config.flush_interval_ms;
+                let reconnect = {
+                    let mut l = ledger.lock().await;
+                    state.apply_reload(&mut l, req)
+                };
+                if reconnect {
+                    break ShutdownReason::Reconfigured;
+                }
+                if state.config.flush_interval_ms != previous_flush_ms {
+                    flush_interval = interval(Duration::from_millis(state.config.flush_interval_ms));
                 }
             }
 
@@ -258,6 +803,12 @@ async fn run_connection(
                 if shutdown.load(Ordering::SeqCst) {
                     break ShutdownReason::Signal;
                 }
+                // Only pinged while this loop runs, so a wedged recorder
+                // misses the watchdog and gets restarted
+                state.notify.heartbeat(&format!(
+                    "Connected. Recording | {} events recorded",
+                    state.metrics.ledger_events()
+                ));
             }
         }
     };
@@ -268,17 +819,173 @@ async fn run_connection(
     Ok(result)
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-    if bytes >= GB {
-        format!("{:.1} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.1} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.1} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} B", bytes)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterPolicy;
+    use crate::{Event, EventKind};
+    use tempfile::TempDir;
+
+    fn test_state(config: &Config) -> DaemonState {
+        DaemonState {
+            filter: IngestFilter::new(config.filter.clone()),
+            redactor: None,
+            sessionizer: None,
+            case_files: JoinSet::new(),
+            auth_token: config.auth_token.clone().unwrap_or_default(),
+            config: config.clone(),
+            reload: None,
+            reveal: None,
+            metrics: Arc::default(),
+            notify: Notifier::disabled(),
+            audit: AuditLog::default(),
+            audit_rx: mpsc::channel(1).1,
+            observer: Arc::new(Headless),
+        }
+    }
+
+    fn request(config: Config) -> ReloadRequest {
+        ReloadRequest {
+            config,
+            source: "test",
+            reply: None,
+        }
+    }
+
+    #[test]
+    fn test_reload_applies_live_and_records_change() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        let config = Config {
+            output_dir: temp.path().to_path_buf(),
+            auth_token: Some("t1".into()),
+            ..Config::default()
+        };
+        let mut state = test_state(&config);
+
+        // Filter and flush interval apply without reconnecting
+        let mut new = config.clone();
+        new.filter = FilterPolicy::new(vec!["tick=drop".parse().unwrap()]);
+        new.flush_interval_ms = 1000;
+        new.batch_size = 7;
+        assert!(!state.apply_reload(&mut ledger, request(new)));
+        assert_eq!(state.config.flush_interval_ms, 1000);
+        assert_eq!(state.config.batch_size, 100, "needs a restart");
+
+        // A new token reconnects
+        let mut new = state.config.clone();
+        new.auth_token = Some("t2".into());
+        assert!(state.apply_reload(&mut ledger, request(new)));
+        assert_eq!(state.auth_token, "t2");
+
+        // Without a token the reload is rejected
+        let mut new = state.config.clone();
+        new.auth_token = None;
+        assert!(!state.apply_reload(&mut ledger, request(new)));
+        assert_eq!(state.auth_token, "t2");
+
+        ledger.flush().unwrap();
+        let mut events = ledger.search_events("", None, None, None, 100).unwrap();
+        events.reverse();
+        let kinds: Vec<_> = events.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                EventKind::FilterPolicy,
+                EventKind::ConfigChange,
+                EventKind::ConfigChange
+            ]
+        );
+        assert_eq!(events[1].payload["restart_required"][0], "batch_size");
+        assert_eq!(
+            ledger.get_meta("config_hash").unwrap().as_deref(),
+            Some(events[2].payload["config_hash"].as_str().unwrap())
+        );
+        assert!(ledger.verify_chain().unwrap().0);
+    }
+
+    #[test]
+    fn test_verifier_resumes_from_checkpoint() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        let append = |ledger: &mut Ledger, n: u64| {
+            for _ in 0..n {
+                let event = Event::new(
+                    RunId("daemon".into()),
+                    EventId(0),
+                    EventKind::Tick,
+                    serde_json::json!({}),
+                    None,
+                );
+                ledger.append_event(event).unwrap();
+            }
+            ledger.flush().unwrap();
+        };
+        let save = |ledger: &Ledger, checkpoint: &VerifyCheckpoint| {
+            let json = serde_json::to_string(checkpoint).unwrap();
+            ledger.set_meta(VERIFY_CHECKPOINT_KEY, &json).unwrap();
+        };
+
+        append(&mut ledger, 4);
+        let (previous, check) = verify_since_checkpoint(temp.path()).unwrap();
+        assert_eq!(previous, None);
+        assert_eq!((check.valid, check.checked), (true, 4));
+        let first = next_checkpoint(None, &check).unwrap();
+        assert_eq!((first.event_id, first.valid), (4, true));
+        save(&ledger, &first);
+
+        // Only the checkpoint and what was appended since are checked
+        append(&mut ledger, 2);
+        let (previous, check) = verify_since_checkpoint(temp.path()).unwrap();
+        assert_eq!(previous.as_ref(), Some(&first));
+        assert_eq!((check.valid, check.checked), (true, 3));
+        let second = next_checkpoint(previous.as_ref(), &check).unwrap();
+        assert_eq!(second.event_id, 6);
+        save(&ledger, &second);
+
+        // A rewritten checkpoint event fails, and the position is kept
+        let conn = rusqlite::Connection::open(temp.path().join("ledger.sqlite")).unwrap();
+        conn.execute(
+            "UPDATE events SET payload = '{\"x\":1}' WHERE event_id = 6",
+            [],
+        )
+        .unwrap();
+        let (previous, check) = verify_since_checkpoint(temp.path()).unwrap();
+        assert!(!check.valid);
+        let failed = next_checkpoint(previous.as_ref(), &check).unwrap();
+        assert_eq!((failed.event_id, failed.valid), (6, false));
+    }
+
+    #[test]
+    fn test_reload_keeps_running_sinks() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        let config = Config {
+            output_dir: temp.path().to_path_buf(),
+            auth_token: Some("t1".into()),
+            ..Config::default()
+        };
+        let mut state = test_state(&config);
+        let hash = crate::reload::config_hash(&state.config);
+
+        let mut new = config.clone();
+        new.sinks = vec![
+            toml::from_str(
+                "name = \"file\"\ntype = \"ndjson\"\npath = \"/tmp/clawprint-sink.ndjson\"",
+            )
+            .unwrap(),
+        ];
+        assert!(!state.apply_reload(&mut ledger, request(new)));
+        assert!(
+            state.config.sinks.is_empty(),
+            "sink tasks are not restarted"
+        );
+        assert_eq!(crate::reload::config_hash(&state.config), hash);
+
+        ledger.flush().unwrap();
+        let events = ledger.search_events("", None, None, None, 10).unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].payload["restart_required"][0], "sinks");
+        assert_eq!(events[0].payload["config_hash"], hash.as_str());
     }
 }
@@ -0,0 +1,597 @@
+//! Ingest filtering — drop, sample or aggregate low-value gateway events
+//!
+//! Sits between the gateway reader and the ledger / run storage. Ticks and
+//! presence heartbeats make up most of the traffic but carry little audit
+//! value, so they can be dropped, sampled, or collapsed into one summary
+//! event per time window.
+//!
+//! The active policy is itself written into the hash chain as a
+//! `FILTER_POLICY` event, and aggregated events are replaced by
+//! `FILTER_SUMMARY` events, so an auditor can always tell what was
+//! intentionally omitted.
+
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use crate::gateway::GatewayEvent;
+use crate::{Event, EventId, EventKind, RunId};
+
+/// What to do with events matching a rule
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum FilterAction {
+    /// Record every event (useful to exempt events from a later `*` rule)
+    Keep,
+    /// Record nothing
+    Drop,
+    /// Record the first event, then one of every `every` events
+    Sample { every: u64 },
+    /// Replace events with one FILTER_SUMMARY per `window_secs` window
+    Aggregate { window_secs: u64 },
+}
+
+/// A single filter rule, matched against the gateway event name and,
+/// optionally, the payload's tool and channel.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterRule {
+    /// Gateway event name (e.g. "tick", "presence"), or "*" for any event
+    pub event: String,
+    /// Only match events whose payload `tool` equals this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Only match events whose payload `channel` (or agent `stream`) equals this
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    #[serde(flatten)]
+    pub action: FilterAction,
+}
+
+impl FilterRule {
+    fn matches(&self, gw: &GatewayEvent) -> bool {
+        let field = |name: &str| gw.payload.get(name).and_then(|v| v.as_str());
+        (self.event == "*" || self.event == gw.event)
+            && self
+                .tool
+                .as_deref()
+                .is_none_or(|t| field("tool") == Some(t))
+            && self
+                .channel
+                .as_deref()
+                .is_none_or(|c| field("channel").or_else(|| field("stream")) == Some(c))
+    }
+
+    /// `EVENT[tool=NAME,channel=NAME]` — the part left of the action.
+    fn selector(&self) -> String {
+        let mut fields = Vec::new();
+        if let Some(tool) = &self.tool {
+            fields.push(format!("tool={}", tool));
+        }
+        if let Some(channel) = &self.channel {
+            fields.push(format!("channel={}", channel));
+        }
+        if fields.is_empty() {
+            self.event.clone()
+        } else {
+            format!("{}[{}]", self.event, fields.join(","))
+        }
+    }
+}
+
+impl FromStr for FilterRule {
+    type Err = anyhow::Error;
+
+    /// Parse `EVENT[FIELD=VALUE,...]=ACTION`, e.g. `tick=aggregate:60`,
+    /// `presence=drop`, `chat=sample:10` or `agent[tool=bash]=keep`. The
+    /// bracketed fields are optional; `tool` and `channel` are supported.
+    fn from_str(s: &str) -> Result<Self> {
+        let (selector, action) = match s.split_once(']') {
+            Some((sel, rest)) => (
+                format!("{}]", sel),
+                rest.trim_start().strip_prefix('=').ok_or_else(|| {
+                    anyhow!("Invalid filter rule '{}': expected EVENT[...]=ACTION", s)
+                })?,
+            ),
+            None => {
+                let (sel, action) = s
+                    .split_once('=')
+                    .ok_or_else(|| anyhow!("Invalid filter rule '{}': expected EVENT=ACTION", s))?;
+                (sel.to_string(), action)
+            }
+        };
+
+        let (event, fields) = match selector.split_once('[') {
+            Some((event, fields)) => (
+                event.trim(),
+                fields
+                    .strip_suffix(']')
+                    .ok_or_else(|| anyhow!("Invalid filter rule '{}': unclosed '['", s))?,
+            ),
+            None => (selector.trim(), ""),
+        };
+        if event.is_empty() {
+            return Err(anyhow!("Invalid filter rule '{}': empty event name", s));
+        }
+
+        let (mut tool, mut channel) = (None, None);
+        for field in fields.split(',').map(str::trim).filter(|f| !f.is_empty()) {
+            let (key, value) = field
+                .split_once('=')
+                .map(|(k, v)| (k.trim(), v.trim()))
+                .filter(|(_, v)| !v.is_empty())
+                .ok_or_else(|| anyhow!("Invalid filter field '{}' in '{}'", field, s))?;
+            match key {
+                "tool" => tool = Some(value.to_string()),
+                "channel" => channel = Some(value.to_string()),
+                other => {
+                    return Err(anyhow!(
+                        "Unknown filter field '{}' in '{}'. Use tool or channel",
+                        other,
+                        s
+                    ));
+                }
+            }
+        }
+
+        let (name, arg) = match action.trim().split_once(':') {
+            Some((n, a)) => (n, Some(a)),
+            None => (action.trim(), None),
+        };
+        let parse_arg = |what: &str| -> Result<u64> {
+            let n: u64 = arg
+                .ok_or_else(|| anyhow!("Filter action '{}' requires :{}", name, what))?
+                .parse()
+                .map_err(|_| anyhow!("Invalid {} in filter rule '{}'", what, s))?;
+            if n == 0 {
+                return Err(anyhow!("{} must be greater than 0 in '{}'", what, s));
+            }
+            Ok(n)
+        };
+
+        let action = match name {
+            "keep" => FilterAction::Keep,
+            "drop" => FilterAction::Drop,
+            "sample" => FilterAction::Sample {
+                every: parse_arg("N")?,
+            },
+            "aggregate" => FilterAction::Aggregate {
+                window_secs: parse_arg("SECS")?,
+            },
+            other => {
+                return Err(anyhow!(
+                    "Unknown filter action '{}'. Use keep, drop, sample:N or aggregate:SECS",
+                    other
+                ));
+            }
+        };
+
+        Ok(Self {
+            event: event.to_string(),
+            tool,
+            channel,
+            action,
+        })
+    }
+}
+
+impl std::fmt::Display for FilterRule {
+    /// Inverse of `FromStr`: `EVENT[FIELD=VALUE,...]=ACTION`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let selector = self.selector();
+        match &self.action {
+            FilterAction::Keep => write!(f, "{}=keep", selector),
+            FilterAction::Drop => write!(f, "{}=drop", selector),
+            FilterAction::Sample { every } => write!(f, "{}=sample:{}", selector, every),
+            FilterAction::Aggregate { window_secs } => {
+                write!(f, "{}=aggregate:{}", selector, window_secs)
+            }
+        }
+    }
+}
+
+/// Ordered list of filter rules. The first matching rule wins; events
+/// matching no rule are kept.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilterPolicy {
+    pub rules: Vec<FilterRule>,
+}
+
+impl FilterPolicy {
+    pub fn new(rules: Vec<FilterRule>) -> Self {
+        Self { rules }
+    }
+
+    /// True if the policy keeps every event.
+    pub fn is_empty(&self) -> bool {
+        self.rules
+            .iter()
+            .all(|r| matches!(r.action, FilterAction::Keep))
+    }
+
+    /// SHA-256 of the policy's JSON form, so auditors can compare policies
+    /// across recordings without diffing rule lists.
+    pub fn hash(&self) -> String {
+        let json = serde_json::to_string(self).expect("filter policy must be JSON-serializable");
+        let mut hasher = Sha256::new();
+        hasher.update(json.as_bytes());
+        hex::encode(hasher.finalize())
+    }
+
+    /// Build the FILTER_POLICY event recording this policy in the chain.
+    pub fn to_event(&self, run_id: &RunId, event_id: EventId) -> Event {
+        Event::new(
+            run_id.clone(),
+            event_id,
+            EventKind::FilterPolicy,
+            serde_json::json!({
+                "rules": self.rules,
+                "policy_hash": self.hash(),
+            }),
+            None,
+        )
+    }
+}
+
+/// Whether an incoming event should be recorded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    Keep,
+    Omit,
+}
+
+/// Aggregated stand-in for a window of omitted events.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FilterSummary {
+    pub gateway_event: String,
+    /// Tool selector of the aggregating rule, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Channel selector of the aggregating rule, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub channel: Option<String>,
+    pub count: u64,
+    pub window_start: DateTime<Utc>,
+    pub window_end: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub first_seq: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seq: Option<u64>,
+}
+
+impl FilterSummary {
+    /// Build the FILTER_SUMMARY event for this window.
+    pub fn to_event(&self, run_id: &RunId, event_id: EventId) -> Event {
+        let payload = serde_json::to_value(self).expect("filter summary must be JSON-serializable");
+        Event::new(
+            run_id.clone(),
+            event_id,
+            EventKind::FilterSummary,
+            payload,
+            None,
+        )
+    }
+}
+
+/// Stateful filter applying a [`FilterPolicy`] to the gateway event stream.
+pub struct IngestFilter {
+    policy: FilterPolicy,
+    /// Events seen per sampling rule (indexed by rule position)
+    sample_counters: HashMap<usize, u64>,
+    /// Open aggregation windows per (rule position, gateway event name)
+    windows: HashMap<(usize, String), FilterSummary>,
+    /// Closed windows not yet handed to the caller
+    pending: Vec<FilterSummary>,
+    /// Events not recorded verbatim, per gateway event name
+    omitted: HashMap<String, u64>,
+}
+
+impl IngestFilter {
+    pub fn new(policy: FilterPolicy) -> Self {
+        Self {
+            policy,
+            sample_counters: HashMap::new(),
+            windows: HashMap::new(),
+            pending: Vec::new(),
+            omitted: HashMap::new(),
+        }
+    }
+
+    pub fn policy(&self) -> &FilterPolicy {
+        &self.policy
+    }
+
+    /// Decide whether `gw` is recorded. Aggregated events are folded into
+    /// their current window; collect the results with [`Self::take_summaries`].
+    pub fn admit(&mut self, gw: &GatewayEvent, now: DateTime<Utc>) -> Admission {
+        let Some((idx, rule)) = self
+            .policy
+            .rules
+            .iter()
+            .enumerate()
+            .find(|(_, r)| r.matches(gw))
+        else {
+            return Admission::Keep;
+        };
+
+        let admission = match rule.action {
+            FilterAction::Keep => Admission::Keep,
+            FilterAction::Drop => Admission::Omit,
+            FilterAction::Sample { every } => {
+                let seen = self.sample_counters.entry(idx).or_insert(0);
+                let keep = seen.is_multiple_of(every);
+                *seen += 1;
+                if keep {
+                    Admission::Keep
+                } else {
+                    Admission::Omit
+                }
+            }
+            FilterAction::Aggregate { window_secs } => {
+                let rule = rule.clone();
+                self.aggregate(idx, &rule, gw, now, window_secs);
+                Admission::Omit
+            }
+        };
+
+        if admission == Admission::Omit {
+            *self.omitted.entry(gw.event.clone()).or_insert(0) += 1;
+        }
+        admission
+    }
+
+    fn aggregate(
+        &mut self,
+        idx: usize,
+        rule: &FilterRule,
+        gw: &GatewayEvent,
+        now: DateTime<Utc>,
+        window_secs: u64,
+    ) {
+        let window = window_secs.min(i64::MAX as u64) as i64;
+        let start_secs = now.timestamp() - now.timestamp().rem_euclid(window);
+        let window_start = Utc.timestamp_opt(start_secs, 0).single().unwrap_or(now);
+        let window_end = window_start + chrono::Duration::seconds(window);
+
+        let key = (idx, gw.event.clone());
+        if let Some(open) = self.windows.get(&key)
+            && open.window_start != window_start
+            && let Some(closed) = self.windows.remove(&key)
+        {
+            self.pending.push(closed);
+        }
+
+        let summary = self.windows.entry(key).or_insert_with(|| FilterSummary {
+            gateway_event: gw.event.clone(),
+            tool: rule.tool.clone(),
+            channel: rule.channel.clone(),
+            count: 0,
+            window_start,
+            window_end,
+            first_seq: gw.seq,
+            last_seq: None,
+        });
+        summary.count += 1;
+        if summary.first_seq.is_none() {
+            summary.first_seq = gw.seq;
+        }
+        if gw.seq.is_some() {
+            summary.last_seq = gw.seq;
+        }
+    }
+
+    /// Return summaries for every window that has closed by `now`.
+    pub fn take_summaries(&mut self, now: DateTime<Utc>) -> Vec<FilterSummary> {
+        let expired: Vec<(usize, String)> = self
+            .windows
+            .iter()
+            .filter(|(_, w)| w.window_end <= now)
+            .map(|(k, _)| k.clone())
+            .collect();
+        for key in expired {
+            if let Some(w) = self.windows.remove(&key) {
+                self.pending.push(w);
+            }
+        }
+        let mut out = std::mem::take(&mut self.pending);
+        out.sort_by_key(|w| w.window_start);
+        out
+    }
+
+    /// Close all windows regardless of time (used at shutdown).
+    pub fn drain(&mut self) -> Vec<FilterSummary> {
+        let mut out = std::mem::take(&mut self.pending);
+        out.extend(self.windows.drain().map(|(_, w)| w));
+        out.sort_by_key(|w| w.window_start);
+        out
+    }
+
+    /// Count of events not recorded verbatim, per gateway event name.
+    pub fn omitted_counts(&self) -> &HashMap<String, u64> {
+        &self.omitted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gw(event: &str, seq: u64) -> GatewayEvent {
+        GatewayEvent {
+            event: event.to_string(),
+            payload: serde_json::json!({}),
+            seq: Some(seq),
+        }
+    }
+
+    fn at(secs: i64) -> DateTime<Utc> {
+        Utc.timestamp_opt(secs, 0).single().unwrap()
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        let r: FilterRule = "tick=aggregate:60".parse().unwrap();
+        assert_eq!(r.event, "tick");
+        assert_eq!(r.action, FilterAction::Aggregate { window_secs: 60 });
+
+        let r: FilterRule = "presence=drop".parse().unwrap();
+        assert_eq!(r.action, FilterAction::Drop);
+
+        let r: FilterRule = "*=sample:10".parse().unwrap();
+        assert_eq!(r.event, "*");
+        assert_eq!(r.action, FilterAction::Sample { every: 10 });
+
+        assert!("tick".parse::<FilterRule>().is_err());
+        assert!("tick=sample".parse::<FilterRule>().is_err());
+        assert!("tick=sample:0".parse::<FilterRule>().is_err());
+        assert!("tick=explode".parse::<FilterRule>().is_err());
+    }
+
+    #[test]
+    fn test_parse_tool_and_channel_rules() {
+        let r: FilterRule = "agent[tool=bash]=drop".parse().unwrap();
+        assert_eq!(r.event, "agent");
+        assert_eq!(r.tool.as_deref(), Some("bash"));
+        assert_eq!(r.channel, None);
+        assert_eq!(r.to_string(), "agent[tool=bash]=drop");
+
+        let r: FilterRule = "*[tool=read_file, channel=tool]=sample:5".parse().unwrap();
+        assert_eq!(r.event, "*");
+        assert_eq!(r.channel.as_deref(), Some("tool"));
+        assert_eq!(r.to_string(), "*[tool=read_file,channel=tool]=sample:5");
+        assert_eq!(r.to_string().parse::<FilterRule>().unwrap(), r);
+
+        // Plain rules serialize without the optional fields, so existing
+        // policy hashes are unchanged
+        let r: FilterRule = "tick=drop".parse().unwrap();
+        assert_eq!(
+            serde_json::to_value(&r).unwrap(),
+            serde_json::json!({"event": "tick", "action": "drop"})
+        );
+
+        assert!("agent[tool=bash=drop".parse::<FilterRule>().is_err());
+        assert!("agent[tool=bash]drop".parse::<FilterRule>().is_err());
+        assert!("agent[tool=]=drop".parse::<FilterRule>().is_err());
+        assert!("agent[model=x]=drop".parse::<FilterRule>().is_err());
+    }
+
+    #[test]
+    fn test_match_on_tool_and_channel() {
+        let event = |payload: serde_json::Value| GatewayEvent {
+            event: "agent".to_string(),
+            payload,
+            seq: None,
+        };
+        let policy = FilterPolicy::new(vec![
+            "agent[tool=read_file]=drop".parse().unwrap(),
+            "agent[channel=assistant]=aggregate:60".parse().unwrap(),
+            "*[channel=general]=drop".parse().unwrap(),
+        ]);
+        let mut f = IngestFilter::new(policy);
+
+        let read = event(serde_json::json!({"type": "tool_use", "tool": "read_file"}));
+        let bash = event(serde_json::json!({"type": "tool_use", "tool": "bash"}));
+        assert_eq!(f.admit(&read, at(0)), Admission::Omit);
+        assert_eq!(f.admit(&bash, at(0)), Admission::Keep);
+
+        // channel falls back to the agent event's stream
+        let delta = event(serde_json::json!({"stream": "assistant", "data": {"delta": "hi"}}));
+        let lifecycle = event(serde_json::json!({"stream": "lifecycle"}));
+        assert_eq!(f.admit(&delta, at(0)), Admission::Omit);
+        assert_eq!(f.admit(&lifecycle, at(0)), Admission::Keep);
+
+        let chat = GatewayEvent {
+            event: "chat".to_string(),
+            payload: serde_json::json!({"channel": "general"}),
+            seq: None,
+        };
+        assert_eq!(f.admit(&chat, at(0)), Admission::Omit);
+
+        let summaries = f.drain();
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].gateway_event, "agent");
+        assert_eq!(summaries[0].channel.as_deref(), Some("assistant"));
+        assert_eq!(summaries[0].tool, None);
+        assert_eq!(f.omitted_counts().get("agent"), Some(&2));
+    }
+
+    #[test]
+    fn test_empty_policy_keeps_everything() {
+        let mut f = IngestFilter::new(FilterPolicy::default());
+        assert!(f.policy().is_empty());
+        assert_eq!(f.admit(&gw("tick", 1), at(0)), Admission::Keep);
+        assert_eq!(f.admit(&gw("agent", 2), at(0)), Admission::Keep);
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let policy = FilterPolicy::new(vec![
+            "agent=keep".parse().unwrap(),
+            "*=drop".parse().unwrap(),
+        ]);
+        let mut f = IngestFilter::new(policy);
+        assert_eq!(f.admit(&gw("agent", 1), at(0)), Admission::Keep);
+        assert_eq!(f.admit(&gw("presence", 2), at(0)), Admission::Omit);
+        assert_eq!(f.omitted_counts().get("presence"), Some(&1));
+    }
+
+    #[test]
+    fn test_sample_keeps_one_in_n() {
+        let policy = FilterPolicy::new(vec!["chat=sample:3".parse().unwrap()]);
+        let mut f = IngestFilter::new(policy);
+        let kept: Vec<bool> = (0..7)
+            .map(|i| f.admit(&gw("chat", i), at(0)) == Admission::Keep)
+            .collect();
+        assert_eq!(kept, vec![true, false, false, true, false, false, true]);
+    }
+
+    #[test]
+    fn test_aggregate_per_window() {
+        let policy = FilterPolicy::new(vec!["tick=aggregate:60".parse().unwrap()]);
+        let mut f = IngestFilter::new(policy);
+
+        for (i, t) in [0, 15, 30, 45].iter().enumerate() {
+            assert_eq!(f.admit(&gw("tick", i as u64), at(*t)), Admission::Omit);
+        }
+        // Window still open
+        assert!(f.take_summaries(at(59)).is_empty());
+
+        // Next window's first tick closes the previous one
+        f.admit(&gw("tick", 4), at(61));
+        let summaries = f.take_summaries(at(61));
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].count, 4);
+        assert_eq!(summaries[0].first_seq, Some(0));
+        assert_eq!(summaries[0].last_seq, Some(3));
+        assert_eq!(summaries[0].window_start, at(0));
+        assert_eq!(summaries[0].window_end, at(60));
+
+        // Time passing alone closes the window
+        let summaries = f.take_summaries(at(120));
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].count, 1);
+        assert!(f.drain().is_empty());
+    }
+
+    #[test]
+    fn test_drain_closes_open_windows() {
+        let policy = FilterPolicy::new(vec!["tick=aggregate:60".parse().unwrap()]);
+        let mut f = IngestFilter::new(policy);
+        f.admit(&gw("tick", 1), at(10));
+        let drained = f.drain();
+        assert_eq!(drained.len(), 1);
+        assert_eq!(drained[0].count, 1);
+    }
+
+    #[test]
+    fn test_policy_event_records_hash() {
+        let policy = FilterPolicy::new(vec!["tick=drop".parse().unwrap()]);
+        let event = policy.to_event(&RunId("r".into()), EventId(1));
+        assert_eq!(event.kind, EventKind::FilterPolicy);
+        assert_eq!(event.payload["policy_hash"], policy.hash());
+        assert_eq!(event.payload["rules"][0]["event"], "tick");
+        assert_eq!(event.payload["rules"][0]["action"], "drop");
+    }
+}
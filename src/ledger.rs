@@ -2,17 +2,51 @@
 //!
 //! Unlike RunStorage (one SQLite DB per recording session), the Ledger
 //! is a single append-only database that grows forever. Agent runs are
-//! detected automatically from gateway event payloads.
+//! derived from gateway event payloads by a `RunAssigner`.
 
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 use rusqlite::{Connection, OptionalExtension, params};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{debug, info, warn};
 
+use crate::assign::{AssignPolicy, RunAssigner};
+use crate::lock::WriterLock;
+use crate::metrics::DaemonMetrics;
+use crate::spool::{self, Spool, SpoolSync};
+use crate::subscribe::EventBus;
 use crate::{Event, EventId, EventKind, RunId};
 
+/// `meta` key holding the run assigner's state as of the last flush
+const ASSIGN_STATE_KEY: &str = "assign_state";
+
+/// `meta` key holding the daemon verifier's [`VerifyCheckpoint`]
+pub const VERIFY_CHECKPOINT_KEY: &str = "verify_checkpoint";
+
+/// Where the daemon's periodic verification got to, so the next one only
+/// checks events appended since.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VerifyCheckpoint {
+    /// Last event that verified, and its hash
+    pub event_id: u64,
+    pub hash: String,
+    /// Outcome of the most recent verification
+    pub valid: bool,
+}
+
+/// Result of [`Ledger::verify_chain_from`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainCheck {
+    pub valid: bool,
+    /// Events checked, including the checkpoint event
+    pub checked: u64,
+    /// Last event that verified, and its hash
+    pub last: Option<(u64, String)>,
+}
+
 /// Summary of a single agent conversation run
 #[derive(Debug, Clone)]
 pub struct AgentRunSummary {
@@ -33,10 +67,21 @@ pub struct ToolCallRecord {
     pub agent_run: Option<String>,
 }
 
+/// Result of re-deriving `agent_run` for stored events
+#[derive(Debug, Clone, Default)]
+pub struct BackfillStats {
+    /// Events examined
+    pub scanned: u64,
+    /// Events whose `agent_run` changed
+    pub changed: u64,
+    /// Events with an `agent_run` after the backfill
+    pub assigned: u64,
+}
+
 /// Single continuous append-only ledger.
 ///
 /// All events are written to one SQLite database with a hash chain.
-/// Agent runs are extracted automatically from event payloads.
+/// Agent runs are assigned from event payloads as events are flushed.
 pub struct Ledger {
     db: Connection,
     db_path: PathBuf,
@@ -44,13 +89,31 @@ pub struct Ledger {
     event_count: u64,
     batch_buffer: Vec<Event>,
     batch_size: usize,
+    spool: Option<Spool>,
+    assigner: RunAssigner,
+    /// Assigner state as last written to `meta`
+    saved_assign_state: Option<String>,
+    metrics: Option<Arc<DaemonMetrics>>,
+    events: Option<EventBus>,
+    /// Exclusive write access to the directory; `None` when read-only.
+    /// Declared last so it is released after the database closes.
+    _lock: Option<WriterLock>,
 }
 
 impl Ledger {
     /// Open or create a ledger at the given directory.
-    /// The database file will be `{path}/ledger.sqlite`.
+    /// The database file will be `{path}/ledger.sqlite`. Fails if another
+    /// process has the ledger open for writing (see [`crate::lock`]).
     pub fn open(path: &Path, batch_size: usize) -> Result<Self> {
+        Self::open_with_spool(path, batch_size, SpoolSync::default())
+    }
+
+    /// Open or create a ledger, syncing the crash spool (`{path}/spool.ndjson`)
+    /// with the given policy. Events left in the spool by a crash are
+    /// replayed into SQLite before the ledger is returned.
+    pub fn open_with_spool(path: &Path, batch_size: usize, sync: SpoolSync) -> Result<Self> {
         std::fs::create_dir_all(path)?;
+        let lock = WriterLock::acquire(path)?;
 
         let db_path = path.join("ledger.sqlite");
         let db = Connection::open(&db_path)?;
@@ -110,14 +173,59 @@ impl Ledger {
             info!("Created new ledger at {:?}", db_path);
         }
 
-        Ok(Self {
+        let spool_path = path.join("spool.ndjson");
+        let recovered = spool::pending_events(
+            Spool::read_events(&spool_path)?,
+            event_count,
+            last_hash.as_deref(),
+        );
+
+        // Pick up run assignment where the last process left off
+        let mut assigner = RunAssigner::default();
+        let saved: Option<String> = db
+            .query_row(
+                "SELECT value FROM meta WHERE key = ?1",
+                [ASSIGN_STATE_KEY],
+                |row| row.get(0),
+            )
+            .optional()?;
+        if let Some(saved) = &saved {
+            match serde_json::from_str(saved) {
+                Ok(state) => assigner.restore(state),
+                Err(e) => warn!("Ignoring saved run assignment state: {}", e),
+            }
+        }
+
+        let mut ledger = Self {
             db,
             db_path,
             last_hash,
-            event_count,
-            batch_buffer: Vec::with_capacity(batch_size),
+            event_count: event_count + recovered.len() as u64,
+            batch_buffer: Vec::with_capacity(batch_size.max(recovered.len())),
             batch_size,
-        })
+            spool: Some(Spool::open(&spool_path, sync)?),
+            assigner,
+            saved_assign_state: saved,
+            metrics: None,
+            events: None,
+            _lock: Some(lock),
+        };
+
+        if !recovered.is_empty() {
+            warn!(
+                "Recovering {} unflushed events from {:?}",
+                recovered.len(),
+                spool_path
+            );
+            ledger.batch_buffer.extend(recovered);
+        }
+        // Commits any recovered events and truncates whatever is left in the spool
+        ledger.flush()?;
+        if let Some(spool) = ledger.spool.as_mut() {
+            spool.clear()?;
+        }
+
+        Ok(ledger)
     }
 
     /// Open a ledger in read-only mode (for MCP server / queries).
@@ -149,17 +257,33 @@ impl Ledger {
             event_count,
             batch_buffer: Vec::new(),
             batch_size: 0, // read-only, no batching
+            spool: None,
+            assigner: RunAssigner::default(),
+            saved_assign_state: None,
+            metrics: None,
+            events: None,
+            _lock: None,
         })
     }
 
-    /// Extract agent_run ID from event payload.
-    /// Looks for `payload.data.runId` (OpenClaw gateway format).
-    fn extract_agent_run(event: &Event) -> Option<String> {
-        event
-            .payload
-            .pointer("/data/runId")
-            .and_then(|v| v.as_str())
-            .map(|s| s.to_string())
+    /// Replace the run-assignment policy used for newly flushed events.
+    pub fn set_assign_policy(&mut self, policy: AssignPolicy) {
+        self.assigner.set_policy(policy);
+    }
+
+    /// Report flush latency and batch sizes to `metrics`.
+    pub fn set_metrics(&mut self, metrics: Arc<DaemonMetrics>) {
+        self.metrics = Some(metrics);
+    }
+
+    /// Publish each event to `events` once its batch is committed.
+    pub fn set_events(&mut self, events: EventBus) {
+        self.events = Some(events);
+    }
+
+    /// Agent runs whose lifecycle ended in events flushed since the last call.
+    pub fn take_completed_agent_runs(&mut self) -> Vec<String> {
+        self.assigner.take_completed()
     }
 
     /// Append an event to the ledger with hash chain linking.
@@ -179,6 +303,9 @@ impl Ledger {
         event.hash_prev = prev_hash;
         event.hash_self = event.compute_hash();
 
+        if let Some(spool) = self.spool.as_mut() {
+            spool.append(&event)?;
+        }
         self.batch_buffer.push(event);
 
         if self.batch_buffer.len() >= self.batch_size {
@@ -188,12 +315,51 @@ impl Ledger {
         Ok(())
     }
 
+    /// Sync the crash spool if its interval has passed. Call on the flush
+    /// tick, so events stay durable when no further appends arrive.
+    pub fn sync_spool(&mut self) -> Result<()> {
+        match self.spool.as_mut() {
+            Some(spool) => spool.sync_if_due(),
+            None => Ok(()),
+        }
+    }
+
     /// Flush buffered events to SQLite.
     pub fn flush(&mut self) -> Result<()> {
         if self.batch_buffer.is_empty() {
             return Ok(());
         }
 
+        let started = std::time::Instant::now();
+        // Assignment runs inside the transaction; roll it back with it
+        let assigner = self.assigner.clone();
+        if let Err(e) = self.commit_batch() {
+            self.assigner = assigner;
+            return Err(e);
+        }
+
+        let flushed = self.batch_buffer.len();
+        if let Some(events) = &self.events {
+            events.publish(&self.batch_buffer);
+        }
+        self.batch_buffer.clear();
+        if let Some(spool) = self.spool.as_mut() {
+            spool.clear()?;
+        }
+        if let Some(metrics) = &self.metrics {
+            metrics.record_flush(flushed, started.elapsed());
+        }
+
+        debug!(
+            "Flushed {} events to ledger (total: {})",
+            flushed, self.event_count
+        );
+
+        Ok(())
+    }
+
+    /// Write the batch and the assigner state in one transaction.
+    fn commit_batch(&mut self) -> Result<()> {
         let tx = self.db.transaction()?;
 
         for event in &self.batch_buffer {
@@ -202,7 +368,7 @@ impl Ledger {
                 .trim_matches('"')
                 .to_owned();
 
-            let agent_run = Self::extract_agent_run(event);
+            let agent_run = self.assigner.assign(event);
 
             // Use explicit event_id (assigned in append_event) instead of AUTOINCREMENT
             tx.execute(
@@ -225,18 +391,23 @@ impl Ledger {
                     event.hash_self,
                 ],
             )?;
-            self.last_hash = Some(event.hash_self.clone());
         }
 
-        let flushed = self.batch_buffer.len();
+        // Saved with the batch so a restart resumes from exactly here
+        let state = serde_json::to_string(self.assigner.state())?;
+        let state_changed = self.saved_assign_state.as_ref() != Some(&state);
+        if state_changed {
+            tx.execute(
+                "INSERT OR REPLACE INTO meta (key, value) VALUES (?1, ?2)",
+                params![ASSIGN_STATE_KEY, state],
+            )?;
+        }
         tx.commit()?;
-        self.batch_buffer.clear();
-
-        debug!(
-            "Flushed {} events to ledger (total: {})",
-            flushed, self.event_count
-        );
 
+        if state_changed {
+            self.saved_assign_state = Some(state);
+        }
+        self.last_hash = self.batch_buffer.last().map(|e| e.hash_self.clone());
         Ok(())
     }
 
@@ -435,6 +606,71 @@ impl Ledger {
         Ok(events)
     }
 
+    /// The committed event with this event_id.
+    pub fn get_event(&self, event_id: u64) -> Result<Option<Event>> {
+        let event = self
+            .db
+            .query_row(
+                "SELECT event_id, run_id, ts, kind, agent_run, span_id, parent_span_id, actor,
+                        payload, artifact_refs, hash_prev, hash_self
+                 FROM events WHERE event_id = ?1",
+                params![event_id as i64],
+                row_to_event,
+            )
+            .optional()?;
+        Ok(event)
+    }
+
+    /// Up to `limit` committed events with event_id greater than `after`,
+    /// oldest first.
+    pub fn events_after(&self, after: u64, limit: usize) -> Result<Vec<Event>> {
+        let mut stmt = self.db.prepare(
+            "SELECT event_id, run_id, ts, kind, agent_run, span_id, parent_span_id, actor,
+                    payload, artifact_refs, hash_prev, hash_self
+             FROM events WHERE event_id > ?1 ORDER BY event_id LIMIT ?2",
+        )?;
+        let events = stmt
+            .query_map(params![after as i64, limit as i64], row_to_event)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
+    /// Like [`Ledger::events_after`], restricted to a time range in SQL so
+    /// the `ts` index narrows the scan on large ledgers.
+    pub fn events_in_range_after(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<Event>> {
+        let mut where_clauses = vec!["event_id > ?".to_string()];
+        let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(after as i64)];
+        if let Some(s) = since {
+            where_clauses.push("ts >= ?".to_string());
+            param_values.push(Box::new(s.to_rfc3339()));
+        }
+        if let Some(u) = until {
+            where_clauses.push("ts <= ?".to_string());
+            param_values.push(Box::new(u.to_rfc3339()));
+        }
+        param_values.push(Box::new(limit as i64));
+
+        let sql = format!(
+            "SELECT event_id, run_id, ts, kind, agent_run, span_id, parent_span_id, actor,
+                    payload, artifact_refs, hash_prev, hash_self
+             FROM events WHERE {} ORDER BY event_id LIMIT ?",
+            where_clauses.join(" AND ")
+        );
+        let params_ref: Vec<&dyn rusqlite::types::ToSql> =
+            param_values.iter().map(|p| p.as_ref()).collect();
+        let mut stmt = self.db.prepare(&sql)?;
+        let events = stmt
+            .query_map(params_ref.as_slice(), row_to_event)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
     /// Event count grouped by kind.
     pub fn event_count_by_kind(&self) -> Result<HashMap<String, u64>> {
         let mut stmt = self
@@ -566,15 +802,31 @@ impl Ledger {
     /// Returns (is_valid, event_count_checked).
     /// Streams events one at a time to avoid loading entire ledger into memory.
     pub fn verify_chain(&self) -> Result<(bool, u64)> {
+        let check = self.verify_chain_from(None)?;
+        Ok((check.valid, check.checked))
+    }
+
+    /// Verify the chain from `from` (event ID and hash of an event verified
+    /// earlier) onwards, or from genesis. The checkpoint event must still be
+    /// there with the same hash; events before it are not re-checked.
+    pub fn verify_chain_from(&self, from: Option<(u64, &str)>) -> Result<ChainCheck> {
         let mut stmt = self.db.prepare(
             "SELECT event_id, run_id, ts, kind, agent_run, span_id, parent_span_id, actor,
                     payload, artifact_refs, hash_prev, hash_self
-             FROM events ORDER BY event_id",
+             FROM events WHERE event_id >= ?1 ORDER BY event_id",
         )?;
 
-        let mut rows = stmt.query([])?;
+        let mut rows = stmt.query(params![from.map_or(0, |(id, _)| id) as i64])?;
         let mut count: u64 = 0;
         let mut prev_hash: Option<String> = None;
+        let mut last = None;
+        let failed = |count, last| {
+            Ok(ChainCheck {
+                valid: false,
+                checked: count,
+                last,
+            })
+        };
 
         while let Some(row) = rows.next()? {
             let event = row_to_event(row)?;
@@ -582,23 +834,92 @@ impl Ledger {
 
             if !event.verify() {
                 warn!("Ledger event {} failed hash verification", event.event_id.0);
-                return Ok((false, count));
+                return failed(count, last);
             }
 
-            if let Some(ref expected_prev) = prev_hash
-                && event.hash_prev.as_ref() != Some(expected_prev)
-            {
-                warn!("Ledger event {} has broken chain link", event.event_id.0);
-                return Ok((false, count));
+            match (&prev_hash, from) {
+                (Some(expected_prev), _) if event.hash_prev.as_ref() != Some(expected_prev) => {
+                    warn!("Ledger event {} has broken chain link", event.event_id.0);
+                    return failed(count, last);
+                }
+                (None, Some((id, hash))) if event.event_id.0 != id || event.hash_self != hash => {
+                    warn!("Ledger verification checkpoint {} no longer matches", id);
+                    return failed(count, last);
+                }
+                _ => {}
             }
 
+            last = Some((event.event_id.0, event.hash_self.clone()));
             prev_hash = Some(event.hash_self);
         }
 
+        if let (Some((id, _)), None) = (from, &last) {
+            warn!("Ledger verification checkpoint {} is missing", id);
+            return failed(count, last);
+        }
         if count > 0 {
             info!("Ledger hash chain verified for {} events", count);
         }
-        Ok((true, count))
+        Ok(ChainCheck {
+            valid: true,
+            checked: count,
+            last,
+        })
+    }
+
+    /// Re-derive `agent_run` for every stored event using `policy`.
+    ///
+    /// `agent_run` is not part of the hashed form, so rewriting it leaves the
+    /// chain intact. With `dry_run` nothing is written.
+    pub fn backfill_agent_runs(
+        &mut self,
+        policy: &AssignPolicy,
+        dry_run: bool,
+    ) -> Result<BackfillStats> {
+        self.flush()?;
+
+        let mut assigner = RunAssigner::new(policy.clone());
+        let mut stats = BackfillStats::default();
+        let mut updates: Vec<(u64, Option<String>)> = Vec::new();
+        {
+            let mut stmt = self.db.prepare(
+                "SELECT event_id, run_id, ts, kind, agent_run, span_id, parent_span_id, actor,
+                        payload, artifact_refs, hash_prev, hash_self
+                 FROM events ORDER BY event_id",
+            )?;
+            let mut rows = stmt.query([])?;
+            while let Some(row) = rows.next()? {
+                let current: Option<String> = row.get(4)?;
+                let event = row_to_event(row)?;
+                let derived = assigner.assign(&event);
+
+                stats.scanned += 1;
+                if derived.is_some() {
+                    stats.assigned += 1;
+                }
+                if derived != current {
+                    updates.push((event.event_id.0, derived));
+                }
+            }
+        }
+        stats.changed = updates.len() as u64;
+
+        if !dry_run && !updates.is_empty() {
+            let tx = self.db.transaction()?;
+            for (event_id, agent_run) in &updates {
+                tx.execute(
+                    "UPDATE events SET agent_run = ?1 WHERE event_id = ?2",
+                    params![agent_run, *event_id as i64],
+                )?;
+            }
+            tx.commit()?;
+            info!(
+                "Backfilled agent_run for {} of {} events",
+                stats.changed, stats.scanned
+            );
+        }
+
+        Ok(stats)
     }
 
     /// Set a metadata key-value pair.
@@ -625,7 +946,7 @@ impl Ledger {
 
 /// Parse a row from the ledger events table into an Event.
 /// The ledger uses AUTOINCREMENT so event_id comes from the DB.
-fn row_to_event(row: &rusqlite::Row) -> rusqlite::Result<Event> {
+pub(crate) fn row_to_event(row: &rusqlite::Row) -> rusqlite::Result<Event> {
     let event_id: i64 = row.get(0)?;
     let run_id_str: String = row.get(1)?;
     let ts_str: String = row.get(2)?;
@@ -645,18 +966,7 @@ fn row_to_event(row: &rusqlite::Row) -> rusqlite::Result<Event> {
         })?
         .with_timezone(&Utc);
 
-    let kind = match kind_str.as_str() {
-        "RUN_START" => EventKind::RunStart,
-        "RUN_END" => EventKind::RunEnd,
-        "AGENT_EVENT" => EventKind::AgentEvent,
-        "TOOL_CALL" => EventKind::ToolCall,
-        "TOOL_RESULT" => EventKind::ToolResult,
-        "OUTPUT_CHUNK" => EventKind::OutputChunk,
-        "PRESENCE" => EventKind::Presence,
-        "TICK" => EventKind::Tick,
-        "SHUTDOWN" => EventKind::Shutdown,
-        _ => EventKind::Custom,
-    };
+    let kind = EventKind::from_name(&kind_str);
 
     let payload: serde_json::Value = serde_json::from_str(&payload_str).map_err(|e| {
         rusqlite::Error::FromSqlConversionFailure(8, rusqlite::types::Type::Text, Box::new(e))
@@ -707,6 +1017,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_ledger_recovers_unflushed_events_from_spool() {
+        let temp = TempDir::new().unwrap();
+        {
+            let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+            for i in 1..=3 {
+                let event = make_event(i, EventKind::Tick, serde_json::json!({"n": i}));
+                ledger.append_event(event).unwrap();
+            }
+            ledger.flush().unwrap();
+            for i in 4..=5 {
+                let event = make_event(i, EventKind::Tick, serde_json::json!({"n": i}));
+                ledger.append_event(event).unwrap();
+            }
+            // Dropped without flushing, as in a crash
+        }
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        assert_eq!(ledger.total_events(), 5);
+        assert_eq!(ledger.verify_chain().unwrap(), (true, 5));
+
+        // New events keep chaining after recovery
+        let event = make_event(6, EventKind::Tick, serde_json::json!({"n": 6}));
+        ledger.append_event(event).unwrap();
+        ledger.flush().unwrap();
+        assert_eq!(ledger.verify_chain().unwrap(), (true, 6));
+    }
+
     #[test]
     fn test_ledger_hash_chain() {
         let temp = TempDir::new().unwrap();
@@ -739,6 +1076,37 @@ mod tests {
         assert_eq!(count, 10);
     }
 
+    #[test]
+    fn test_events_in_range_after_pages_within_range() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        let base = Utc::now() - chrono::Duration::hours(10);
+        for i in 1..=10 {
+            let mut event = make_event(i, EventKind::Tick, serde_json::json!({"n": i}));
+            event.ts = base + chrono::Duration::hours(i as i64);
+            ledger.append_event(event).unwrap();
+        }
+        ledger.flush().unwrap();
+
+        let since = Some(base + chrono::Duration::hours(3));
+        let until = Some(base + chrono::Duration::hours(8));
+        let first = ledger.events_in_range_after(since, until, 0, 4).unwrap();
+        let ids: Vec<u64> = first.iter().map(|e| e.event_id.0).collect();
+        assert_eq!(ids, vec![3, 4, 5, 6]);
+
+        let rest = ledger.events_in_range_after(since, until, 6, 4).unwrap();
+        let ids: Vec<u64> = rest.iter().map(|e| e.event_id.0).collect();
+        assert_eq!(ids, vec![7, 8]);
+
+        assert_eq!(
+            ledger
+                .events_in_range_after(None, None, 0, 100)
+                .unwrap()
+                .len(),
+            10
+        );
+    }
+
     #[test]
     fn test_ledger_tamper_detection() {
         let temp = TempDir::new().unwrap();
@@ -763,6 +1131,60 @@ mod tests {
         assert!(!valid);
     }
 
+    #[test]
+    fn test_verify_chain_from_checkpoint() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        for i in 1..=5 {
+            let event = make_event(i, EventKind::ToolCall, serde_json::json!({"step": i}));
+            ledger.append_event(event).unwrap();
+        }
+        ledger.flush().unwrap();
+
+        let full = ledger.verify_chain_from(None).unwrap();
+        assert!(full.valid);
+        assert_eq!(full.checked, 5);
+        let (id, hash) = full.last.clone().unwrap();
+        assert_eq!(id, 5);
+
+        for i in 6..=7 {
+            let event = make_event(i, EventKind::ToolCall, serde_json::json!({"step": i}));
+            ledger.append_event(event).unwrap();
+        }
+        ledger.flush().unwrap();
+
+        // Only the checkpoint and the new events are read
+        let check = ledger.verify_chain_from(Some((id, &hash))).unwrap();
+        assert!(check.valid);
+        assert_eq!(check.checked, 3);
+        assert_eq!(check.last.as_ref().unwrap().0, 7);
+
+        // Nothing new: just the checkpoint itself
+        let (id, hash) = check.last.unwrap();
+        assert_eq!(
+            ledger.verify_chain_from(Some((id, &hash))).unwrap().checked,
+            1
+        );
+
+        // A checkpoint that no longer matches the ledger fails
+        assert!(!ledger.verify_chain_from(Some((id, "bogus"))).unwrap().valid);
+        assert!(!ledger.verify_chain_from(Some((99, &hash))).unwrap().valid);
+
+        // Tampering after the checkpoint is caught
+        ledger
+            .db
+            .execute(
+                "UPDATE events SET payload = '{\"step\":999}' WHERE event_id = 7",
+                [],
+            )
+            .unwrap();
+        let check = ledger
+            .verify_chain_from(Some((5, &full.last.unwrap().1)))
+            .unwrap();
+        assert!(!check.valid);
+        assert_eq!(check.last.unwrap().0, 6);
+    }
+
     #[test]
     fn test_ledger_agent_run_extraction() {
         let temp = TempDir::new().unwrap();
@@ -812,6 +1234,124 @@ mod tests {
         assert_eq!(abc.tool_call_count, 2);
     }
 
+    #[test]
+    fn test_assign_state_survives_restart() {
+        let temp = TempDir::new().unwrap();
+        {
+            let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+            ledger.set_assign_policy(AssignPolicy::correlated());
+            ledger
+                .append_event(make_event(
+                    1,
+                    EventKind::AgentEvent,
+                    serde_json::json!({"data": {"runId": "run-1", "sessionKey": "main"}}),
+                ))
+                .unwrap();
+            ledger.flush().unwrap();
+        }
+
+        // The session continues after the daemon restarts
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        ledger.set_assign_policy(AssignPolicy::correlated());
+        ledger
+            .append_event(make_event(
+                2,
+                EventKind::OutputChunk,
+                serde_json::json!({"data": {"sessionKey": "main", "text": "done"}}),
+            ))
+            .unwrap();
+        ledger.flush().unwrap();
+        assert_eq!(ledger.get_agent_run_events("run-1").unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_failed_flush_rolls_back_assigner() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        ledger
+            .append_event(make_event(
+                1,
+                EventKind::AgentEvent,
+                serde_json::json!({"data": {"runId": "run-1", "stream": "lifecycle", "data": {"phase": "end"}}}),
+            ))
+            .unwrap();
+        ledger.db.execute_batch("DROP TABLE meta").unwrap();
+        assert!(ledger.flush().is_err());
+        assert!(ledger.take_completed_agent_runs().is_empty());
+        assert_eq!(ledger.root_hash(), None);
+
+        // The retry assigns the batch again
+        ledger
+            .db
+            .execute_batch("CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL)")
+            .unwrap();
+        ledger.flush().unwrap();
+        assert_eq!(ledger.take_completed_agent_runs(), ["run-1"]);
+        assert_eq!(ledger.verify_chain().unwrap(), (true, 1));
+    }
+
+    #[test]
+    fn test_assign_state_written_only_when_changed() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        ledger
+            .append_event(make_event(1, EventKind::Tick, serde_json::json!({})))
+            .unwrap();
+        ledger.flush().unwrap();
+        let saved = ledger.saved_assign_state.clone();
+        assert!(saved.is_some());
+
+        // An unchanged state is not rewritten: a read-only meta still commits
+        ledger
+            .db
+            .execute_batch(
+                "CREATE TRIGGER no_meta BEFORE UPDATE ON meta BEGIN SELECT RAISE(ABORT, 'ro'); END;
+                 CREATE TRIGGER no_meta_insert BEFORE INSERT ON meta BEGIN SELECT RAISE(ABORT, 'ro'); END;",
+            )
+            .unwrap();
+        ledger
+            .append_event(make_event(2, EventKind::Tick, serde_json::json!({})))
+            .unwrap();
+        ledger.flush().unwrap();
+        assert_eq!(ledger.saved_assign_state, saved);
+    }
+
+    #[test]
+    fn test_ledger_backfill_agent_runs() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        ledger.set_assign_policy(AssignPolicy::new(vec![
+            crate::assign::AssignStrategy::RunId,
+        ]));
+
+        ledger
+            .append_event(make_event(
+                1,
+                EventKind::AgentEvent,
+                serde_json::json!({"data": {"runId": "run-1", "sessionKey": "main"}}),
+            ))
+            .unwrap();
+        ledger
+            .append_event(make_event(
+                2,
+                EventKind::OutputChunk,
+                serde_json::json!({"data": {"sessionKey": "main", "state": "final", "text": "done"}}),
+            ))
+            .unwrap();
+        ledger.flush().unwrap();
+        assert_eq!(ledger.get_agent_run_events("run-1").unwrap().len(), 1);
+
+        let policy = AssignPolicy::correlated();
+        let dry = ledger.backfill_agent_runs(&policy, true).unwrap();
+        assert_eq!((dry.scanned, dry.changed), (2, 1));
+        assert_eq!(ledger.get_agent_run_events("run-1").unwrap().len(), 1);
+
+        let stats = ledger.backfill_agent_runs(&policy, false).unwrap();
+        assert_eq!((stats.changed, stats.assigned), (1, 2));
+        assert_eq!(ledger.get_agent_run_events("run-1").unwrap().len(), 2);
+        assert_eq!(ledger.verify_chain().unwrap(), (true, 2));
+    }
+
     #[test]
     fn test_ledger_search_events() {
         let temp = TempDir::new().unwrap();
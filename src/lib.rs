@@ -7,16 +7,37 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+pub mod admin;
+pub mod artifact;
+pub mod assign;
+pub mod audit;
+pub mod coverage;
 pub mod daemon;
+pub mod filter;
 pub mod gateway;
 pub mod ledger;
+pub mod lock;
+pub mod logging;
 #[cfg(feature = "mcp")]
 pub mod mcp;
+pub mod metrics;
+pub mod otlp;
+pub mod pii;
+pub mod progress;
 pub mod record;
 pub mod redact;
+pub mod reload;
 pub mod replay;
+pub mod reredact;
 pub mod security;
+pub mod sessionize;
+pub mod settings;
+pub mod sink;
+pub mod spool;
 pub mod storage;
+pub mod subscribe;
+pub mod systemd;
+pub mod vault;
 pub mod viewer;
 
 /// Unique identifier for a recorded run
@@ -61,10 +82,30 @@ pub enum EventKind {
     Tick,
     /// Gateway shutdown
     Shutdown,
+    /// Ingest filter policy in effect (what is dropped, sampled or aggregated)
+    FilterPolicy,
+    /// Aggregated stand-in for events collapsed by the ingest filter
+    FilterSummary,
+    /// Daemon configuration reloaded while running
+    ConfigChange,
+    /// Clawprint's own action (lifecycle, verification, export, evidence access)
+    Audit,
+    /// Redacted originals of an event were revealed from the vault
+    Reveal,
+    /// A redaction pass rewrote the ledger as a new generation
+    RedactionPass,
     /// Custom/unknown
     Custom,
 }
 
+impl EventKind {
+    /// Parse a stored kind name (e.g. "RUN_START"); unknown names map to `Custom`.
+    pub fn from_name(name: &str) -> Self {
+        serde_json::from_value(serde_json::Value::String(name.to_string()))
+            .unwrap_or(EventKind::Custom)
+    }
+}
+
 /// Core event structure - stored in ledger
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Event {
@@ -175,6 +216,32 @@ struct CanonicalEvent {
     hash_prev: Option<String>,
 }
 
+/// How a run's case file was sealed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RunStatus {
+    /// Finalized by the recorder on stop
+    #[default]
+    Complete,
+    /// Ended with an unclean RUN_END, or finalized by `clawprint recover`
+    /// after the recorder died
+    Incomplete,
+}
+
+/// Where a case file's events came from in the continuous daemon ledger
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LedgerRange {
+    /// Agent run the events were grouped under
+    pub agent_run: String,
+    /// First and last ledger event_id of the run (other runs may interleave)
+    pub first_event_id: u64,
+    pub last_event_id: u64,
+    /// Number of ledger events copied into the case file
+    pub event_count: u64,
+    /// Ledger `hash_self` of the last copied event
+    pub last_hash: String,
+}
+
 /// Run metadata stored in meta.json
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunMeta {
@@ -185,6 +252,11 @@ pub struct RunMeta {
     pub root_hash: String,
     pub gateway_url: String,
     pub version: String,
+    #[serde(default)]
+    pub status: RunStatus,
+    /// Set when the run was materialized from the daemon ledger
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ledger_range: Option<LedgerRange>,
 }
 
 impl RunMeta {
@@ -197,6 +269,8 @@ impl RunMeta {
             root_hash: String::new(),
             gateway_url,
             version: env!("CARGO_PKG_VERSION").to_string(),
+            status: RunStatus::Complete,
+            ledger_range: None,
         }
     }
 }
@@ -208,6 +282,8 @@ pub struct Config {
     pub output_dir: std::path::PathBuf,
     /// Whether to redact secrets
     pub redact_secrets: bool,
+    /// Redaction rules, compiled into a `redact::Redactor` by the recorder
+    pub redaction: redact::RedactionRules,
     /// Gateway WebSocket URL
     pub gateway_url: String,
     /// Gateway auth token
@@ -216,6 +292,16 @@ pub struct Config {
     pub batch_size: usize,
     /// Flush interval in milliseconds
     pub flush_interval_ms: u64,
+    /// Ingest filter applied before events reach storage
+    pub filter: filter::FilterPolicy,
+    /// How often the crash spool is fsynced
+    pub spool_sync: spool::SpoolSync,
+    /// How ledger events are attributed to agent runs
+    pub assign: assign::AssignPolicy,
+    /// Also write each completed agent run to its own `runs/<id>` case file (daemon)
+    pub sessionize: bool,
+    /// External systems that receive committed events (daemon)
+    pub sinks: Vec<sink::SinkConfig>,
 }
 
 impl Default for Config {
@@ -223,10 +309,16 @@ impl Default for Config {
         Self {
             output_dir: std::path::PathBuf::from("./clawprints"),
             redact_secrets: true,
+            redaction: redact::RedactionRules::default(),
             gateway_url: "ws://127.0.0.1:18789".to_string(),
             auth_token: None,
             batch_size: 100,
             flush_interval_ms: 200,
+            filter: filter::FilterPolicy::default(),
+            spool_sync: spool::SpoolSync::default(),
+            assign: assign::AssignPolicy::default(),
+            sessionize: false,
+            sinks: Vec::new(),
         }
     }
 }
@@ -360,6 +452,12 @@ mod tests {
             (EventKind::Presence, "\"PRESENCE\""),
             (EventKind::Tick, "\"TICK\""),
             (EventKind::Shutdown, "\"SHUTDOWN\""),
+            (EventKind::FilterPolicy, "\"FILTER_POLICY\""),
+            (EventKind::FilterSummary, "\"FILTER_SUMMARY\""),
+            (EventKind::ConfigChange, "\"CONFIG_CHANGE\""),
+            (EventKind::Audit, "\"AUDIT\""),
+            (EventKind::Reveal, "\"REVEAL\""),
+            (EventKind::RedactionPass, "\"REDACTION_PASS\""),
             (EventKind::Custom, "\"CUSTOM\""),
         ];
 
@@ -369,6 +467,10 @@ mod tests {
 
             let deserialized: EventKind = serde_json::from_str(&json).unwrap();
             assert_eq!(deserialized, kind, "Deserialization of {}", json);
+
+            let name = expected_json.trim_matches('"');
+            assert_eq!(EventKind::from_name(name), kind, "from_name of {}", name);
         }
+        assert_eq!(EventKind::from_name("SOMETHING_ELSE"), EventKind::Custom);
     }
 }
@@ -0,0 +1,236 @@
+//! Single-writer lock for the ledger directory
+//!
+//! Two writers on one `ledger.sqlite` would each keep their own event count
+//! and last hash in memory, interleave event_ids and fork the hash chain.
+//! `Ledger::open` therefore takes an exclusive OS lock on `{dir}/ledger.lock`
+//! and writes its PID, host and start time into the file, so a second writer
+//! can report who holds it.
+//!
+//! The OS releases the lock when the process exits, even after a crash or
+//! `kill -9`. A lock file whose lock can be taken is therefore stale and is
+//! reclaimed. Read-only access (viewer, MCP, the chain verifier) never takes
+//! the lock.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions, TryLockError};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+pub const LOCK_FILE: &str = "ledger.lock";
+
+/// Who holds (or last held) the writer lock.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockOwner {
+    pub pid: u32,
+    pub host: String,
+    pub started_at: DateTime<Utc>,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            host: hostname(),
+            started_at: Utc::now(),
+        }
+    }
+}
+
+impl std::fmt::Display for LockOwner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {} on {} (since {})",
+            self.pid,
+            self.host,
+            self.started_at.to_rfc3339()
+        )
+    }
+}
+
+/// Another writer holds the lock (returned inside `anyhow::Error`).
+#[derive(Debug)]
+pub struct LedgerLocked {
+    pub dir: PathBuf,
+    pub holder: Option<LockOwner>,
+}
+
+impl std::fmt::Display for LedgerLocked {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Ledger at {:?} is already open for writing by ",
+            self.dir
+        )?;
+        match &self.holder {
+            Some(owner) => write!(f, "{}", owner)?,
+            None => write!(f, "another process")?,
+        }
+        write!(
+            f,
+            ". Stop that writer first; read-only commands (view, mcp) can run alongside it."
+        )
+    }
+}
+
+impl std::error::Error for LedgerLocked {}
+
+/// Exclusive write access to a ledger directory, released on drop.
+#[derive(Debug)]
+pub struct WriterLock {
+    file: File,
+    path: PathBuf,
+}
+
+impl WriterLock {
+    /// Take the lock, or fail with the current holder if another writer has it.
+    pub fn acquire(dir: &Path) -> Result<Self> {
+        let path = dir.join(LOCK_FILE);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Cannot open lock file {:?}", path))?;
+
+        match file.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => {
+                return Err(LedgerLocked {
+                    dir: dir.to_path_buf(),
+                    holder: read_owner(&mut file),
+                }
+                .into());
+            }
+            Err(TryLockError::Error(e)) => {
+                return Err(e).with_context(|| format!("Cannot lock {:?}", path));
+            }
+        }
+
+        if let Some(stale) = read_owner(&mut file) {
+            warn!(
+                "Reclaiming stale ledger lock left by {} (process exited without releasing it)",
+                stale
+            );
+        }
+
+        let owner = LockOwner::current();
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(serde_json::to_string(&owner)?.as_bytes())?;
+        file.sync_all()?;
+
+        Ok(Self { file, path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for WriterLock {
+    fn drop(&mut self) {
+        // Clear the owner so a leftover file isn't mistaken for a crash
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+fn read_owner(file: &mut File) -> Option<LockOwner> {
+    let mut contents = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut contents).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+pub(crate) fn hostname() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/hostname")
+        .or_else(|_| std::fs::read_to_string("/etc/hostname"))
+        .ok()
+        .map(|h| h.trim().to_string())
+        .filter(|h| !h.is_empty())
+        .or_else(|| std::env::var("HOSTNAME").ok())
+        .or_else(|| std::env::var("COMPUTERNAME").ok())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_second_writer_rejected_with_holder() {
+        let dir = tempfile::tempdir().unwrap();
+        let _lock = WriterLock::acquire(dir.path()).unwrap();
+
+        let err = WriterLock::acquire(dir.path()).unwrap_err();
+        let locked = err.downcast_ref::<LedgerLocked>().unwrap();
+        assert_eq!(locked.holder.as_ref().unwrap().pid, std::process::id());
+        let err = err.to_string();
+        assert!(err.contains("already open for writing"), "{}", err);
+        assert!(
+            err.contains(&format!("pid {}", std::process::id())),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_lock_released_and_cleared_on_drop() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = WriterLock::acquire(dir.path()).unwrap();
+        drop(lock);
+        assert!(
+            std::fs::read_to_string(dir.path().join(LOCK_FILE))
+                .unwrap()
+                .is_empty()
+        );
+        WriterLock::acquire(dir.path()).unwrap();
+    }
+
+    #[test]
+    fn test_stale_lock_reclaimed() {
+        let dir = tempfile::tempdir().unwrap();
+        // A crashed writer leaves its owner in the file but no OS lock
+        let crashed = LockOwner {
+            pid: 999_999,
+            host: "elsewhere".to_string(),
+            started_at: Utc::now(),
+        };
+        std::fs::write(
+            dir.path().join(LOCK_FILE),
+            serde_json::to_string(&crashed).unwrap(),
+        )
+        .unwrap();
+
+        let lock = WriterLock::acquire(dir.path()).unwrap();
+        let owner: LockOwner =
+            serde_json::from_str(&std::fs::read_to_string(lock.path()).unwrap()).unwrap();
+        assert_eq!(owner.pid, std::process::id());
+    }
+
+    #[test]
+    fn test_unreadable_owner_still_reported_as_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = WriterLock::acquire(dir.path()).unwrap();
+        std::fs::write(lock.path(), "not json").unwrap();
+
+        let err = WriterLock::acquire(dir.path()).unwrap_err();
+        assert!(err.downcast_ref::<LedgerLocked>().unwrap().holder.is_none());
+        assert!(err.to_string().contains("another process"));
+    }
+
+    #[test]
+    fn test_ledger_open_takes_lock_but_readers_do_not() {
+        let dir = tempfile::tempdir().unwrap();
+        let _writer = crate::ledger::Ledger::open(dir.path(), 10).unwrap();
+
+        let err = crate::ledger::Ledger::open(dir.path(), 10).err().unwrap();
+        assert!(err.downcast_ref::<LedgerLocked>().is_some());
+        crate::ledger::Ledger::open_readonly(dir.path()).unwrap();
+    }
+}
@@ -0,0 +1,180 @@
+//! Log output formats
+//!
+//! Human-readable logs by default; `--log-format json` writes one JSON object
+//! per line (timestamp, level, target, message, span names and fields) for
+//! journald, Loki or any other collector that parses structured logs.
+
+use serde_json::{Map, Value};
+use std::fmt;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Subscriber};
+use tracing_subscriber::fmt::format::Writer;
+use tracing_subscriber::fmt::{FmtContext, FormatEvent, FormatFields};
+use tracing_subscriber::registry::LookupSpan;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LogFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl std::str::FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            other => Err(anyhow::anyhow!(
+                "unknown log format '{}' (expected text or json)",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Text => "text",
+            Self::Json => "json",
+        })
+    }
+}
+
+/// Install the global subscriber. `filter` uses `RUST_LOG` syntax.
+pub fn init(format: LogFormat, filter: &str) {
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.with_ansi(false).event_format(JsonFormat).init(),
+    }
+}
+
+/// Formats each event as a single-line JSON object.
+pub struct JsonFormat;
+
+impl<S, N> FormatEvent<S, N> for JsonFormat
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    N: for<'a> FormatFields<'a> + 'static,
+{
+    fn format_event(
+        &self,
+        ctx: &FmtContext<'_, S, N>,
+        mut writer: Writer<'_>,
+        event: &Event<'_>,
+    ) -> fmt::Result {
+        writeln!(writer, "{}", to_json(event, ctx.event_scope()))
+    }
+}
+
+fn to_json<'a, R>(
+    event: &Event<'_>,
+    scope: Option<tracing_subscriber::registry::Scope<'a, R>>,
+) -> Value
+where
+    R: LookupSpan<'a>,
+{
+    let meta = event.metadata();
+    let mut fields = JsonFields::default();
+    event.record(&mut fields);
+
+    let mut line = Map::new();
+    line.insert(
+        "timestamp".into(),
+        chrono::Utc::now()
+            .to_rfc3339_opts(chrono::SecondsFormat::Micros, true)
+            .into(),
+    );
+    line.insert("level".into(), meta.level().as_str().into());
+    line.insert("target".into(), meta.target().into());
+    if let Some(message) = fields.0.remove("message") {
+        line.insert("message".into(), message);
+    }
+    if let Some(scope) = scope {
+        let spans: Vec<Value> = scope.from_root().map(|s| s.name().into()).collect();
+        line.insert("spans".into(), spans.into());
+    }
+    for (key, value) in fields.0 {
+        line.entry(key).or_insert(value);
+    }
+    Value::Object(line)
+}
+
+#[derive(Default)]
+struct JsonFields(Map<String, Value>);
+
+impl Visit for JsonFields {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().into(), format!("{:?}", value).into());
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_f64(&mut self, field: &Field, value: f64) {
+        self.0.insert(field.name().into(), value.into());
+    }
+
+    fn record_bool(&mut self, field: &Field, value: bool) {
+        self.0.insert(field.name().into(), value.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_lines() {
+        let buf = Buffer::default();
+        let writer = buf.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_ansi(false)
+            .event_format(JsonFormat)
+            .with_writer(move || writer.clone())
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("connection");
+            let _guard = span.enter();
+            tracing::warn!(events = 3u64, url = "ws://gw", "Gateway \"disconnected\"");
+        });
+
+        let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let line: Value = serde_json::from_str(out.trim()).unwrap();
+        assert_eq!(line["level"], "WARN");
+        assert_eq!(line["message"], "Gateway \"disconnected\"");
+        assert_eq!(line["events"], 3);
+        assert_eq!(line["url"], "ws://gw");
+        assert_eq!(line["spans"], serde_json::json!(["connection"]));
+        assert!(line["timestamp"].as_str().unwrap().ends_with('Z'));
+    }
+}
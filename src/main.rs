@@ -8,11 +8,14 @@
 //!   clawprint stats --run <run_id>
 
 use anyhow::{Result, bail};
-use clap::{Parser, Subcommand};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use clap::{Args, Parser, Subcommand};
+use indicatif::{ProgressBar, ProgressStyle};
 use owo_colors::OwoColorize;
 use std::io::{IsTerminal, Write as _};
 use std::net::Ipv4Addr;
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tracing::{info, warn};
 
@@ -65,6 +68,40 @@ fn parse_host(host: &str) -> Result<[u8; 4]> {
     Ok(addr.octets())
 }
 
+/// Parse RFC 3339, YYYY-MM-DD (midnight UTC) or a relative time such as
+/// `30m`, `24h` or `7d` before now.
+fn parse_time(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+    if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+        return Ok(d.and_time(NaiveTime::MIN).and_utc());
+    }
+    let (n, unit) = s.split_at(s.len().saturating_sub(1));
+    let n: i64 = n.parse().map_err(|_| {
+        anyhow::anyhow!(
+            "Invalid time '{}': use RFC 3339, YYYY-MM-DD or 30m/24h/7d",
+            s
+        )
+    })?;
+    let ago = match unit {
+        "m" => chrono::Duration::minutes(n),
+        "h" => chrono::Duration::hours(n),
+        "d" => chrono::Duration::days(n),
+        _ => bail!("Invalid time unit in '{}': use m, h or d", s),
+    };
+    Ok(Utc::now() - ago)
+}
+
+fn parse_header(s: &str) -> Result<(String, String)> {
+    match s.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.to_string()))
+        }
+        _ => bail!("Invalid header '{}': expected NAME=VALUE", s),
+    }
+}
+
 /// Print the large CLAWPRINT ASCII text banner (for startup/main display)
 fn print_banner_large() {
     let art = r#"
@@ -103,11 +140,28 @@ use rmcp::transport::streamable_http_server::{
 };
 
 use clawprint::{
-    Config,
-    daemon::{run_daemon, run_daemon_with_shutdown},
-    record::RecordingSession,
+    Config, RunStatus,
+    admin::start_admin_with_shutdown,
+    assign::{AssignPolicy, AssignStrategy},
+    audit::{self, AuditEntry, AuditLog},
+    coverage::{self, Coverage, ReportFormat},
+    daemon::{DaemonOptions, run_daemon_with_options},
+    filter::{FilterPolicy, FilterRule},
+    ledger::Ledger,
+    lock::LedgerLocked,
+    logging::LogFormat,
+    metrics::DaemonMetrics,
+    otlp::{OtlpExporter, export_range},
+    progress::{Observer, Progress, format_bytes},
+    record::{RecordingSession, SessionOptions},
+    redact::{RedactionRules, Redactor},
+    reload::{ConfigLoader, ReloadHandle},
     replay::{diff_runs, generate_transcript, replay_run},
-    storage::{RunStorage, list_runs_with_stats, resolve_run_id},
+    reredact::{PassOptions, Target, redaction_pass},
+    settings::Settings,
+    spool::SpoolSync,
+    storage::{RunStorage, list_runs_with_stats, orphaned_runs, recover_run, resolve_run_id},
+    vault::{self, Identity, RevealHandle},
     viewer::{start_viewer, start_viewer_with_shutdown},
 };
 
@@ -116,6 +170,15 @@ use clawprint::{
 #[command(about = "Every molt leaves a mark. Trace. Verify. Trust.")]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 struct Cli {
+    /// Config file (default: first of ./clawprint.toml, $XDG_CONFIG_HOME/clawprint/clawprint.toml, /etc/clawprint/clawprint.toml)
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Log format: text or json (one object per line)
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "text")]
+    log_format: LogFormat,
+    /// Progress output for record, daemon and serve: auto, spinner, log or quiet
+    #[arg(long, global = true, value_name = "MODE", default_value = "auto")]
+    progress: ProgressMode,
     #[command(subcommand)]
     command: Commands,
 }
@@ -124,30 +187,17 @@ struct Cli {
 enum Commands {
     /// Record agent runs from gateway
     Record {
-        /// Gateway WebSocket URL
-        #[arg(short, long, default_value = "ws://127.0.0.1:18789")]
-        gateway: String,
-        /// Output directory for recordings
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[command(flatten)]
+        recorder: RecorderArgs,
         /// Run name/ID (auto-generated if not specified)
         #[arg(long)]
         run_name: Option<String>,
-        /// Gateway auth token (auto-discovered from ~/.openclaw/openclaw.json if omitted)
-        #[arg(short, long)]
-        token: Option<String>,
-        /// Disable secret redaction
-        #[arg(long)]
-        no_redact: bool,
-        /// Batch size for SQLite commits
-        #[arg(long, default_value = "100")]
-        batch_size: usize,
     },
     /// List recorded runs
     List {
         /// Output directory
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
     },
     /// View a recorded run (opens HTTP viewer)
     View {
@@ -155,17 +205,17 @@ enum Commands {
         #[arg(short, long)]
         run: String,
         /// Output directory
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
         /// Open in browser automatically
         #[arg(long)]
         open: bool,
         /// Host to bind the viewer (use 0.0.0.0 for network access)
-        #[arg(long, default_value = "127.0.0.1")]
-        host: String,
+        #[arg(long)]
+        host: Option<String>,
         /// Port for viewer server
-        #[arg(short, long, default_value = "8080")]
-        port: u16,
+        #[arg(short, long)]
+        port: Option<u16>,
         /// Bearer token for HTTP auth (recommended when using --host 0.0.0.0)
         #[arg(long)]
         token: Option<String>,
@@ -176,8 +226,8 @@ enum Commands {
         #[arg(short, long)]
         run: String,
         /// Output directory
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
         /// Offline mode (no gateway contact)
         #[arg(long)]
         offline: bool,
@@ -194,8 +244,8 @@ enum Commands {
         #[arg(long)]
         run_b: String,
         /// Output directory
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
     },
     /// Verify run integrity
     Verify {
@@ -203,8 +253,112 @@ enum Commands {
         #[arg(short, long)]
         run: String,
         /// Output directory
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Finalize runs left unsealed by a crash, replaying spooled events
+    Recover {
+        /// Output directory
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Re-derive agent run grouping for events already in the daemon ledger
+    Backfill {
+        /// Directory containing the ledger
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Run-assignment strategy (run-id, session-key, chat, seq-window:N), repeatable [default: run-id]
+        #[arg(long = "assign", value_name = "STRATEGY")]
+        assign: Vec<AssignStrategy>,
+        /// Report what would change without writing
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Export agent runs from the daemon ledger to an OpenTelemetry collector
+    Export {
+        /// OTLP/HTTP collector URL, e.g. http://localhost:4318
+        #[arg(long, value_name = "URL")]
+        otlp: String,
+        /// Directory containing the ledger
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+        /// Start of the range: RFC 3339, YYYY-MM-DD, or relative (30m, 24h, 7d)
+        #[arg(long, value_parser = parse_time)]
+        since: Option<DateTime<Utc>>,
+        /// End of the range, same formats as --since
+        #[arg(long, value_parser = parse_time)]
+        until: Option<DateTime<Utc>>,
+        /// Extra request header as NAME=VALUE, repeatable
+        #[arg(long = "header", value_name = "NAME=VALUE", value_parser = parse_header)]
+        headers: Vec<(String, String)>,
+        /// service.name resource attribute
+        #[arg(long, default_value = "clawprint")]
+        service_name: String,
+    },
+    /// Reveal the redacted originals in a ledger event from the redaction vault
+    Reveal {
+        /// Event ID in the ledger (or in the run with --run)
+        #[arg(long)]
+        event: u64,
+        /// Reveal from a recorded run's case file instead of the daemon ledger
+        #[arg(long)]
+        run: Option<String>,
+        /// Investigator private key file (from `clawprint vault keygen`)
+        #[arg(long, value_name = "FILE")]
+        key: PathBuf,
+        /// Admin API of the daemon holding the ledger [default: from admin.host and admin.port]
+        #[arg(long, value_name = "URL")]
+        admin_url: Option<String>,
+        /// Directory containing the ledger
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Apply redaction rules to traces already recorded, as a new ledger generation
+    Redact {
+        /// Redact the daemon ledger
+        #[arg(long, conflicts_with = "run", required_unless_present = "run")]
+        ledger: bool,
+        /// Redact a run's case file instead
+        #[arg(long)]
+        run: Option<String>,
+        /// Rules file applied on top of the configured rules
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
+        /// Report what would be redacted without writing
+        #[arg(long)]
+        dry_run: bool,
+        /// Directory containing the ledger
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Report redaction coverage: counts per detector, top redacted paths, and survivors
+    RedactionReport {
+        /// Report on a run's case file instead of the daemon ledger
+        #[arg(long)]
+        run: Option<String>,
+        /// Start of the range: RFC 3339, YYYY-MM-DD, or relative (30m, 24h, 7d)
+        #[arg(long, value_parser = parse_time)]
+        since: Option<DateTime<Utc>>,
+        /// End of the range, same formats as --since
+        #[arg(long, value_parser = parse_time)]
+        until: Option<DateTime<Utc>>,
+        /// Rules file applied on top of the configured rules when probing for survivors
+        #[arg(long, value_name = "FILE")]
+        rules: Option<PathBuf>,
+        /// Survivors to list (all are counted)
+        #[arg(long, default_value_t = coverage::DEFAULT_LIMIT)]
+        limit: usize,
+        /// Output format: text or json
+        #[arg(long, value_name = "FORMAT", default_value = "text")]
+        format: ReportFormat,
+        /// Directory containing the ledger
+        #[arg(short, long)]
+        out: Option<PathBuf>,
+    },
+    /// Manage redaction vault keys
+    Vault {
+        #[command(subcommand)]
+        action: VaultAction,
     },
     /// Show run statistics
     Stats {
@@ -212,24 +366,24 @@ enum Commands {
         #[arg(short, long)]
         run: String,
         /// Output directory
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
     },
     /// Start MCP server (for Claude Desktop integration)
     #[cfg(feature = "mcp")]
     Mcp {
         /// Directory containing the ledger
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
         /// Transport: "stdio" (default, for local Claude Desktop) or "sse" (for network access)
-        #[arg(long, default_value = "stdio")]
-        transport: String,
+        #[arg(long)]
+        transport: Option<String>,
         /// Host to bind SSE server (only used with --transport sse)
-        #[arg(long, default_value = "0.0.0.0")]
-        host: String,
+        #[arg(long)]
+        host: Option<String>,
         /// Port for SSE server (only used with --transport sse)
-        #[arg(short, long, default_value = "3000")]
-        port: u16,
+        #[arg(short, long)]
+        port: Option<u16>,
         /// Bearer token for HTTP auth (recommended for SSE transport)
         #[arg(long)]
         token: Option<String>,
@@ -240,14 +394,14 @@ enum Commands {
         #[arg(short, long)]
         run: Option<String>,
         /// Output directory
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
+        #[arg(short, long)]
+        out: Option<PathBuf>,
         /// Host to bind the viewer (use 0.0.0.0 for network access)
-        #[arg(long, default_value = "127.0.0.1")]
-        host: String,
+        #[arg(long)]
+        host: Option<String>,
         /// Port for viewer server
-        #[arg(short, long, default_value = "8080")]
-        port: u16,
+        #[arg(short, long)]
+        port: Option<u16>,
         /// Bearer token for HTTP auth (recommended when using --host 0.0.0.0)
         #[arg(long)]
         token: Option<String>,
@@ -264,56 +418,108 @@ enum Commands {
         #[cfg(feature = "mcp")]
         #[arg(long)]
         mcp: bool,
-        /// Gateway WebSocket URL (for daemon)
-        #[arg(short, long, default_value = "ws://127.0.0.1:18789")]
-        gateway: String,
-        /// Output directory
-        #[arg(short, long, default_value = "./clawprints")]
-        out: PathBuf,
-        /// Gateway auth token (auto-discovered from ~/.openclaw/openclaw.json if omitted)
-        #[arg(short, long)]
-        token: Option<String>,
-        /// Disable secret redaction (for daemon)
-        #[arg(long)]
-        no_redact: bool,
-        /// Batch size for SQLite commits (for daemon)
-        #[arg(long, default_value = "100")]
-        batch_size: usize,
+        #[command(flatten)]
+        recording: DaemonArgs,
         /// Host to bind the viewer
-        #[arg(long, default_value = "127.0.0.1")]
-        viewer_host: String,
+        #[arg(long)]
+        viewer_host: Option<String>,
         /// Port for viewer server
-        #[arg(long, default_value = "8080")]
-        viewer_port: u16,
+        #[arg(long)]
+        viewer_port: Option<u16>,
         /// Host to bind the MCP SSE server
         #[cfg(feature = "mcp")]
-        #[arg(long, default_value = "0.0.0.0")]
-        mcp_host: String,
+        #[arg(long)]
+        mcp_host: Option<String>,
         /// Port for MCP SSE server
         #[cfg(feature = "mcp")]
-        #[arg(long, default_value = "3000")]
-        mcp_port: u16,
+        #[arg(long)]
+        mcp_port: Option<u16>,
     },
     /// Run as a 24/7 daemon recording to a continuous ledger
     Daemon {
-        /// Gateway WebSocket URL
-        #[arg(short, long, default_value = "ws://127.0.0.1:18789")]
-        gateway: String,
-        /// Output directory for the ledger
-        #[arg(short, long, default_value = "./clawprints")]
+        #[command(flatten)]
+        recording: DaemonArgs,
+    },
+    /// Inspect the clawprint.toml configuration
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum VaultAction {
+    /// Create an investigator key pair; prints the public key for [redaction.vault]
+    Keygen {
+        /// Private key file to create
+        #[arg(long, value_name = "FILE")]
         out: PathBuf,
-        /// Gateway auth token (auto-discovered from ~/.openclaw/openclaw.json if omitted)
-        #[arg(short, long)]
-        token: Option<String>,
-        /// Disable secret redaction
-        #[arg(long)]
-        no_redact: bool,
-        /// Batch size for SQLite commits
-        #[arg(long, default_value = "100")]
-        batch_size: usize,
     },
 }
 
+#[derive(Subcommand)]
+enum ConfigAction {
+    /// Print the effective configuration (file merged over defaults) with secrets masked
+    Show,
+}
+
+/// Recording options shared by `record`, `daemon` and `serve`. Options left
+/// unset fall back to clawprint.toml, then to the built-in defaults.
+#[derive(Args, Clone)]
+struct RecorderArgs {
+    /// Gateway WebSocket URL [default: ws://127.0.0.1:18789]
+    #[arg(short, long)]
+    gateway: Option<String>,
+    /// Output directory for recordings and the ledger [default: ./clawprints]
+    #[arg(short, long)]
+    out: Option<PathBuf>,
+    /// Gateway auth token (auto-discovered from ~/.openclaw/openclaw.json if omitted)
+    #[arg(short, long)]
+    token: Option<String>,
+    /// Disable secret redaction
+    #[arg(long)]
+    no_redact: bool,
+    /// Batch size for SQLite commits [default: 100]
+    #[arg(long)]
+    batch_size: Option<usize>,
+    /// Ingest filter rule EVENT[tool=NAME,channel=NAME]=ACTION (keep, drop, sample:N, aggregate:SECS), repeatable
+    #[arg(long = "filter", value_name = "RULE")]
+    filters: Vec<FilterRule>,
+    /// fsync the crash spool at most every N ms (0 = after every event) [default: 0]
+    #[arg(long, value_name = "MS")]
+    spool_sync_ms: Option<u64>,
+}
+
+impl RecorderArgs {
+    /// Merge these flags over the config file settings.
+    fn to_config(&self, settings: &Settings) -> Result<Config> {
+        let mut config = settings.to_config();
+        if let Some(gateway) = &self.gateway {
+            config.gateway_url = gateway.clone();
+        }
+        if let Some(out) = &self.out {
+            config.output_dir = out.clone();
+        }
+        if self.no_redact {
+            config.redact_secrets = false;
+        }
+        if let Some(batch_size) = self.batch_size {
+            config.batch_size = batch_size;
+        }
+        if !self.filters.is_empty() {
+            config.filter = FilterPolicy::new(self.filters.clone());
+        }
+        if let Some(ms) = self.spool_sync_ms {
+            config.spool_sync = SpoolSync::from_millis(ms);
+        }
+        config.auth_token = resolve_gateway_token(self.token.clone(), settings);
+        if config.redact_secrets {
+            config.redaction.check_key_outside(&config.output_dir)?;
+        }
+        Ok(config)
+    }
+}
+
 fn format_duration(secs: i64) -> String {
     let hours = secs / 3600;
     let mins = (secs % 3600) / 60;
@@ -327,18 +533,81 @@ fn format_duration(secs: i64) -> String {
     }
 }
 
-fn format_bytes(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-    if bytes >= GB {
-        format!("{:.1} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.1} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.1} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} B", bytes)
+/// How the recorders' progress reaches the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProgressMode {
+    /// Spinner when stderr is a terminal, log lines otherwise
+    Auto,
+    Spinner,
+    Log,
+    Quiet,
+}
+
+impl std::str::FromStr for ProgressMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "spinner" => Ok(Self::Spinner),
+            "log" => Ok(Self::Log),
+            "quiet" => Ok(Self::Quiet),
+            other => bail!(
+                "unknown progress mode '{}' (expected auto, spinner, log or quiet)",
+                other
+            ),
+        }
+    }
+}
+
+/// Renders recorder progress as a spinner on stderr or as log lines.
+struct TerminalProgress {
+    spinner: Option<ProgressBar>,
+    log: bool,
+}
+
+impl TerminalProgress {
+    fn new(mode: ProgressMode) -> Arc<Self> {
+        let mode = match mode {
+            ProgressMode::Auto if std::io::stderr().is_terminal() => ProgressMode::Spinner,
+            ProgressMode::Auto => ProgressMode::Log,
+            mode => mode,
+        };
+        let spinner = (mode == ProgressMode::Spinner).then(|| {
+            let pb = ProgressBar::new_spinner();
+            pb.set_style(
+                ProgressStyle::default_spinner()
+                    .template("{spinner:.green} [{elapsed_precise}] {msg}")
+                    .unwrap(),
+            );
+            pb.enable_steady_tick(std::time::Duration::from_millis(100));
+            pb
+        });
+        Arc::new(Self {
+            spinner,
+            log: mode == ProgressMode::Log,
+        })
+    }
+
+    fn finish(&self) {
+        if let Some(pb) = &self.spinner {
+            pb.finish_and_clear();
+        }
+    }
+}
+
+impl Observer for TerminalProgress {
+    fn on_progress(&self, progress: &Progress<'_>) {
+        match (&self.spinner, progress) {
+            (Some(pb), Progress::Stopped { .. }) => {
+                pb.finish_and_clear();
+                eprintln!("  {}", progress);
+            }
+            (Some(pb), _) => pb.set_message(progress.to_string()),
+            (None, Progress::Event { .. }) => {}
+            (None, _) if self.log => info!("{}", progress),
+            (None, _) => {}
+        }
     }
 }
 
@@ -354,18 +623,161 @@ fn discover_openclaw_token() -> Option<String> {
         .map(|s| s.to_string())
 }
 
+/// Continuous-recording options shared by `daemon` and `serve`.
+#[derive(Args, Clone)]
+struct DaemonArgs {
+    #[command(flatten)]
+    recorder: RecorderArgs,
+    /// Run-assignment strategy (run-id, session-key, chat, seq-window:N), repeatable; order is precedence [default: run-id]
+    #[arg(long = "assign", value_name = "STRATEGY")]
+    assign: Vec<AssignStrategy>,
+    /// Also write each completed agent run to its own runs/<id> case file
+    #[arg(long)]
+    sessionize: bool,
+    /// Serve the admin API (POST /admin/reload, /admin/reveal, GET /metrics, /healthz, /readyz) on this port
+    #[arg(long, value_name = "PORT")]
+    admin_port: Option<u16>,
+    /// Allow the admin API on a non-loopback host without admin.token
+    #[arg(long)]
+    insecure_admin: bool,
+}
+
+impl DaemonArgs {
+    fn to_config(&self, settings: &Settings) -> Result<Config> {
+        let mut config = self.recorder.to_config(settings)?;
+        if !self.assign.is_empty() {
+            config.assign = AssignPolicy::new(self.assign.clone());
+        }
+        config.sessionize |= self.sessionize;
+        Ok(config)
+    }
+
+    /// Reload the daemon config on SIGHUP and, if an admin port is set,
+    /// serve `POST /admin/reload` and `GET /metrics`. Reloads re-read the
+    /// config file and re-apply these flags on top. Returns the options to
+    /// hand to the daemon, including the audit queue it will drain.
+    fn spawn_control(
+        &self,
+        config_path: Option<PathBuf>,
+        settings: &Settings,
+        ct: &tokio_util::sync::CancellationToken,
+        observer: Arc<dyn Observer>,
+    ) -> Result<DaemonOptions> {
+        let metrics = std::sync::Arc::new(DaemonMetrics::default());
+        let audit = AuditLog::channel();
+        let args = self.clone();
+        let loader: ConfigLoader = std::sync::Arc::new(move || {
+            let (settings, _) = Settings::load(config_path.as_deref())?;
+            args.to_config(&settings)
+        });
+        let (reload, reload_rx) = ReloadHandle::new(loader);
+        reload.spawn_sighup(ct.clone());
+
+        let mut reveal_rx = None;
+        if let Some(port) = self.admin_port.or(settings.admin.port) {
+            let host = settings.admin.host.clone();
+            let host_octets = parse_host(&host)?;
+            let token = settings.admin.token.clone();
+            if !Ipv4Addr::from(host_octets).is_loopback() && token.is_none() {
+                if !self.insecure_admin {
+                    bail!(
+                        "Refusing to serve the admin API on {} without admin.token: anyone on the network could reload the config and record reveals. Set admin.token, bind to 127.0.0.1, or pass --insecure-admin",
+                        host
+                    );
+                }
+                warn!(
+                    "Admin API on {} without admin.token (--insecure-admin): anyone on the network can reload",
+                    host
+                );
+            }
+            cprintln!(
+                "  {} Admin: {}",
+                "+".green().bold(),
+                format!("http://{}:{}/admin/reload", host, port).underline(),
+            );
+            cprintln!(
+                "  {} Metrics: {}",
+                "+".green().bold(),
+                format!("http://{}:{}/metrics", host, port).underline(),
+            );
+            let (reveal, rx) = RevealHandle::new();
+            reveal_rx = Some(rx);
+            let metrics = metrics.clone();
+            let ct = ct.clone();
+            tokio::spawn(async move {
+                if let Err(e) =
+                    start_admin_with_shutdown(host_octets, port, token, reload, reveal, metrics, ct)
+                        .await
+                {
+                    warn!("Admin API stopped: {}", e);
+                }
+            });
+        }
+        Ok(DaemonOptions {
+            reload: Some(reload_rx),
+            reveal: reveal_rx,
+            metrics,
+            audit: Some(audit),
+            observer,
+            ..DaemonOptions::default()
+        })
+    }
+}
+
+/// Record a CLI action in the ledger's audit trail (or only in the log if a
+/// daemon holds the ledger).
+fn audit_cli(out: &std::path::Path, action: &'static str, detail: serde_json::Value) {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    let entry = AuditEntry::new(action, format!("cli {}", user), detail);
+    if let Err(e) = audit::record_standalone(out, entry) {
+        warn!("Could not record {} in the audit trail: {}", action, e);
+    }
+}
+
+/// Gateway token: --token, then clawprint.toml, then ~/.openclaw/openclaw.json
+fn resolve_gateway_token(flag: Option<String>, settings: &Settings) -> Option<String> {
+    if let Some(t) = flag {
+        info!("Using token from --token flag");
+        return Some(t);
+    }
+    if let Some(t) = settings.gateway.token.clone() {
+        info!("Using gateway token from config file");
+        return Some(t);
+    }
+    match discover_openclaw_token() {
+        Some(t) => {
+            info!("Auto-discovered token from ~/.openclaw/openclaw.json");
+            Some(t)
+        }
+        None => {
+            warn!(
+                "No auth token found. Pass --token, set gateway.token in clawprint.toml, or configure gateway.auth.token in ~/.openclaw/openclaw.json"
+            );
+            None
+        }
+    }
+}
+
 #[cfg(feature = "mcp")]
 async fn start_mcp_sse_with_shutdown(
     ledger_path: PathBuf,
     host: [u8; 4],
     port: u16,
     token: Option<String>,
+    scan_rules: clawprint::security::ScanRules,
+    audit: AuditLog,
     ct: tokio_util::sync::CancellationToken,
 ) -> Result<()> {
     let mcp_ct = ct.child_token();
     let service: StreamableHttpService<clawprint::mcp::ClawprintMcp, LocalSessionManager> =
         StreamableHttpService::new(
-            move || Ok(clawprint::mcp::ClawprintMcp::new(ledger_path.clone())),
+            move || {
+                Ok(clawprint::mcp::ClawprintMcp::new(ledger_path.clone())
+                    .with_scan_rules(scan_rules.clone())
+                    .with_audit(audit.clone()))
+            },
             Default::default(),
             StreamableHttpServerConfig {
                 stateful_mode: true,
@@ -411,45 +823,23 @@ async fn main() -> Result<()> {
         _ => "clawprint=warn",
     };
 
-    tracing_subscriber::fmt()
-        .with_env_filter(std::env::var("RUST_LOG").unwrap_or_else(|_| default_log.to_string()))
-        .init();
+    clawprint::logging::init(
+        cli.log_format,
+        &std::env::var("RUST_LOG").unwrap_or_else(|_| default_log.to_string()),
+    );
 
-    match cli.command {
-        Commands::Record {
-            gateway,
-            out,
-            run_name,
-            token,
-            no_redact,
-            batch_size,
-        } => {
-            let auth_token = match token {
-                Some(t) => {
-                    info!("Using token from --token flag");
-                    Some(t)
-                }
-                None => match discover_openclaw_token() {
-                    Some(t) => {
-                        info!("Auto-discovered token from ~/.openclaw/openclaw.json");
-                        Some(t)
-                    }
-                    None => {
-                        warn!(
-                            "No auth token found. Pass --token or configure gateway.auth.token in ~/.openclaw/openclaw.json"
-                        );
-                        None
-                    }
-                },
-            };
+    let config_path = cli.config.clone();
+    let (settings, settings_path) = Settings::load(config_path.as_deref())?;
+    if let Some(path) = &settings_path {
+        info!("Config: {:?}", path);
+    }
+    let out_or_default = |out: Option<PathBuf>| out.unwrap_or_else(|| settings.storage.out.clone());
 
+    match cli.command {
+        Commands::Record { recorder, run_name } => {
             let config = Config {
-                output_dir: out,
-                redact_secrets: !no_redact,
-                gateway_url: gateway,
-                auth_token,
-                batch_size,
-                flush_interval_ms: 200,
+                sessionize: false,
+                ..recorder.to_config(&settings)?
             };
 
             print_banner("Tracking molt activity");
@@ -460,7 +850,12 @@ async fn main() -> Result<()> {
                 if config.redact_secrets { "on" } else { "off" }
             );
 
-            let session = RecordingSession::start(config, run_name).await?;
+            let progress = TerminalProgress::new(cli.progress);
+            let options = SessionOptions {
+                observer: progress.clone(),
+                ..SessionOptions::default()
+            };
+            let session = RecordingSession::start_with_options(config, run_name, options).await?;
             let run_id = session.run_id().clone();
 
             info!(
@@ -470,6 +865,7 @@ async fn main() -> Result<()> {
             info!("Ctrl+C to seal the ledger");
 
             tokio::signal::ctrl_c().await?;
+            progress.finish();
 
             info!("\nSealing ledger...");
             let summary = session.stop().await?;
@@ -497,6 +893,12 @@ async fn main() -> Result<()> {
                     integrity.red().to_string()
                 },
             );
+            if !summary.clean_shutdown {
+                cprintln!(
+                    "    {}",
+                    "Recorder did not stop cleanly — RUN_END marked unclean_shutdown".yellow()
+                );
+            }
             cprintln!("\n    {}", "Examine the evidence:".dimmed());
             cprintln!(
                 "      clawprint stats --run {} --out {:?}",
@@ -511,6 +913,7 @@ async fn main() -> Result<()> {
         }
 
         Commands::List { out } => {
+            let out = out_or_default(out);
             let runs = list_runs_with_stats(&out)?;
 
             if runs.is_empty() {
@@ -541,6 +944,11 @@ async fn main() -> Result<()> {
                         format_duration(d.num_seconds())
                     })
                     .unwrap_or_else(|| "recording...".to_string());
+                let dur = if meta.status == RunStatus::Incomplete {
+                    format!("{} (inc.)", dur)
+                } else {
+                    dur
+                };
 
                 total_events += meta.event_count;
                 total_size += size;
@@ -576,6 +984,10 @@ async fn main() -> Result<()> {
             port,
             token,
         } => {
+            let out = out_or_default(out);
+            let host = host.unwrap_or_else(|| settings.viewer.host.clone());
+            let port = port.unwrap_or(settings.viewer.port);
+            let token = token.or_else(|| settings.viewer.token.clone());
             let host_octets = parse_host(&host)?;
             if host == "0.0.0.0" && token.is_none() {
                 warn!("Binding to 0.0.0.0 without --token: viewer is open to the network");
@@ -607,6 +1019,10 @@ async fn main() -> Result<()> {
             port,
             token,
         } => {
+            let out = out_or_default(out);
+            let host = host.unwrap_or_else(|| settings.viewer.host.clone());
+            let port = port.unwrap_or(settings.viewer.port);
+            let token = token.or_else(|| settings.viewer.token.clone());
             let host_octets = parse_host(&host)?;
             if host == "0.0.0.0" && token.is_none() {
                 warn!("Binding to 0.0.0.0 without --token: viewer is open to the network");
@@ -646,6 +1062,7 @@ async fn main() -> Result<()> {
             offline,
             export,
         } => {
+            let out = out_or_default(out);
             let run_id = resolve_run_id(&run, &out)?;
             info!("Replaying run: {}", run_id.0);
 
@@ -654,17 +1071,24 @@ async fn main() -> Result<()> {
 
             if let Some(export_path) = export {
                 std::fs::write(&export_path, &transcript)?;
+                audit_cli(
+                    &out,
+                    "export",
+                    serde_json::json!({ "run": run_id.0, "path": export_path }),
+                );
                 cprintln!(
                     "  {} Transcript exported to {:?}",
                     "OK".green().bold(),
                     export_path,
                 );
             } else {
+                audit_cli(&out, "replay", serde_json::json!({ "run": run_id.0 }));
                 cprintln!("{}", transcript);
             }
         }
 
         Commands::Diff { run_a, run_b, out } => {
+            let out = out_or_default(out);
             let run_a = resolve_run_id(&run_a, &out)?;
             let run_b = resolve_run_id(&run_b, &out)?;
 
@@ -675,6 +1099,7 @@ async fn main() -> Result<()> {
         }
 
         Commands::Verify { run, out } => {
+            let out = out_or_default(out);
             let run_id = resolve_run_id(&run, &out)?;
             let storage = RunStorage::open(run_id.clone(), &out)?;
 
@@ -683,7 +1108,13 @@ async fn main() -> Result<()> {
             cprint!("  Inspecting chain of evidence... ");
             std::io::stdout().flush()?;
 
-            match storage.verify_chain() {
+            let verified = storage.verify_chain();
+            audit_cli(
+                &out,
+                "verification",
+                serde_json::json!({ "run": run_id.0, "valid": verified.as_ref().ok() }),
+            );
+            match verified {
                 Ok(true) => {
                     cprintln!("{}", "INTACT".green().bold());
                     cprintln!("  Traces:    {}", storage.event_count().to_string().cyan());
@@ -716,10 +1147,17 @@ async fn main() -> Result<()> {
             port,
             token,
         } => {
+            let out = out_or_default(out);
+            let transport = transport.unwrap_or_else(|| settings.mcp.transport.clone());
+            let host = host.unwrap_or_else(|| settings.mcp.host.clone());
+            let port = port.unwrap_or(settings.mcp.port);
+            let token = token.or_else(|| settings.mcp.token.clone());
+            let scan_rules = settings.scanner.clone();
             match transport.as_str() {
                 "stdio" => {
                     // MCP server: stdout is JSON-RPC only, all logging to stderr
                     let service = clawprint::mcp::ClawprintMcp::new(out)
+                        .with_scan_rules(scan_rules)
                         .serve(rmcp::transport::stdio())
                         .await
                         .map_err(|e| anyhow::anyhow!("MCP server error: {}", e))?;
@@ -743,7 +1181,10 @@ async fn main() -> Result<()> {
                         clawprint::mcp::ClawprintMcp,
                         LocalSessionManager,
                     > = StreamableHttpService::new(
-                        move || Ok(clawprint::mcp::ClawprintMcp::new(ledger_path.clone())),
+                        move || {
+                            Ok(clawprint::mcp::ClawprintMcp::new(ledger_path.clone())
+                                .with_scan_rules(scan_rules.clone()))
+                        },
                         Default::default(),
                         StreamableHttpServerConfig {
                             stateful_mode: true,
@@ -798,11 +1239,7 @@ async fn main() -> Result<()> {
             viewer,
             #[cfg(feature = "mcp")]
             mcp,
-            gateway,
-            out,
-            token,
-            no_redact,
-            batch_size,
+            recording,
             viewer_host,
             viewer_port,
             #[cfg(feature = "mcp")]
@@ -812,15 +1249,14 @@ async fn main() -> Result<()> {
         } => {
             #[cfg(not(feature = "mcp"))]
             let mcp = false;
-            #[cfg(not(feature = "mcp"))]
-            let mcp_host = "0.0.0.0".to_string();
-            #[cfg(not(feature = "mcp"))]
-            let mcp_port: u16 = 3000;
 
             if !daemon && !viewer && !mcp {
                 bail!("Enable at least one service: --daemon, --viewer, or --mcp");
             }
 
+            let out = out_or_default(recording.recorder.out.clone());
+            let token = recording.recorder.token.clone();
+
             print_banner("Serving");
 
             let ct = tokio_util::sync::CancellationToken::new();
@@ -835,35 +1271,12 @@ async fn main() -> Result<()> {
             }
 
             let mut handles: Vec<tokio::task::JoinHandle<Result<()>>> = Vec::new();
+            // Viewer and MCP accesses reach the ledger only through the daemon
+            let mut audit = AuditLog::default();
+            let mut metrics = None;
 
             if daemon {
-                let auth_token = match token.clone() {
-                    Some(t) => {
-                        info!("Using token from --token flag");
-                        Some(t)
-                    }
-                    None => match discover_openclaw_token() {
-                        Some(t) => {
-                            info!("Auto-discovered token from ~/.openclaw/openclaw.json");
-                            Some(t)
-                        }
-                        None => {
-                            warn!(
-                                "No auth token found. Pass --token or configure gateway.auth.token in ~/.openclaw/openclaw.json"
-                            );
-                            None
-                        }
-                    },
-                };
-
-                let config = Config {
-                    output_dir: out.clone(),
-                    redact_secrets: !no_redact,
-                    gateway_url: gateway.clone(),
-                    auth_token,
-                    batch_size,
-                    flush_interval_ms: 200,
-                };
+                let config = recording.to_config(&settings)?;
 
                 cprintln!(
                     "  {} Daemon: wire={}",
@@ -871,13 +1284,26 @@ async fn main() -> Result<()> {
                     config.gateway_url.dimmed(),
                 );
 
+                let options = recording.spawn_control(
+                    config_path.clone(),
+                    &settings,
+                    &ct,
+                    TerminalProgress::new(cli.progress),
+                )?;
+                if let Some((log, _)) = &options.audit {
+                    audit = log.clone();
+                }
+                metrics = Some(options.metrics.clone());
                 let ct = ct.clone();
                 handles.push(tokio::spawn(async move {
-                    run_daemon_with_shutdown(config, ct).await
+                    run_daemon_with_options(config, ct, options).await
                 }));
             }
 
             if viewer {
+                let viewer_host = viewer_host.unwrap_or_else(|| settings.viewer.host.clone());
+                let viewer_port = viewer_port.unwrap_or(settings.viewer.port);
+                let token = token.clone().or_else(|| settings.viewer.token.clone());
                 let vh_octets = parse_host(&viewer_host)?;
                 if viewer_host == "0.0.0.0" && token.is_none() {
                     warn!(
@@ -893,14 +1319,28 @@ async fn main() -> Result<()> {
 
                 let base_path = out.clone();
                 let tok = token.clone();
+                let audit = audit.clone();
+                let metrics = metrics.clone();
                 let ct = ct.clone();
                 handles.push(tokio::spawn(async move {
-                    start_viewer_with_shutdown(base_path, vh_octets, viewer_port, tok, ct).await
+                    start_viewer_with_shutdown(
+                        base_path,
+                        vh_octets,
+                        viewer_port,
+                        tok,
+                        audit,
+                        metrics,
+                        ct,
+                    )
+                    .await
                 }));
             }
 
             #[cfg(feature = "mcp")]
             if mcp {
+                let mcp_host = mcp_host.unwrap_or_else(|| settings.mcp.host.clone());
+                let mcp_port = mcp_port.unwrap_or(settings.mcp.port);
+                let token = token.clone().or_else(|| settings.mcp.token.clone());
                 let mh_octets = parse_host(&mcp_host)?;
                 if mcp_host == "0.0.0.0" && token.is_none() {
                     warn!(
@@ -916,9 +1356,20 @@ async fn main() -> Result<()> {
 
                 let ledger_path = out.clone();
                 let tok = token.clone();
+                let scan_rules = settings.scanner.clone();
+                let audit = audit.clone();
                 let ct = ct.clone();
                 handles.push(tokio::spawn(async move {
-                    start_mcp_sse_with_shutdown(ledger_path, mh_octets, mcp_port, tok, ct).await
+                    start_mcp_sse_with_shutdown(
+                        ledger_path,
+                        mh_octets,
+                        mcp_port,
+                        tok,
+                        scan_rules,
+                        audit,
+                        ct,
+                    )
+                    .await
                 }));
             }
 
@@ -936,53 +1387,441 @@ async fn main() -> Result<()> {
             info!("All services stopped");
         }
 
-        Commands::Daemon {
-            gateway,
+        Commands::Daemon { recording } => {
+            let config = recording.to_config(&settings)?;
+
+            print_banner("Watching the wire");
+            info!("Wire: {}", config.gateway_url);
+            info!("Ledger: {:?}", config.output_dir);
+            info!(
+                "Redaction: {}",
+                if config.redact_secrets { "on" } else { "off" }
+            );
+
+            let ct = tokio_util::sync::CancellationToken::new();
+            {
+                let ct = ct.clone();
+                tokio::spawn(async move {
+                    let _ = tokio::signal::ctrl_c().await;
+                    ct.cancel();
+                });
+            }
+            let options = recording.spawn_control(
+                config_path,
+                &settings,
+                &ct,
+                TerminalProgress::new(cli.progress),
+            )?;
+            run_daemon_with_options(config, ct, options).await?;
+        }
+
+        Commands::Recover { out } => {
+            let out = out_or_default(out);
+            print_banner("Recover");
+
+            // Opening the daemon ledger replays its spool into SQLite
+            let spool_path = out.join("spool.ndjson");
+            if out.join("ledger.sqlite").exists()
+                && std::fs::metadata(&spool_path)
+                    .map(|m| m.len() > 0)
+                    .unwrap_or(false)
+            {
+                let ledger = Ledger::open(&out, 100)?;
+                cprintln!(
+                    "  {} Ledger: replayed spool ({} traces total)",
+                    "+".green().bold(),
+                    ledger.total_events().to_string().cyan(),
+                );
+            }
+
+            let orphans = orphaned_runs(&out)?;
+            if orphans.is_empty() {
+                cprintln!("  {}", "No unsealed runs found.".dimmed());
+                return Ok(());
+            }
+
+            for run_id in orphans {
+                let id_short = &run_id.0[..8.min(run_id.0.len())];
+                match recover_run(run_id.clone(), &out) {
+                    Ok(meta) => cprintln!(
+                        "  {} {}  {} traces  sealed {}  root {}",
+                        "+".green().bold(),
+                        id_short.bright_blue(),
+                        meta.event_count.to_string().cyan(),
+                        "incomplete".yellow(),
+                        meta.root_hash.dimmed(),
+                    ),
+                    Err(e) => cprintln!(
+                        "  {} {}  {}: {}",
+                        "x".red().bold(),
+                        id_short.bright_blue(),
+                        "ERROR".red().bold(),
+                        e
+                    ),
+                }
+            }
+        }
+
+        Commands::Backfill {
+            out,
+            assign,
+            dry_run,
+        } => {
+            let out = out_or_default(out);
+            let assign = if assign.is_empty() {
+                settings.ingest.assign.clone()
+            } else {
+                assign
+            };
+            print_banner("Backfill");
+            if !out.join("ledger.sqlite").exists() {
+                return Err(anyhow::anyhow!("Ledger not found at {:?}", out));
+            }
+
+            let mut ledger = Ledger::open(&out, 100)?;
+            let stats = ledger.backfill_agent_runs(&AssignPolicy::new(assign), dry_run)?;
+
+            cprintln!("  Scanned:  {}", stats.scanned.to_string().cyan());
+            cprintln!("  Grouped:  {}", stats.assigned.to_string().cyan());
+            if dry_run {
+                cprintln!(
+                    "  Would change: {} {}",
+                    stats.changed.to_string().yellow(),
+                    "(dry run, nothing written)".dimmed()
+                );
+            } else {
+                cprintln!("  Changed:  {}", stats.changed.to_string().green());
+                cprintln!(
+                    "  {}",
+                    "Hash chain untouched: agent_run is an index column.".dimmed()
+                );
+            }
+        }
+
+        Commands::Export {
+            otlp,
+            out,
+            since,
+            until,
+            headers,
+            service_name,
+        } => {
+            let out = out_or_default(out);
+            print_banner("Export");
+            if !out.join("ledger.sqlite").exists() {
+                return Err(anyhow::anyhow!("Ledger not found at {:?}", out));
+            }
+
+            // Read-only: works while the daemon holds the ledger
+            let ledger = Ledger::open_readonly(&out)?;
+            let exporter = OtlpExporter::new(&otlp, headers.into_iter().collect(), service_name)?;
+            cprint!("  Sending traces to {}... ", exporter.url());
+            std::io::stdout().flush()?;
+            let stats = export_range(&ledger, &exporter, since, until).await?;
+            cprintln!("{}", "OK".green().bold());
+            audit_cli(
+                &out,
+                "export",
+                serde_json::json!({
+                    "otlp": exporter.url(),
+                    "since": since,
+                    "until": until,
+                    "agent_runs": stats.runs,
+                }),
+            );
+            cprintln!("  Agent runs: {}", stats.runs.to_string().cyan());
+            cprintln!("  Spans:      {}", stats.spans.to_string().cyan());
+        }
+
+        Commands::Reveal {
+            event,
+            run,
+            key,
+            admin_url,
             out,
-            token,
-            no_redact,
-            batch_size,
         } => {
-            let auth_token = match token {
-                Some(t) => {
-                    info!("Using token from --token flag");
-                    Some(t)
+            let out = out_or_default(out);
+            let target = match &run {
+                Some(run) => Target::Run {
+                    run_id: resolve_run_id(run, &out)?,
+                    out: out.clone(),
+                },
+                None => Target::Ledger(out.clone()),
+            };
+            print_banner(&format!("Reveal — event {}", event));
+            let identity = Identity::from_file(&key)?;
+            let actor = match std::env::var("USER") {
+                Ok(user) => format!("cli {}", user),
+                Err(_) => "cli".to_string(),
+            };
+            let result = match vault::reveal(&target, event, &identity, &actor) {
+                // A daemon holds the ledger: it records the REVEAL event
+                Err(e)
+                    if matches!(target, Target::Ledger(_))
+                        && e.downcast_ref::<LedgerLocked>().is_some() =>
+                {
+                    let url = admin_url
+                        .or_else(|| {
+                            let host = match settings.admin.host.as_str() {
+                                "0.0.0.0" => "127.0.0.1",
+                                host => host,
+                            };
+                            settings
+                                .admin
+                                .port
+                                .map(|port| format!("http://{}:{}", host, port))
+                        })
+                        .ok_or_else(|| {
+                            anyhow::anyhow!(
+                                "The ledger is in use by a running daemon and no admin API is configured; set admin.port (or pass --admin-url) so the daemon can record the REVEAL event"
+                            )
+                        })?;
+                    info!("Ledger in use; revealing through the daemon at {}", url);
+                    vault::reveal_via_admin(
+                        &url,
+                        settings.admin.token.as_deref(),
+                        &out,
+                        event,
+                        &identity,
+                        &actor,
+                    )
+                    .await?
                 }
-                None => match discover_openclaw_token() {
-                    Some(t) => {
-                        info!("Auto-discovered token from ~/.openclaw/openclaw.json");
-                        Some(t)
-                    }
-                    None => {
-                        warn!(
-                            "No auth token found. Pass --token or configure gateway.auth.token in ~/.openclaw/openclaw.json"
-                        );
-                        None
-                    }
+                result => result?,
+            };
+            cprintln!(
+                "  {:?} at {} {}",
+                result.event.kind,
+                result.event.ts.format("%Y-%m-%d %H:%M:%S"),
+                "(REVEAL event recorded)".dimmed()
+            );
+            println!();
+            if result.revealed.is_empty() && result.missing.is_empty() {
+                cprintln!("  No pseudonym tokens in this event.");
+            }
+            for r in &result.revealed {
+                cprintln!(
+                    "  {} {}",
+                    r.token.yellow(),
+                    format!("({})", r.rule).dimmed()
+                );
+                cprintln!("    {}", r.value.bright_white());
+            }
+            for token in &result.missing {
+                cprintln!(
+                    "  {} {}",
+                    token.yellow(),
+                    "not in the vault for this key".dimmed()
+                );
+            }
+        }
+
+        Commands::Redact {
+            ledger: _,
+            run,
+            rules: rules_file,
+            dry_run,
+            out,
+        } => {
+            let out = out_or_default(out);
+            let target = match &run {
+                Some(run) => Target::Run {
+                    run_id: resolve_run_id(run, &out)?,
+                    out: out.clone(),
                 },
+                None => Target::Ledger(out.clone()),
             };
+            print_banner(if dry_run {
+                "Redaction pass (dry run)"
+            } else {
+                "Redaction pass"
+            });
 
-            let config = Config {
-                output_dir: out,
-                redact_secrets: !no_redact,
-                gateway_url: gateway,
-                auth_token,
-                batch_size,
-                flush_interval_ms: 200,
+            let mut rules = settings.to_config().redaction;
+            if let Some(path) = &rules_file {
+                rules.merge(RedactionRules::from_file(path)?);
+            }
+            rules.check_key_outside(&out)?;
+            let mut redactor = Redactor::new(&rules)?;
+            if !dry_run {
+                redactor = redactor.with_vault(&out)?;
+            }
+            let actor = match std::env::var("USER") {
+                Ok(user) => format!("cli {}", user),
+                Err(_) => "cli".to_string(),
             };
+            let report = redaction_pass(
+                &target,
+                &PassOptions {
+                    redactor: &redactor,
+                    rules: &rules,
+                    rules_file: rules_file.as_deref(),
+                    actor: &actor,
+                    dry_run,
+                },
+            )?;
 
-            print_banner("Watching the wire");
-            info!("Wire: {}", config.gateway_url);
-            info!("Ledger: {:?}", config.output_dir);
-            info!(
-                "Redaction: {}",
-                if config.redact_secrets { "on" } else { "off" }
+            for m in &report.matches {
+                cprintln!(
+                    "  #{} {}",
+                    m.event_id.to_string().cyan(),
+                    format!("{:?}", m.kind).dimmed()
+                );
+                for d in &m.detections {
+                    cprintln!("    {} {}", d.rule.yellow(), d.path);
+                }
+            }
+            if !report.matches.is_empty() {
+                println!();
+            }
+            cprintln!("  Scanned:  {}", report.scanned.to_string().cyan());
+            cprintln!(
+                "  Matched:  {} traces",
+                report.matches.len().to_string().yellow()
+            );
+            for (rule, count) in &report.by_rule {
+                cprintln!("    {:<24} {}", rule, count);
+            }
+            match &report.generation {
+                Some(generation) => {
+                    cprintln!(
+                        "  Generation {}: {} rewritten, {} relinked",
+                        generation.number.to_string().green(),
+                        generation.rewritten,
+                        generation.relinked
+                    );
+                    cprintln!(
+                        "  Archived: {}",
+                        generation.archive.display().to_string().dimmed()
+                    );
+                    cprintln!("  Root hash: {}", generation.root_hash.dimmed());
+                }
+                None if dry_run => {
+                    cprintln!("  {}", "(dry run, nothing written)".dimmed());
+                }
+                None => {
+                    cprintln!(
+                        "  {}",
+                        "Nothing new to redact; the ledger is unchanged.".dimmed()
+                    );
+                }
+            }
+        }
+
+        Commands::RedactionReport {
+            run,
+            since,
+            until,
+            rules: rules_file,
+            limit,
+            format,
+            out,
+        } => {
+            let out = out_or_default(out);
+            let mut rules = settings.to_config().redaction;
+            if let Some(path) = &rules_file {
+                rules.merge(RedactionRules::from_file(path)?);
+            }
+            let mut coverage = Coverage::new(&rules)?.with_limits(limit, 10);
+            let page_after: Box<dyn Fn(u64) -> Result<Vec<clawprint::Event>>> = match &run {
+                Some(run) => {
+                    let storage = RunStorage::open(resolve_run_id(run, &out)?, &out)?;
+                    Box::new(move |after| storage.events_in_range_after(since, until, after, 1000))
+                }
+                None => {
+                    let ledger = Ledger::open_readonly(&out)?;
+                    Box::new(move |after| ledger.events_in_range_after(since, until, after, 1000))
+                }
+            };
+            let mut after = 0;
+            loop {
+                let page = page_after(after)?;
+                let Some(last) = page.last() else { break };
+                after = last.event_id.0;
+                for event in &page {
+                    coverage.add(event);
+                }
+            }
+            let report = coverage.finish();
+
+            if format == ReportFormat::Json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+            print_banner("Redaction coverage");
+            let range = match (report.first_event_id, report.last_event_id) {
+                (Some(first), Some(last)) => format!("#{}–#{}", first, last),
+                _ => "none".to_string(),
+            };
+            cprintln!(
+                "  Traces:    {} {}",
+                report.scanned.to_string().cyan(),
+                range.dimmed()
             );
+            cprintln!("  Redacted:  {}", report.redacted.to_string().cyan());
+            if report.unattributed > 0 {
+                cprintln!(
+                    "  Unattributed: {} {}",
+                    report.unattributed,
+                    "(redaction markers in traces without a manifest)".dimmed()
+                );
+            }
+            if !report.by_detector.is_empty() {
+                println!();
+                cprintln!("  {}", "By detector".bold());
+                for (rule, count) in &report.by_detector {
+                    cprintln!("    {:<28} {}", rule, count);
+                }
+            }
+            if !report.top_paths.is_empty() {
+                println!();
+                cprintln!("  {}", "Top paths".bold());
+                for p in &report.top_paths {
+                    cprintln!("    {:<40} {}", p.path, p.count);
+                }
+            }
+            println!();
+            if report.survivor_count() == 0 {
+                cprintln!("  {}", "No suspicious strings survived.".green());
+            } else {
+                cprintln!(
+                    "  {} {}",
+                    "Possible survivors".bold(),
+                    report.survivor_count().to_string().yellow()
+                );
+                for (reason, count) in &report.survivors_by_reason {
+                    cprintln!("    {:<28} {}", reason, count);
+                }
+                println!();
+                for s in &report.survivors {
+                    cprintln!(
+                        "    #{} {} {} {}",
+                        s.event_id.to_string().cyan(),
+                        s.path,
+                        s.reason.yellow(),
+                        s.preview.dimmed()
+                    );
+                }
+                if report.survivor_count() > report.survivors.len() as u64 {
+                    cprintln!("    {}", "(more not shown, see --limit)".dimmed());
+                }
+            }
+        }
 
-            run_daemon(config).await?;
+        Commands::Vault {
+            action: VaultAction::Keygen { out },
+        } => {
+            let identity = Identity::generate()?;
+            identity.write(&out)?;
+            cprintln!("  Private key written to {}", out.display());
+            cprintln!("  Keep it offline; it opens every value sealed to it.");
+            println!();
+            cprintln!("  [redaction.vault]");
+            cprintln!("  public_key = \"{}\"", identity.public_key_hex());
         }
 
         Commands::Stats { run, out } => {
+            let out = out_or_default(out);
             let run_id = resolve_run_id(&run, &out)?;
             let storage = RunStorage::open(run_id.clone(), &out)?;
 
@@ -1054,6 +1893,29 @@ async fn main() -> Result<()> {
                 format_bytes(size).cyan(),
             );
         }
+
+        Commands::Config {
+            action: ConfigAction::Show,
+        } => {
+            let mut effective = settings.clone();
+            match &settings_path {
+                Some(path) => println!("# Config file: {}", path.display()),
+                None => {
+                    println!("# No config file found, showing built-in defaults. Searched:");
+                    for path in Settings::search_paths() {
+                        println!("#   {}", path.display());
+                    }
+                }
+            }
+            if effective.gateway.token.is_none()
+                && let Some(t) = discover_openclaw_token()
+            {
+                println!("# gateway.token discovered from ~/.openclaw/openclaw.json");
+                effective.gateway.token = Some(t);
+            }
+            println!();
+            print!("{}", effective.masked().to_toml()?);
+        }
     }
 
     Ok(())
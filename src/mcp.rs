@@ -3,6 +3,8 @@
 //! Exposes the continuous ledger as MCP tools so Claude Desktop
 //! (or any MCP client) can query agent activity via natural language.
 //!
+//! Every tool call is passed to the [`AuditLog`] with its parameters.
+//!
 //! Run with: `clawprint mcp --out ./clawprints`
 //! All output goes to stderr; stdout is reserved for the MCP JSON-RPC protocol.
 
@@ -13,12 +15,13 @@ use rmcp::{
     handler::server::wrapper::Parameters, model::*, schemars, tool, tool_handler, tool_router,
 };
 
+use crate::audit::AuditLog;
 use crate::ledger::Ledger;
 
 /// Parameter types for MCP tools.
 /// Each derives Deserialize + JsonSchema so rmcp can generate schemas.
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct ListRunsParams {
     /// ISO 8601 datetime — only show runs after this time (e.g. "2026-01-31T00:00:00Z" or "today")
     #[serde(default)]
@@ -31,13 +34,13 @@ pub struct ListRunsParams {
     pub limit: Option<u32>,
 }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct GetRunParams {
     /// Agent run ID, or "latest" for the most recent conversation
     pub run_id: String,
 }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct SearchParams {
     /// Text to search for in event payloads
     pub query: String,
@@ -52,7 +55,7 @@ pub struct SearchParams {
     pub until: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct ToolCallsParams {
     /// Filter by agent run ID
     #[serde(default)]
@@ -65,7 +68,7 @@ pub struct ToolCallsParams {
     pub tool_name: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct SecurityCheckParams {
     /// ISO 8601 datetime — only scan events after this time
     #[serde(default)]
@@ -75,7 +78,7 @@ pub struct SecurityCheckParams {
     pub run_id: Option<String>,
 }
 
-#[derive(Debug, serde::Deserialize, schemars::JsonSchema)]
+#[derive(Debug, serde::Deserialize, serde::Serialize, schemars::JsonSchema)]
 pub struct StatsParams {
     /// ISO 8601 datetime — only include events after this time
     #[serde(default)]
@@ -86,6 +89,8 @@ pub struct StatsParams {
 #[derive(Clone)]
 pub struct ClawprintMcp {
     ledger_path: PathBuf,
+    scan_rules: crate::security::ScanRules,
+    audit: AuditLog,
     tool_router: ToolRouter<ClawprintMcp>,
 }
 
@@ -93,10 +98,33 @@ impl ClawprintMcp {
     pub fn new(ledger_path: PathBuf) -> Self {
         Self {
             ledger_path,
+            scan_rules: crate::security::ScanRules::default(),
+            audit: AuditLog::default(),
             tool_router: Self::tool_router(),
         }
     }
 
+    /// Use `rules` for the security check tool instead of the defaults.
+    pub fn with_scan_rules(mut self, rules: crate::security::ScanRules) -> Self {
+        self.scan_rules = rules;
+        self
+    }
+
+    /// Record tool calls through `audit` (by default they are only logged).
+    pub fn with_audit(mut self, audit: AuditLog) -> Self {
+        self.audit = audit;
+        self
+    }
+
+    /// Record a tool call and its parameters in the audit log.
+    fn audit_tool(&self, tool: &str, params: &impl serde::Serialize) {
+        self.audit.record(
+            "mcp_tool",
+            "mcp",
+            serde_json::json!({ "tool": tool, "params": params }),
+        );
+    }
+
     /// Open a read-only ledger connection.
     fn open_ledger(&self) -> Result<Ledger, McpError> {
         Ledger::open_readonly(&self.ledger_path)
@@ -175,6 +203,7 @@ impl ClawprintMcp {
         description = "Get Clawprint recording status: total events, last event time, ledger size, and daemon info"
     )]
     async fn clawprint_status(&self) -> Result<CallToolResult, McpError> {
+        self.audit_tool("clawprint_status", &serde_json::json!({}));
         let ledger = self.open_ledger()?;
 
         let total = ledger.total_events();
@@ -227,6 +256,7 @@ impl ClawprintMcp {
         &self,
         Parameters(params): Parameters<ListRunsParams>,
     ) -> Result<CallToolResult, McpError> {
+        self.audit_tool("clawprint_list_runs", &params);
         let ledger = self.open_ledger()?;
 
         let since = params.since.as_deref().and_then(Self::parse_datetime);
@@ -269,6 +299,7 @@ impl ClawprintMcp {
         &self,
         Parameters(params): Parameters<GetRunParams>,
     ) -> Result<CallToolResult, McpError> {
+        self.audit_tool("clawprint_get_run", &params);
         let ledger = self.open_ledger()?;
 
         let run_id = if params.run_id == "latest" {
@@ -372,6 +403,7 @@ impl ClawprintMcp {
         &self,
         Parameters(params): Parameters<SearchParams>,
     ) -> Result<CallToolResult, McpError> {
+        self.audit_tool("clawprint_search", &params);
         let ledger = self.open_ledger()?;
 
         let since = params.since.as_deref().and_then(Self::parse_datetime);
@@ -419,6 +451,7 @@ impl ClawprintMcp {
         &self,
         Parameters(params): Parameters<ToolCallsParams>,
     ) -> Result<CallToolResult, McpError> {
+        self.audit_tool("clawprint_tool_calls", &params);
         let ledger = self.open_ledger()?;
 
         let since = params.since.as_deref().and_then(Self::parse_datetime);
@@ -456,6 +489,7 @@ impl ClawprintMcp {
         &self,
         Parameters(params): Parameters<SecurityCheckParams>,
     ) -> Result<CallToolResult, McpError> {
+        self.audit_tool("clawprint_security_check", &params);
         let ledger = self.open_ledger()?;
 
         // Get events to scan
@@ -474,7 +508,7 @@ impl ClawprintMcp {
             return text_result("No events to scan.".to_string());
         }
 
-        let report = crate::security::scan_events(&events);
+        let report = crate::security::scan_events_with(&events, &self.scan_rules);
         text_result(report.to_text())
     }
 
@@ -482,6 +516,7 @@ impl ClawprintMcp {
         description = "Verify hash chain integrity of the Clawprint recording ledger. Detects any tampering or corruption"
     )]
     async fn clawprint_verify(&self) -> Result<CallToolResult, McpError> {
+        self.audit_tool("clawprint_verify", &serde_json::json!({}));
         let ledger = self.open_ledger()?;
 
         let (valid, count) = ledger
@@ -515,6 +550,7 @@ impl ClawprintMcp {
         &self,
         Parameters(params): Parameters<StatsParams>,
     ) -> Result<CallToolResult, McpError> {
+        self.audit_tool("clawprint_stats", &params);
         let ledger = self.open_ledger()?;
 
         let since = params.since.as_deref().and_then(Self::parse_datetime);
@@ -0,0 +1,438 @@
+//! Prometheus metrics for the daemon
+//!
+//! Counters and gauges are updated by the recording loop and rendered in the
+//! Prometheus text exposition format by `GET /metrics` on the admin API.
+//! Together they answer: is the daemon still receiving events, is it keeping
+//! up with the gateway, and is the ledger intact. `last_event_age_seconds`
+//! and `gateway_connected` are the ones to alert on when recording silently
+//! stops. The same state backs the `/readyz` check.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicI64, AtomicU64, Ordering};
+use std::time::Duration;
+
+const FLUSH_SECONDS_BUCKETS: &[f64] = &[0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 1.0];
+const BATCH_SIZE_BUCKETS: &[f64] = &[1.0, 5.0, 10.0, 25.0, 50.0, 100.0, 250.0, 500.0, 1000.0];
+
+/// A flush older than this many flush intervals (and at least
+/// `MIN_FLUSH_STALENESS`) makes the daemon not ready.
+const FLUSH_STALENESS_INTERVALS: u32 = 5;
+const MIN_FLUSH_STALENESS: Duration = Duration::from_secs(10);
+
+/// Cumulative histogram with fixed upper bounds.
+struct Histogram {
+    bounds: &'static [f64],
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            counts: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, count) in self.bounds.iter().zip(self.counts.iter_mut()) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, help: &str) {
+        header(out, name, help, "histogram");
+        for (bound, count) in self.bounds.iter().zip(&self.counts) {
+            let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count);
+        }
+        let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, self.count);
+        let _ = writeln!(out, "{}_sum {}", name, self.sum);
+        let _ = writeln!(out, "{}_count {}", name, self.count);
+    }
+}
+
+/// Live metrics for one daemon. Share it with `Arc`.
+pub struct DaemonMetrics {
+    events_by_kind: Mutex<BTreeMap<String, u64>>,
+    events_omitted: AtomicU64,
+    flush_seconds: Mutex<Histogram>,
+    batch_size: Mutex<Histogram>,
+    channel_depth: AtomicU64,
+    connected: AtomicBool,
+    reconnects: AtomicU64,
+    backoff_ms: AtomicU64,
+    seq_gaps: AtomicU64,
+    seq_missed: AtomicU64,
+    /// Unix millis of the last ingested event, 0 if none yet
+    last_event_ms: AtomicI64,
+    ledger_events: AtomicU64,
+    ledger_bytes: AtomicU64,
+    /// False after a failed write or flush, until the next successful flush
+    ledger_writable: AtomicBool,
+    /// Unix millis of the last successful flush tick, 0 if none yet
+    last_flush_ms: AtomicI64,
+    flush_interval_ms: AtomicU64,
+    /// 1 intact, 0 broken, -1 not verified yet
+    chain_valid: AtomicI64,
+    last_verified: AtomicI64,
+}
+
+impl Default for DaemonMetrics {
+    fn default() -> Self {
+        Self {
+            events_by_kind: Mutex::new(BTreeMap::new()),
+            events_omitted: AtomicU64::new(0),
+            flush_seconds: Mutex::new(Histogram::new(FLUSH_SECONDS_BUCKETS)),
+            batch_size: Mutex::new(Histogram::new(BATCH_SIZE_BUCKETS)),
+            channel_depth: AtomicU64::new(0),
+            connected: AtomicBool::new(false),
+            reconnects: AtomicU64::new(0),
+            backoff_ms: AtomicU64::new(0),
+            seq_gaps: AtomicU64::new(0),
+            seq_missed: AtomicU64::new(0),
+            last_event_ms: AtomicI64::new(0),
+            ledger_events: AtomicU64::new(0),
+            ledger_bytes: AtomicU64::new(0),
+            ledger_writable: AtomicBool::new(true),
+            last_flush_ms: AtomicI64::new(0),
+            flush_interval_ms: AtomicU64::new(crate::Config::default().flush_interval_ms),
+            chain_valid: AtomicI64::new(-1),
+            last_verified: AtomicI64::new(0),
+        }
+    }
+}
+
+impl DaemonMetrics {
+    /// An event passed the ingest filter and was handed to the ledger.
+    pub fn record_ingested(&self, kind: &str, at: DateTime<Utc>) {
+        *self
+            .events_by_kind
+            .lock()
+            .unwrap()
+            .entry(kind.to_string())
+            .or_insert(0) += 1;
+        self.last_event_ms
+            .store(at.timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// An event was dropped, sampled out or aggregated by the ingest filter.
+    pub fn record_omitted(&self) {
+        self.events_omitted.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A batch of `batch` events was committed to SQLite in `took`.
+    pub fn record_flush(&self, batch: usize, took: Duration) {
+        self.flush_seconds
+            .lock()
+            .unwrap()
+            .observe(took.as_secs_f64());
+        self.batch_size.lock().unwrap().observe(batch as f64);
+    }
+
+    /// Gateway events waiting in the reader channel.
+    pub fn set_channel_depth(&self, depth: usize) {
+        self.channel_depth.store(depth as u64, Ordering::Relaxed);
+    }
+
+    pub fn set_connected(&self, connected: bool) {
+        self.connected.store(connected, Ordering::Relaxed);
+        if connected {
+            self.backoff_ms.store(0, Ordering::Relaxed);
+        }
+    }
+
+    pub fn record_reconnect(&self) {
+        self.reconnects.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn set_backoff(&self, backoff: Duration) {
+        self.backoff_ms
+            .store(backoff.as_millis() as u64, Ordering::Relaxed);
+    }
+
+    /// The gateway sequence number jumped, skipping `missed` events.
+    pub fn record_seq_gap(&self, missed: u64) {
+        self.seq_gaps.fetch_add(1, Ordering::Relaxed);
+        self.seq_missed.fetch_add(missed, Ordering::Relaxed);
+    }
+
+    pub fn set_ledger(&self, events: u64, bytes: u64) {
+        self.ledger_events.store(events, Ordering::Relaxed);
+        self.ledger_bytes.store(bytes, Ordering::Relaxed);
+    }
+
+    pub fn ledger_events(&self) -> u64 {
+        self.ledger_events.load(Ordering::Relaxed)
+    }
+
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// The periodic flush succeeded (even with nothing to write).
+    pub fn record_flushed(&self, at: DateTime<Utc>) {
+        self.ledger_writable.store(true, Ordering::Relaxed);
+        self.last_flush_ms
+            .store(at.timestamp_millis(), Ordering::Relaxed);
+    }
+
+    /// Appending to or flushing the ledger failed.
+    pub fn record_write_error(&self) {
+        self.ledger_writable.store(false, Ordering::Relaxed);
+    }
+
+    pub fn set_flush_interval(&self, interval_ms: u64) {
+        self.flush_interval_ms.store(interval_ms, Ordering::Relaxed);
+    }
+
+    /// Whether the daemon is recording: connected, writing, and flushing on
+    /// schedule.
+    pub fn readiness(&self, now: DateTime<Utc>) -> Readiness {
+        let connected = self.is_connected();
+        let ledger_writable = self.ledger_writable.load(Ordering::Relaxed);
+        let last_flush_age_seconds = match self.last_flush_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(((now.timestamp_millis() - ms).max(0)) as f64 / 1000.0),
+        };
+        let interval = Duration::from_millis(self.flush_interval_ms.load(Ordering::Relaxed));
+        let stale_after = (interval * FLUSH_STALENESS_INTERVALS).max(MIN_FLUSH_STALENESS);
+        let flush_recent =
+            last_flush_age_seconds.is_some_and(|age| age <= stale_after.as_secs_f64());
+        Readiness {
+            ready: connected && ledger_writable && flush_recent,
+            connected,
+            ledger_writable,
+            last_flush_age_seconds,
+        }
+    }
+
+    /// Result of the latest hash chain verification.
+    pub fn set_verification(&self, valid: bool, at: DateTime<Utc>) {
+        self.chain_valid.store(valid as i64, Ordering::Relaxed);
+        self.last_verified.store(at.timestamp(), Ordering::Relaxed);
+    }
+
+    /// Seconds since the last ingested event, if any.
+    pub fn last_event_age(&self, now: DateTime<Utc>) -> Option<f64> {
+        match self.last_event_ms.load(Ordering::Relaxed) {
+            0 => None,
+            ms => Some(((now.timestamp_millis() - ms).max(0)) as f64 / 1000.0),
+        }
+    }
+
+    /// Render in the Prometheus text exposition format.
+    pub fn render(&self, now: DateTime<Utc>) -> String {
+        let mut out = String::new();
+
+        header(
+            &mut out,
+            "clawprint_events_ingested_total",
+            "Events written to the ledger, by kind",
+            "counter",
+        );
+        for (kind, count) in self.events_by_kind.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "clawprint_events_ingested_total{{kind=\"{}\"}} {}",
+                escape_label(kind),
+                count
+            );
+        }
+        counter(
+            &mut out,
+            "clawprint_events_omitted_total",
+            "Events dropped, sampled out or aggregated by the ingest filter",
+            self.events_omitted.load(Ordering::Relaxed),
+        );
+
+        self.flush_seconds.lock().unwrap().render(
+            &mut out,
+            "clawprint_flush_duration_seconds",
+            "Time to commit one batch to SQLite",
+        );
+        self.batch_size.lock().unwrap().render(
+            &mut out,
+            "clawprint_flush_batch_size",
+            "Events per SQLite commit",
+        );
+
+        gauge(
+            &mut out,
+            "clawprint_channel_depth",
+            "Gateway events waiting to be written",
+            self.channel_depth.load(Ordering::Relaxed) as f64,
+        );
+        gauge(
+            &mut out,
+            "clawprint_gateway_connected",
+            "1 while connected to the gateway",
+            self.connected.load(Ordering::Relaxed) as u8 as f64,
+        );
+        counter(
+            &mut out,
+            "clawprint_reconnects_total",
+            "Gateway reconnect attempts",
+            self.reconnects.load(Ordering::Relaxed),
+        );
+        gauge(
+            &mut out,
+            "clawprint_backoff_seconds",
+            "Current reconnect backoff (0 while connected)",
+            self.backoff_ms.load(Ordering::Relaxed) as f64 / 1000.0,
+        );
+        counter(
+            &mut out,
+            "clawprint_seq_gaps_total",
+            "Jumps in the gateway sequence number",
+            self.seq_gaps.load(Ordering::Relaxed),
+        );
+        counter(
+            &mut out,
+            "clawprint_seq_missed_events_total",
+            "Events skipped by gateway sequence gaps",
+            self.seq_missed.load(Ordering::Relaxed),
+        );
+        if let Some(age) = self.last_event_age(now) {
+            gauge(
+                &mut out,
+                "clawprint_last_event_age_seconds",
+                "Seconds since the last event was ingested",
+                age,
+            );
+        }
+
+        gauge(
+            &mut out,
+            "clawprint_ledger_events",
+            "Events in the ledger",
+            self.ledger_events.load(Ordering::Relaxed) as f64,
+        );
+        gauge(
+            &mut out,
+            "clawprint_ledger_size_bytes",
+            "Ledger database size on disk",
+            self.ledger_bytes.load(Ordering::Relaxed) as f64,
+        );
+        gauge(
+            &mut out,
+            "clawprint_ledger_writable",
+            "0 after a failed ledger write, until the next successful flush",
+            self.ledger_writable.load(Ordering::Relaxed) as u8 as f64,
+        );
+        let valid = self.chain_valid.load(Ordering::Relaxed);
+        if valid >= 0 {
+            gauge(
+                &mut out,
+                "clawprint_ledger_chain_valid",
+                "1 if the last hash chain verification passed",
+                valid as f64,
+            );
+            gauge(
+                &mut out,
+                "clawprint_ledger_last_verified_timestamp_seconds",
+                "Unix time of the last hash chain verification",
+                self.last_verified.load(Ordering::Relaxed) as f64,
+            );
+        }
+
+        out
+    }
+}
+
+/// Body of `/readyz`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Readiness {
+    pub ready: bool,
+    pub connected: bool,
+    pub ledger_writable: bool,
+    pub last_flush_age_seconds: Option<f64>,
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn counter(out: &mut String, name: &str, help: &str, value: u64) {
+    header(out, name, help, "counter");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, value: f64) {
+    header(out, name, help, "gauge");
+    let _ = writeln!(out, "{} {}", name, value);
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_exposition_format() {
+        let m = DaemonMetrics::default();
+        let t0 = Utc::now();
+        m.record_ingested("AGENT_EVENT", t0);
+        m.record_ingested("AGENT_EVENT", t0);
+        m.record_ingested("TICK", t0);
+        m.record_flush(3, Duration::from_millis(4));
+        m.record_seq_gap(5);
+        m.set_connected(true);
+
+        let text = m.render(t0 + chrono::Duration::seconds(30));
+        assert!(text.contains("clawprint_events_ingested_total{kind=\"AGENT_EVENT\"} 2\n"));
+        assert!(text.contains("clawprint_flush_batch_size_bucket{le=\"1\"} 0\n"));
+        assert!(text.contains("clawprint_flush_batch_size_bucket{le=\"5\"} 1\n"));
+        assert!(text.contains("clawprint_flush_duration_seconds_count 1\n"));
+        assert!(text.contains("clawprint_seq_missed_events_total 5\n"));
+        assert!(text.contains("clawprint_gateway_connected 1\n"));
+        assert!(text.contains("clawprint_last_event_age_seconds 30\n"));
+        assert!(
+            !text.contains("clawprint_ledger_chain_valid"),
+            "not verified yet"
+        );
+
+        m.set_verification(true, t0);
+        assert!(m.render(t0).contains("clawprint_ledger_chain_valid 1\n"));
+    }
+
+    #[test]
+    fn test_readiness() {
+        let m = DaemonMetrics::default();
+        let t0 = Utc::now();
+        assert!(!m.readiness(t0).ready, "no flush yet");
+
+        m.set_connected(true);
+        m.record_flushed(t0);
+        assert!(m.readiness(t0 + chrono::Duration::seconds(2)).ready);
+
+        let stale = m.readiness(t0 + chrono::Duration::seconds(60));
+        assert!(!stale.ready);
+        assert_eq!(stale.last_flush_age_seconds, Some(60.0));
+
+        m.record_write_error();
+        let broken = m.readiness(t0);
+        assert!(!broken.ready && !broken.ledger_writable && broken.connected);
+
+        m.record_flushed(t0);
+        m.set_connected(false);
+        assert!(!m.readiness(t0).ready);
+    }
+}
@@ -0,0 +1,742 @@
+//! OpenTelemetry export — agent runs as OTLP traces
+//!
+//! Each agent run becomes one trace: a root `agent_run` span from its first
+//! to its last event, an `execute_tool {name}` child span per tool call (from
+//! the `tool_use` event to its `tool_result`) with arguments and result as
+//! attributes, and final chat messages as events on the root span. Every
+//! span and span event carries `clawprint.event.hash`, the `hash_self` of
+//! the ledger event it came from, so a trace can be checked against the
+//! chain.
+//!
+//! Traces are sent as OTLP/HTTP JSON to `{endpoint}/v1/traces`. Trace and
+//! span IDs are derived from the agent run ID and event hashes, so exporting
+//! a run twice yields the same IDs. `clawprint export --otlp` sends a time
+//! range from the ledger. The daemon exports each run live when its
+//! lifecycle ends, through a sink of type `otlp`.
+
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::ledger::Ledger;
+use crate::sink::Sink;
+use crate::{Event, EventKind};
+
+/// Attribute values longer than this are cut, e.g. large tool results
+pub const MAX_ATTRIBUTE_LEN: usize = 8192;
+/// Agent runs per export request
+const RUNS_PER_REQUEST: usize = 20;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+const SPAN_KIND_INTERNAL: u8 = 1;
+const STATUS_ERROR: u8 = 2;
+
+/// Posts traces to an OTLP/HTTP collector.
+pub struct OtlpExporter {
+    url: String,
+    headers: BTreeMap<String, String>,
+    service_name: String,
+    client: reqwest::Client,
+}
+
+impl OtlpExporter {
+    /// `endpoint` is the collector base URL (`http://localhost:4318`) or the
+    /// full `/v1/traces` URL.
+    pub fn new(
+        endpoint: &str,
+        headers: BTreeMap<String, String>,
+        service_name: impl Into<String>,
+    ) -> Result<Self> {
+        let endpoint = endpoint.trim_end_matches('/');
+        let url = if endpoint.ends_with("/v1/traces") {
+            endpoint.to_string()
+        } else {
+            format!("{}/v1/traces", endpoint)
+        };
+        reqwest::Url::parse(&url).with_context(|| format!("invalid OTLP endpoint {}", url))?;
+        Ok(Self {
+            url,
+            headers,
+            service_name: service_name.into(),
+            client: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?,
+        })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Export agent runs (ID and events in chain order) in one request.
+    pub async fn export(&self, runs: &[(String, Vec<Event>)]) -> Result<usize> {
+        let spans: Vec<Value> = runs
+            .iter()
+            .flat_map(|(id, events)| run_to_spans(id, events))
+            .collect();
+        if spans.is_empty() {
+            return Ok(0);
+        }
+        let count = spans.len();
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(export_request(&self.service_name, spans).to_string());
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        let body: Value = serde_json::from_str(&text).unwrap_or(Value::Null);
+        if !status.is_success() {
+            bail!("{} returned {}: {}", self.url, status, text);
+        }
+        if let Some(rejected) = body
+            .pointer("/partialSuccess/rejectedSpans")
+            .and_then(|v| {
+                v.as_str()
+                    .and_then(|s| s.parse::<u64>().ok())
+                    .or(v.as_u64())
+            })
+            .filter(|n| *n > 0)
+        {
+            warn!(
+                "Collector rejected {} of {} spans: {}",
+                rejected, count, body["partialSuccess"]["errorMessage"]
+            );
+        }
+        Ok(count)
+    }
+}
+
+/// What `export_range` sent.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExportStats {
+    pub runs: usize,
+    pub spans: usize,
+}
+
+/// Export every agent run with events between `since` and `until`.
+pub async fn export_range(
+    ledger: &Ledger,
+    exporter: &OtlpExporter,
+    since: Option<DateTime<Utc>>,
+    until: Option<DateTime<Utc>>,
+) -> Result<ExportStats> {
+    let mut summaries = ledger.list_agent_runs(since, until, i64::MAX as usize)?;
+    summaries.reverse(); // oldest first
+    let mut stats = ExportStats::default();
+    for chunk in summaries.chunks(RUNS_PER_REQUEST) {
+        let runs = chunk
+            .iter()
+            .map(|s| {
+                let events = ledger.get_agent_run_events(&s.agent_run_id)?;
+                Ok((s.agent_run_id.clone(), events))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        stats.spans += exporter.export(&runs).await?;
+        stats.runs += runs.len();
+    }
+    Ok(stats)
+}
+
+/// The agent run whose lifecycle `event` ends, if any.
+pub fn completed_run(event: &Event) -> Option<&str> {
+    let data = event.payload.get("data")?;
+    let phase = data.pointer("/data/phase").and_then(|v| v.as_str());
+    if data.get("stream").and_then(|v| v.as_str()) == Some("lifecycle")
+        && matches!(phase, Some("end") | Some("error"))
+    {
+        data.get("runId").and_then(|v| v.as_str())
+    } else {
+        None
+    }
+}
+
+/// Convert one agent run to OTLP JSON spans: the root span first, then one
+/// span per tool call.
+pub fn run_to_spans(agent_run: &str, events: &[Event]) -> Vec<Value> {
+    let (Some(first), Some(last)) = (events.first(), events.last()) else {
+        return Vec::new();
+    };
+    let trace_id = hex::encode(&Sha256::digest(format!("clawprint:{}", agent_run))[..16]);
+    let root_id = span_id(&format!("run:{}", agent_run));
+
+    let mut root_attrs = vec![
+        attr("gen_ai.operation.name", "invoke_agent"),
+        attr("clawprint.agent_run.id", agent_run),
+        attr("clawprint.run.id", first.run_id.0.as_str()),
+        attr("clawprint.event.count", events.len() as i64),
+        attr("clawprint.event.first_hash", first.hash_self.as_str()),
+        attr("clawprint.event.hash", last.hash_self.as_str()),
+    ];
+    if let Some(session) = events.iter().find_map(|e| {
+        e.payload
+            .pointer("/data/sessionKey")
+            .and_then(|v| v.as_str())
+    }) {
+        root_attrs.push(attr("clawprint.session_key", session));
+    }
+
+    let mut root_status = None;
+    let mut root_events = Vec::new();
+    let mut tool_spans: Vec<ToolSpan> = Vec::new();
+    let mut deltas = String::new();
+    let mut last_delta: Option<&Event> = None;
+
+    for event in events {
+        let data = event.payload.get("data");
+        let field = |name: &str| data.and_then(|d| d.get(name));
+        match event.kind {
+            EventKind::AgentEvent => match field("type").and_then(|v| v.as_str()) {
+                Some("tool_use") => tool_spans.push(ToolSpan::start(event)),
+                Some("tool_result") => {
+                    let call_id = call_id(data);
+                    let tool = field("tool").and_then(|v| v.as_str());
+                    let open = tool_spans.iter_mut().find(|s| {
+                        s.result.is_none()
+                            && match (&call_id, &s.call_id) {
+                                (Some(a), Some(b)) => a == b,
+                                _ => tool == Some(s.tool.as_str()),
+                            }
+                    });
+                    if let Some(span) = open {
+                        span.result = Some(event);
+                    }
+                }
+                _ => {
+                    if completed_run(event).is_some()
+                        && event
+                            .payload
+                            .pointer("/data/data/phase")
+                            .and_then(|v| v.as_str())
+                            == Some("error")
+                    {
+                        let message = event
+                            .payload
+                            .pointer("/data/data/error")
+                            .map(display)
+                            .unwrap_or_default();
+                        root_status = Some(json!({ "code": STATUS_ERROR, "message": message }));
+                    }
+                }
+            },
+            EventKind::OutputChunk => {
+                if field("state").and_then(|v| v.as_str()) == Some("final") {
+                    if let Some(text) = field("text")
+                        .or_else(|| field("content"))
+                        .and_then(|v| v.as_str())
+                    {
+                        root_events.push(chat_event(event, "final", text));
+                    }
+                } else if let Some(delta) = field("delta").and_then(|v| v.as_str()) {
+                    deltas.push_str(delta);
+                    last_delta = Some(event);
+                }
+            }
+            _ => {}
+        }
+    }
+    // Streams that never sent a final message: report what was streamed
+    if root_events.is_empty()
+        && let Some(event) = last_delta
+    {
+        root_events.push(chat_event(event, "partial", &deltas));
+    }
+
+    let mut root = json!({
+        "traceId": trace_id,
+        "spanId": root_id,
+        "name": "agent_run",
+        "kind": SPAN_KIND_INTERNAL,
+        "startTimeUnixNano": nanos(first.ts),
+        "endTimeUnixNano": nanos(last.ts),
+        "attributes": root_attrs,
+        "events": root_events,
+    });
+    if let Some(status) = root_status {
+        root["status"] = status;
+    }
+
+    let mut spans = vec![root];
+    spans.extend(
+        tool_spans
+            .into_iter()
+            .map(|s| s.to_span(&trace_id, &root_id)),
+    );
+    spans
+}
+
+/// Wrap spans in an `ExportTraceServiceRequest`.
+pub fn export_request(service_name: &str, spans: Vec<Value>) -> Value {
+    json!({
+        "resourceSpans": [{
+            "resource": {
+                "attributes": [
+                    attr("service.name", service_name),
+                    attr("service.version", env!("CARGO_PKG_VERSION")),
+                ],
+            },
+            "scopeSpans": [{
+                "scope": { "name": "clawprint", "version": env!("CARGO_PKG_VERSION") },
+                "spans": spans,
+            }],
+        }],
+    })
+}
+
+struct ToolSpan<'a> {
+    call: &'a Event,
+    tool: String,
+    call_id: Option<String>,
+    result: Option<&'a Event>,
+}
+
+impl<'a> ToolSpan<'a> {
+    fn start(call: &'a Event) -> Self {
+        let data = call.payload.get("data");
+        Self {
+            call,
+            tool: data
+                .and_then(|d| d.get("tool"))
+                .and_then(|v| v.as_str())
+                .unwrap_or("unknown")
+                .to_string(),
+            call_id: call_id(data),
+            result: None,
+        }
+    }
+
+    fn to_span(&self, trace_id: &str, parent: &str) -> Value {
+        let data = self.call.payload.get("data");
+        let mut attrs = vec![
+            attr("gen_ai.operation.name", "execute_tool"),
+            attr("gen_ai.tool.name", self.tool.as_str()),
+            attr("clawprint.event.id", self.call.event_id.0 as i64),
+            attr("clawprint.event.hash", self.call.hash_self.as_str()),
+        ];
+        if let Some(id) = &self.call_id {
+            attrs.push(attr("gen_ai.tool.call.id", id.as_str()));
+        }
+        if let Some(args) = data.and_then(|d| d.get("args")) {
+            attrs.push(attr("clawprint.tool.args", display(args)));
+        }
+        let mut status = None;
+        if let Some(result) = self.result {
+            let data = result.payload.get("data");
+            if let Some(output) = data.and_then(|d| {
+                ["result", "output", "content"]
+                    .iter()
+                    .find_map(|k| d.get(*k))
+            }) {
+                attrs.push(attr("clawprint.tool.result", display(output)));
+            }
+            attrs.push(attr(
+                "clawprint.result.event.hash",
+                result.hash_self.as_str(),
+            ));
+            let failed = data.is_some_and(|d| {
+                ["isError", "is_error", "error"]
+                    .iter()
+                    .any(|k| d.get(*k).is_some_and(|v| v.as_bool() == Some(true)))
+            });
+            if failed {
+                status = Some(json!({ "code": STATUS_ERROR }));
+            }
+        }
+
+        let mut span = json!({
+            "traceId": trace_id,
+            "spanId": span_id(&self.call.hash_self),
+            "parentSpanId": parent,
+            "name": format!("execute_tool {}", self.tool),
+            "kind": SPAN_KIND_INTERNAL,
+            "startTimeUnixNano": nanos(self.call.ts),
+            "endTimeUnixNano": nanos(self.result.map_or(self.call.ts, |r| r.ts)),
+            "attributes": attrs,
+        });
+        if let Some(status) = status {
+            span["status"] = status;
+        }
+        span
+    }
+}
+
+fn call_id(data: Option<&Value>) -> Option<String> {
+    let data = data?;
+    ["toolCallId", "callId", "id"]
+        .iter()
+        .find_map(|k| data.get(*k))
+        .map(|v| v.as_str().map_or_else(|| v.to_string(), str::to_string))
+}
+
+fn chat_event(event: &Event, state: &str, text: &str) -> Value {
+    json!({
+        "timeUnixNano": nanos(event.ts),
+        "name": "chat",
+        "attributes": [
+            attr("clawprint.chat.state", state),
+            attr("clawprint.chat.text", text),
+            attr("clawprint.event.hash", event.hash_self.as_str()),
+        ],
+    })
+}
+
+fn span_id(seed: &str) -> String {
+    hex::encode(&Sha256::digest(seed.as_bytes())[..8])
+}
+
+fn nanos(ts: DateTime<Utc>) -> String {
+    ts.timestamp_nanos_opt().unwrap_or_default().to_string()
+}
+
+/// Strings as-is, anything else as compact JSON
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// An OTLP `KeyValue`
+fn attr(key: &str, value: impl Into<AttrValue>) -> Value {
+    let value = match value.into() {
+        AttrValue::Str(s) => json!({ "stringValue": truncate(s) }),
+        AttrValue::Int(i) => json!({ "intValue": i.to_string() }),
+    };
+    json!({ "key": key, "value": value })
+}
+
+enum AttrValue {
+    Str(String),
+    Int(i64),
+}
+
+impl From<&str> for AttrValue {
+    fn from(s: &str) -> Self {
+        Self::Str(s.to_string())
+    }
+}
+
+impl From<String> for AttrValue {
+    fn from(s: String) -> Self {
+        Self::Str(s)
+    }
+}
+
+impl From<i64> for AttrValue {
+    fn from(i: i64) -> Self {
+        Self::Int(i)
+    }
+}
+
+fn truncate(mut s: String) -> String {
+    if s.len() > MAX_ATTRIBUTE_LEN {
+        let mut end = MAX_ATTRIBUTE_LEN;
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+        s.truncate(end);
+        s.push_str("…[truncated]");
+    }
+    s
+}
+
+/// Sink that exports each agent run when its lifecycle ends, reading the
+/// whole run back from the ledger.
+pub struct OtlpSink {
+    name: String,
+    batch_size: usize,
+    exporter: OtlpExporter,
+    reader: Option<Ledger>,
+}
+
+impl OtlpSink {
+    pub fn new(name: String, batch_size: usize, exporter: OtlpExporter) -> Self {
+        Self {
+            name,
+            batch_size,
+            exporter,
+            reader: None,
+        }
+    }
+}
+
+impl Sink for OtlpSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    fn open(&mut self, dir: &Path) -> Result<()> {
+        self.reader = Some(Ledger::open_readonly(dir)?);
+        Ok(())
+    }
+
+    fn deliver<'a>(&'a mut self, events: &'a [Arc<Event>]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            // Read before awaiting: a `&Ledger` may not be held across it
+            let runs = {
+                let Some(reader) = &self.reader else {
+                    bail!("OTLP sink used before open");
+                };
+                events
+                    .iter()
+                    .filter_map(|e| completed_run(e))
+                    .map(|id| Ok((id.to_string(), reader.get_agent_run_events(id)?)))
+                    .collect::<Result<Vec<_>>>()?
+            };
+            if runs.is_empty() {
+                return Ok(());
+            }
+            let spans = self.exporter.export(&runs).await?;
+            info!(
+                "Exported {} agent run(s) as {} spans to {}",
+                runs.len(),
+                spans,
+                self.exporter.url()
+            );
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventId, RunId};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+
+    fn agent(ledger: &mut Ledger, data: Value) {
+        ledger
+            .append_event(Event::new(
+                RunId("daemon".into()),
+                EventId(0),
+                EventKind::AgentEvent,
+                json!({ "gateway_event": "agent", "data": data }),
+                None,
+            ))
+            .unwrap();
+    }
+
+    fn chat(ledger: &mut Ledger, data: Value) {
+        ledger
+            .append_event(Event::new(
+                RunId("daemon".into()),
+                EventId(0),
+                EventKind::OutputChunk,
+                json!({ "gateway_event": "chat", "data": data }),
+                None,
+            ))
+            .unwrap();
+    }
+
+    /// One-request OTLP collector stand-in that answers with `response` and
+    /// returns the posted head and body.
+    async fn collector(
+        response: &'static [u8],
+    ) -> (String, tokio::task::JoinHandle<(String, Value)>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let mut reader = tokio::io::BufReader::new(socket);
+            let mut head = String::new();
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                head.push_str(&line);
+            }
+            let length: usize = head
+                .lines()
+                .find_map(|l| {
+                    l.to_ascii_lowercase()
+                        .strip_prefix("content-length: ")
+                        .map(|v| v.trim().parse().unwrap())
+                })
+                .unwrap();
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+            reader.get_mut().write_all(response).await.unwrap();
+            (head, serde_json::from_slice(&body).unwrap())
+        });
+        (url, handle)
+    }
+
+    const OK: &[u8] = b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\n{}";
+
+    /// A ledger holding agent run `r1`: one tool call, a streamed chat reply,
+    /// and a lifecycle end.
+    fn recorded_run() -> (tempfile::TempDir, Ledger) {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ledger = Ledger::open(dir.path(), 100).unwrap();
+        ledger.set_assign_policy(crate::assign::AssignPolicy::correlated());
+        agent(
+            &mut ledger,
+            json!({"runId": "r1", "sessionKey": "main", "stream": "lifecycle", "data": {"phase": "start"}}),
+        );
+        agent(
+            &mut ledger,
+            json!({"runId": "r1", "type": "tool_use", "tool": "bash", "toolCallId": "c1", "args": {"cmd": "ls"}}),
+        );
+        agent(
+            &mut ledger,
+            json!({"runId": "r1", "type": "tool_result", "tool": "bash", "toolCallId": "c1", "result": "a.txt"}),
+        );
+        chat(&mut ledger, json!({"delta": "Do"}));
+        chat(
+            &mut ledger,
+            json!({"state": "final", "text": "Done: a.txt"}),
+        );
+        agent(
+            &mut ledger,
+            json!({"runId": "r1", "stream": "lifecycle", "data": {"phase": "end"}}),
+        );
+        ledger.flush().unwrap();
+        (dir, ledger)
+    }
+
+    fn attr(span: &Value, key: &str) -> Option<Value> {
+        span["attributes"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|a| a["key"] == key)
+            .map(|a| a["value"].clone())
+    }
+
+    #[test]
+    fn test_completed_run_on_lifecycle_end_only() {
+        let (_dir, ledger) = recorded_run();
+        let events = ledger.get_agent_run_events("r1").unwrap();
+        assert_eq!(completed_run(events.last().unwrap()), Some("r1"));
+        assert!(
+            events[..events.len() - 1]
+                .iter()
+                .all(|e| completed_run(e).is_none())
+        );
+    }
+
+    #[test]
+    fn test_tool_call_is_child_span_of_run() {
+        let (_dir, ledger) = recorded_run();
+        let events = ledger.get_agent_run_events("r1").unwrap();
+        let spans = run_to_spans("r1", &events);
+        assert_eq!(spans.len(), 2);
+        let (root, tool) = (&spans[0], &spans[1]);
+        assert_eq!(root["name"], "agent_run");
+        assert_eq!(root["traceId"].as_str().unwrap().len(), 32);
+        assert_eq!(tool["traceId"], root["traceId"]);
+        assert_eq!(tool["parentSpanId"], root["spanId"]);
+        assert_eq!(tool["name"], "execute_tool bash");
+        assert_eq!(
+            attr(tool, "clawprint.tool.args").unwrap()["stringValue"],
+            r#"{"cmd":"ls"}"#
+        );
+        assert_eq!(
+            attr(tool, "clawprint.tool.result").unwrap()["stringValue"],
+            "a.txt"
+        );
+        assert_eq!(
+            attr(root, "clawprint.session_key").unwrap()["stringValue"],
+            "main"
+        );
+    }
+
+    #[test]
+    fn test_spans_carry_event_hashes() {
+        let (_dir, ledger) = recorded_run();
+        let events = ledger.get_agent_run_events("r1").unwrap();
+        let spans = run_to_spans("r1", &events);
+        assert_eq!(
+            attr(&spans[1], "clawprint.event.hash").unwrap()["stringValue"],
+            events[1].hash_self.as_str()
+        );
+        assert_eq!(
+            attr(&spans[0], "clawprint.event.hash").unwrap()["stringValue"],
+            events.last().unwrap().hash_self.as_str()
+        );
+    }
+
+    #[test]
+    fn test_final_chat_becomes_span_event() {
+        let (_dir, ledger) = recorded_run();
+        let events = ledger.get_agent_run_events("r1").unwrap();
+        let spans = run_to_spans("r1", &events);
+        let chat = &spans[0]["events"][0];
+        assert_eq!(chat["name"], "chat");
+        assert_eq!(
+            attr(chat, "clawprint.chat.text").unwrap()["stringValue"],
+            "Done: a.txt"
+        );
+    }
+
+    #[test]
+    fn test_span_ids_stable_across_exports() {
+        let (_dir, ledger) = recorded_run();
+        let events = ledger.get_agent_run_events("r1").unwrap();
+        let first = run_to_spans("r1", &events);
+        let second = run_to_spans("r1", &events);
+        assert_eq!(first[0]["traceId"], second[0]["traceId"]);
+        assert_eq!(first[1]["spanId"], second[1]["spanId"]);
+    }
+
+    #[test]
+    fn test_exporter_endpoint_normalized_and_validated() {
+        let exporter = OtlpExporter::new("http://localhost:4318/", BTreeMap::new(), "c").unwrap();
+        assert_eq!(exporter.url(), "http://localhost:4318/v1/traces");
+        let exporter =
+            OtlpExporter::new("http://localhost:4318/v1/traces", BTreeMap::new(), "c").unwrap();
+        assert_eq!(exporter.url(), "http://localhost:4318/v1/traces");
+        assert!(OtlpExporter::new("not a url", BTreeMap::new(), "c").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_export_range_posts_to_collector() {
+        let (_dir, ledger) = recorded_run();
+        let (url, request) = collector(OK).await;
+        let mut headers = BTreeMap::new();
+        headers.insert("x-api-key".to_string(), "k".to_string());
+        let exporter = OtlpExporter::new(&url, headers, "clawprint").unwrap();
+        let stats = export_range(&ledger, &exporter, None, None).await.unwrap();
+        assert_eq!(stats, ExportStats { runs: 1, spans: 2 });
+
+        let (head, body) = request.await.unwrap();
+        assert!(head.starts_with("POST /v1/traces "));
+        assert!(head.to_ascii_lowercase().contains("x-api-key: k"));
+        let resource = &body["resourceSpans"][0];
+        assert_eq!(
+            attr(&resource["resource"], "service.name").unwrap()["stringValue"],
+            "clawprint"
+        );
+        assert_eq!(
+            resource["scopeSpans"][0]["spans"].as_array().unwrap().len(),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_export_fails_on_collector_error() {
+        let (_dir, ledger) = recorded_run();
+        let (url, _request) =
+            collector(b"HTTP/1.1 503 Service Unavailable\r\ncontent-length: 4\r\n\r\nbusy").await;
+        let exporter = OtlpExporter::new(&url, BTreeMap::new(), "clawprint").unwrap();
+        let err = export_range(&ledger, &exporter, None, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("503"));
+    }
+}
@@ -0,0 +1,430 @@
+//! PII detectors for the redaction pipeline
+//!
+//! Optional detectors for personal data in payload strings: email
+//! addresses, phone numbers, payment card numbers (Luhn-checked), IBANs
+//! (mod-97-checked) and US social security numbers. Each is off unless
+//! `[redaction.pii]` gives it an action:
+//!
+//! - `mask` — `[REDACTED-EMAIL]`
+//! - `hash` — `[EMAIL:1f3a9c0b]`, a SHA-256 prefix of the normalized value, so
+//!   the same address always gets the same token; keyed (HMAC) in
+//!   `pseudonymize` mode. An unkeyed hash is not anonymization: anyone can
+//!   hash candidates and compare. Phone, card, IBAN and SSN values are few
+//!   enough to enumerate, so `hash` on those needs `pseudonymize` mode
+//! - `last4` — `[CARD:****4242]`
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::redact::Pseudonymizer;
+
+/// What to do with a detected value
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiAction {
+    #[default]
+    Off,
+    Mask,
+    Hash,
+    Last4,
+}
+
+/// `[redaction.pii]` — one action per detector
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PiiRules {
+    pub email: PiiAction,
+    /// E.164 (`+4915112345678`) and separated 3-3-4 numbers
+    pub phone: PiiAction,
+    /// 13–19 digit card numbers with a known prefix and valid Luhn digit
+    pub card: PiiAction,
+    pub iban: PiiAction,
+    /// US social security numbers (`123-45-6789`)
+    pub ssn: PiiAction,
+}
+
+impl PiiRules {
+    pub fn is_enabled(&self) -> bool {
+        *self != Self::default()
+    }
+
+    /// Detectors set to `hash` whose values are small enough to recover
+    /// from an unkeyed hash by trying every candidate.
+    pub fn enumerable_hashes(&self) -> Vec<&'static str> {
+        [
+            ("phone", self.phone),
+            ("card", self.card),
+            ("iban", self.iban),
+            ("ssn", self.ssn),
+        ]
+        .into_iter()
+        .filter(|(_, action)| *action == PiiAction::Hash)
+        .map(|(name, _)| name)
+        .collect()
+    }
+
+    /// The same detectors with `hash` turned into `mask`.
+    pub fn masked(&self) -> Self {
+        let mask = |action| match action {
+            PiiAction::Hash => PiiAction::Mask,
+            other => other,
+        };
+        Self {
+            email: mask(self.email),
+            phone: mask(self.phone),
+            card: mask(self.card),
+            iban: mask(self.iban),
+            ssn: mask(self.ssn),
+        }
+    }
+}
+
+/// A compiled detector with its action.
+pub(crate) struct PiiDetector {
+    /// Rule name reported in detections, e.g. `pii-email`
+    pub name: &'static str,
+    label: &'static str,
+    pub pattern: Regex,
+    action: PiiAction,
+    validate: fn(&str) -> bool,
+    normalize: fn(&str) -> String,
+}
+
+impl PiiDetector {
+    /// True if a pattern match is the real thing (checksums, ranges).
+    pub fn accepts(&self, matched: &str) -> bool {
+        (self.validate)(matched)
+    }
+
+    /// True if the replacement is a hash token rather than a mask.
+    pub fn is_hashed(&self) -> bool {
+        self.action == PiiAction::Hash
+    }
+
+    /// `keyed` makes `hash` an HMAC under the pseudonymization key.
+    pub fn replacement(&self, matched: &str, keyed: Option<&Pseudonymizer>) -> String {
+        let normalized = (self.normalize)(matched);
+        match self.action {
+            PiiAction::Off | PiiAction::Mask => format!("[REDACTED-{}]", self.label),
+            PiiAction::Hash => {
+                let digest = match keyed {
+                    Some(p) => p.digest(&normalized),
+                    None => hex::encode(Sha256::digest(normalized.as_bytes())),
+                };
+                format!("[{}:{}]", self.label, &digest[..8])
+            }
+            PiiAction::Last4 => {
+                let tail: String = normalized
+                    .chars()
+                    .rev()
+                    .take(4)
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .rev()
+                    .collect();
+                format!("[{}:****{}]", self.label, tail)
+            }
+        }
+    }
+}
+
+/// Detectors switched on in `rules`, in the order they are applied: the
+/// longer numeric formats first so a card number isn't taken for a phone.
+pub(crate) fn detectors(rules: &PiiRules) -> Vec<PiiDetector> {
+    let all = [
+        (
+            rules.card,
+            "pii-card",
+            "CARD",
+            r"\+?\b\d(?:[ -]?\d){12,18}\b",
+            is_card as fn(&str) -> bool,
+            digits as fn(&str) -> String,
+        ),
+        (
+            rules.iban,
+            "pii-iban",
+            "IBAN",
+            r"\b[A-Z]{2}\d{2}(?: ?[A-Z0-9]{4}){2,7}(?: ?[A-Z0-9]{1,4})?\b",
+            is_iban,
+            alphanumeric,
+        ),
+        (
+            rules.ssn,
+            "pii-ssn",
+            "SSN",
+            r"\b\d{3}-\d{2}-\d{4}\b",
+            is_ssn,
+            digits,
+        ),
+        (
+            rules.phone,
+            "pii-phone",
+            "PHONE",
+            r"(?:\+[1-9]\d{7,14}\b|(?:\+1[ .-]?)?(?:\(\d{3}\)|\b\d{3})[ .-]\d{3}[ .-]\d{4}\b)",
+            is_phone,
+            digits,
+        ),
+        (
+            rules.email,
+            "pii-email",
+            "EMAIL",
+            r"\b[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}\b",
+            |_: &str| true,
+            |s: &str| s.to_lowercase(),
+        ),
+    ];
+    all.into_iter()
+        .filter(|(action, ..)| *action != PiiAction::Off)
+        .map(
+            |(action, name, label, pattern, validate, normalize)| PiiDetector {
+                name,
+                label,
+                pattern: Regex::new(pattern).expect("PII patterns must compile"),
+                action,
+                validate,
+                normalize,
+            },
+        )
+        .collect()
+}
+
+fn digits(s: &str) -> String {
+    s.chars().filter(char::is_ascii_digit).collect()
+}
+
+fn alphanumeric(s: &str) -> String {
+    s.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_uppercase())
+        .collect()
+}
+
+/// Luhn checksum over a digit string
+pub fn luhn_valid(digits: &str) -> bool {
+    let mut sum = 0;
+    for (i, c) in digits.chars().rev().enumerate() {
+        let Some(mut d) = c.to_digit(10) else {
+            return false;
+        };
+        if i % 2 == 1 {
+            d *= 2;
+            if d > 9 {
+                d -= 9;
+            }
+        }
+        sum += d;
+    }
+    !digits.is_empty() && sum % 10 == 0
+}
+
+/// Card length, issuer prefix (Visa, Mastercard, Amex, Discover, JCB,
+/// Diners, UnionPay) and Luhn digit. A leading `+` makes it a phone number.
+fn is_card(s: &str) -> bool {
+    if s.starts_with('+') {
+        return false;
+    }
+    let d = digits(s);
+    let prefix = |n: usize| d[..n].parse::<u32>().unwrap_or(0);
+    let known = d.starts_with('4')
+        || (51..=55).contains(&prefix(2))
+        || (2221..=2720).contains(&prefix(4))
+        || matches!(prefix(2), 34 | 37 | 36 | 38 | 35 | 62 | 65)
+        || d.starts_with("6011")
+        || (300..=305).contains(&prefix(3));
+    (13..=19).contains(&d.len()) && known && luhn_valid(&d)
+}
+
+/// ISO 13616 check digits (mod 97 == 1)
+pub fn iban_valid(iban: &str) -> bool {
+    let iban = alphanumeric(iban);
+    if !(15..=34).contains(&iban.len()) {
+        return false;
+    }
+    let (head, tail) = iban.split_at(4);
+    let mut remainder = 0u32;
+    for c in tail.chars().chain(head.chars()) {
+        let value = match c.to_digit(36) {
+            Some(v) => v,
+            None => return false,
+        };
+        remainder = if value >= 10 {
+            (remainder * 100 + value) % 97
+        } else {
+            (remainder * 10 + value) % 97
+        };
+    }
+    remainder == 1
+}
+
+fn is_iban(s: &str) -> bool {
+    iban_valid(s)
+}
+
+/// Area not 000, 666 or 9xx; group not 00; serial not 0000
+fn is_ssn(s: &str) -> bool {
+    let d = digits(s);
+    let (area, group, serial) = (&d[..3], &d[3..5], &d[5..]);
+    area != "000" && area != "666" && !area.starts_with('9') && group != "00" && serial != "0000"
+}
+
+fn is_phone(s: &str) -> bool {
+    (10..=15).contains(&digits(s).len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::redact::{RedactionMode, RedactionRules, Redactor};
+    use crate::vault::{Identity, Vault, VaultSettings, tokens_in};
+
+    fn all(action: PiiAction) -> PiiRules {
+        PiiRules {
+            email: action,
+            phone: action,
+            card: action,
+            iban: action,
+            ssn: action,
+        }
+    }
+
+    /// A redactor with only these detectors, so built-in rules don't fire.
+    fn redactor(pii: PiiRules) -> Redactor {
+        Redactor::new(&RedactionRules {
+            builtin: false,
+            pii,
+            ..RedactionRules::default()
+        })
+        .unwrap()
+    }
+
+    /// Redact `s`, returning the result and the rules that fired.
+    fn run(redactor: &Redactor, s: &str) -> (String, Vec<String>) {
+        let (out, found) = redactor.redact_string_report(s);
+        (out, found.into_iter().map(|d| d.rule).collect())
+    }
+
+    #[test]
+    fn test_detectors() {
+        let mask = redactor(all(PiiAction::Mask));
+        let cases: &[(&str, &str, &str)] = &[
+            (
+                "pii-email",
+                "mail jane.doe+x@example.co.uk now",
+                "user@localhost or a@b",
+            ),
+            ("pii-phone", "call +4915112345678", "build 20240115 v1.2.3"),
+            ("pii-phone", "call (415) 555-2671 today", "ratio 3.141.5926"),
+            (
+                "pii-card",
+                "card 4242 4242 4242 4242",
+                "card 4242 4242 4242 4241",
+            ),
+            ("pii-card", "amex 378282246310005", "ts 1700000000000000"),
+            (
+                "pii-iban",
+                "IBAN GB82 WEST 1234 5698 7654 32",
+                "GB82 WEST 1234 5698 7654 33",
+            ),
+            (
+                "pii-iban",
+                "DE89370400440532013000",
+                "DE00370400440532013000",
+            ),
+            (
+                "pii-ssn",
+                "ssn 123-45-6789",
+                "ssn 000-45-6789 or 123-00-6789",
+            ),
+        ];
+        for (rule, pii, benign) in cases {
+            let (out, fired) = run(&mask, pii);
+            assert_eq!(fired, [*rule], "{} on {:?} -> {}", rule, pii, out);
+            assert!(out.contains("[REDACTED-"), "{}", out);
+            let (out, fired) = run(&mask, benign);
+            assert_eq!(out, *benign, "{} false positive", rule);
+            assert!(fired.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_off_by_default() {
+        let (out, fired) = run(&redactor(PiiRules::default()), "a@example.com");
+        assert_eq!(out, "a@example.com");
+        assert!(fired.is_empty());
+    }
+
+    #[test]
+    fn test_last4() {
+        let (out, _) = run(&redactor(all(PiiAction::Last4)), "card 4242-4242-4242-4242");
+        assert_eq!(out, "card [CARD:****4242]");
+    }
+
+    #[test]
+    fn test_unkeyed_email_hash() {
+        let hash = redactor(PiiRules {
+            email: PiiAction::Hash,
+            ..PiiRules::default()
+        });
+        let (a, _) = run(&hash, "Jane@Example.com");
+        let (b, _) = run(&hash, "jane@example.com");
+        assert_eq!(a, b, "hash is over the normalized value");
+        assert!(a.starts_with("[EMAIL:") && a.len() == "[EMAIL:12345678]".len());
+    }
+
+    #[test]
+    fn test_allow_values() {
+        let redactor = Redactor::new(&RedactionRules {
+            builtin: false,
+            pii: all(PiiAction::Mask),
+            allow_values: vec![r"@example\.com$".into()],
+            ..RedactionRules::default()
+        })
+        .unwrap();
+        let (out, fired) = run(&redactor, "from ops@example.com to jane@acme.io");
+        assert_eq!(out, "from ops@example.com to [REDACTED-EMAIL]");
+        assert_eq!(fired, ["pii-email"]);
+    }
+
+    #[test]
+    fn test_keyed_hash_and_vault() {
+        let dir = tempfile::tempdir().unwrap();
+        let key_file = dir.path().join("pseudonym.key");
+        std::fs::write(&key_file, "11".repeat(32)).unwrap();
+        let investigator = Identity::generate().unwrap();
+        let rules = RedactionRules {
+            builtin: false,
+            pii: all(PiiAction::Hash),
+            mode: RedactionMode::Pseudonymize,
+            key_file: Some(key_file),
+            vault: Some(VaultSettings {
+                public_key: investigator.public_key_hex(),
+            }),
+            ..RedactionRules::default()
+        };
+        let ledger_dir = dir.path().join("ledger");
+        let redactor = Redactor::new(&rules)
+            .unwrap()
+            .with_vault(&ledger_dir)
+            .unwrap();
+        let (out, fired) = run(&redactor, "ssn 123-45-6789");
+        assert_eq!(fired, ["pii-ssn"]);
+        let unkeyed = hex::encode(Sha256::digest(b"123456789"));
+        assert!(!out.contains(&unkeyed[..8]), "hash is keyed: {}", out);
+
+        // The original is sealed under the token
+        let payload = serde_json::Value::String(out);
+        let tokens = tokens_in(&payload);
+        assert_eq!(tokens.len(), 1);
+        let entries = Vault::open(&ledger_dir)
+            .unwrap()
+            .entries(&tokens[0], &investigator.public_key())
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].rule, "pii-ssn");
+        assert_eq!(
+            investigator.open(&entries[0].sealed).unwrap(),
+            "123-45-6789"
+        );
+    }
+}
@@ -0,0 +1,139 @@
+//! Progress reporting for the recorders
+//!
+//! `RecordingSession` and the daemon never write to stdout or stderr; they
+//! report what they are doing to an [`Observer`]. The CLI renders it as a
+//! spinner or log lines, and an embedding application can forward it
+//! anywhere or ignore it with [`Headless`].
+
+use std::fmt;
+use std::time::Duration;
+
+/// What a recorder is doing. `Display` gives the one-line status the CLI
+/// spinner shows.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Progress<'a> {
+    /// Opening the gateway connection
+    Connecting,
+    Connected {
+        conn_id: &'a str,
+    },
+    /// A trace was written; `total` counts traces in the ledger or run
+    Event {
+        total: u64,
+        kind: &'a str,
+    },
+    /// Connection lost or failed; retrying after `after`
+    Reconnecting {
+        after: Duration,
+        error: Option<&'a str>,
+    },
+    /// Gateway settings were reloaded; reconnecting right away
+    Reconfigured,
+    /// The recorder stopped
+    Stopped {
+        total_events: u64,
+        size_bytes: u64,
+    },
+}
+
+impl fmt::Display for Progress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Connecting => write!(f, "Connecting to gateway..."),
+            Self::Connected { .. } => write!(f, "Connected. Recording..."),
+            Self::Event { total, kind } => write!(f, "{} events | Last: {}", total, kind),
+            Self::Reconnecting { after, error: None } => {
+                write!(f, "Disconnected. Reconnecting in {}s...", after.as_secs())
+            }
+            Self::Reconnecting {
+                after,
+                error: Some(_),
+            } => write!(f, "Error. Reconnecting in {}s...", after.as_secs()),
+            Self::Reconfigured => write!(f, "Configuration changed. Reconnecting..."),
+            Self::Stopped {
+                total_events,
+                size_bytes,
+            } => write!(
+                f,
+                "Stopped. {} events recorded, {} on disk.",
+                total_events,
+                format_bytes(*size_bytes)
+            ),
+        }
+    }
+}
+
+/// Receives progress from a running recorder. Called from the recording
+/// loop, so implementations must return quickly.
+pub trait Observer: Send + Sync {
+    fn on_progress(&self, progress: &Progress<'_>);
+}
+
+/// Observer that discards progress, for services and embedding.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Headless;
+
+impl Observer for Headless {
+    fn on_progress(&self, _progress: &Progress<'_>) {}
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+    if bytes >= GB {
+        format!("{:.1} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.1} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.1} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Collect(Mutex<Vec<String>>);
+
+    impl Observer for Collect {
+        fn on_progress(&self, progress: &Progress<'_>) {
+            self.0.lock().unwrap().push(progress.to_string());
+        }
+    }
+
+    #[test]
+    fn test_observer_messages() {
+        let observer = Collect::default();
+        for progress in [
+            Progress::Connecting,
+            Progress::Event {
+                total: 3,
+                kind: "TOOL_CALL",
+            },
+            Progress::Reconnecting {
+                after: Duration::from_secs(4),
+                error: Some("refused"),
+            },
+            Progress::Stopped {
+                total_events: 3,
+                size_bytes: 2048,
+            },
+        ] {
+            observer.on_progress(&progress);
+        }
+        assert_eq!(
+            *observer.0.lock().unwrap(),
+            [
+                "Connecting to gateway...",
+                "3 events | Last: TOOL_CALL",
+                "Error. Reconnecting in 4s...",
+                "Stopped. 3 events recorded, 2.0 KB on disk.",
+            ]
+        );
+    }
+}
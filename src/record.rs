@@ -1,43 +1,81 @@
 //! Recording session manager
 //!
-//! Coordinates gateway connection, event processing, and storage.
+//! Coordinates gateway connection, event processing, and storage. Progress
+//! goes to an [`Observer`]; nothing is printed.
 
 use anyhow::Result;
-use indicatif::{ProgressBar, ProgressStyle};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicU64, Ordering};
 use std::time::Duration;
 use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
 use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
 use crate::{
-    Config, Event, EventId, EventKind, RunId, RunMeta,
+    Config, Event, EventId, EventKind, RunId, RunMeta, RunStatus,
+    filter::{Admission, IngestFilter},
     gateway::{GatewayClient, GatewayEvent},
-    redact::redact_json,
+    progress::{Headless, Observer, Progress},
+    redact::Redactor,
     storage::RunStorage,
+    subscribe::EventBus,
 };
 
+/// How long `stop()` waits for the recording loop to write RUN_END and flush.
+pub const DEFAULT_STOP_TIMEOUT: Duration = Duration::from_secs(10);
+
 /// Summary returned after a recording session ends.
 pub struct RecordingSummary {
     pub duration_secs: i64,
     pub event_count: u64,
     pub size_bytes: u64,
     pub valid: bool,
+    /// False if the recording loop failed or timed out and RUN_END was
+    /// written by `stop()` as an unclean-shutdown marker.
+    pub clean_shutdown: bool,
     pub out_dir: std::path::PathBuf,
 }
 
+/// Optional hooks into a recording session.
+pub struct SessionOptions {
+    /// Progress reporting; [`Headless`] by default
+    pub observer: Arc<dyn Observer>,
+    /// Receives every event committed to the run
+    pub events: EventBus,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        Self {
+            observer: Arc::new(Headless),
+            events: EventBus::new(),
+        }
+    }
+}
+
 /// Active recording session
 pub struct RecordingSession {
     run_id: RunId,
     config: Config,
+    events: EventBus,
     storage: Arc<Mutex<RunStorage>>,
     shutdown_tx: mpsc::Sender<()>,
+    loop_handle: JoinHandle<Result<()>>,
 }
 
 impl RecordingSession {
-    /// Start a new recording session
+    /// Start a new recording session with default options
     pub async fn start(config: Config, run_name: Option<String>) -> Result<Self> {
+        Self::start_with_options(config, run_name, SessionOptions::default()).await
+    }
+
+    /// Start a new recording session with progress and event hooks
+    pub async fn start_with_options(
+        config: Config,
+        run_name: Option<String>,
+        options: SessionOptions,
+    ) -> Result<Self> {
+        let SessionOptions { observer, events } = options;
         let run_id = match run_name {
             Some(name) => RunId(name),
             None => RunId::new(),
@@ -45,8 +83,14 @@ impl RecordingSession {
 
         info!("Starting recording session: {}", run_id.0);
 
-        let storage = RunStorage::new(run_id.clone(), &config.output_dir, config.batch_size)?;
+        let mut storage = RunStorage::new_with_spool(
+            run_id.clone(),
+            &config.output_dir,
+            config.batch_size,
+            config.spool_sync,
+        )?;
 
+        storage.set_events(events.clone());
         let storage = Arc::new(Mutex::new(storage));
         let (shutdown_tx, shutdown_rx) = mpsc::channel(1);
 
@@ -54,19 +98,28 @@ impl RecordingSession {
         let run_id_clone = run_id.clone();
         let storage_clone = storage.clone();
 
-        tokio::spawn(async move {
-            if let Err(e) =
-                recording_loop(run_id_clone, config_clone, storage_clone, shutdown_rx).await
-            {
+        let loop_handle = tokio::spawn(async move {
+            let result = recording_loop(
+                run_id_clone,
+                config_clone,
+                storage_clone,
+                shutdown_rx,
+                observer,
+            )
+            .await;
+            if let Err(ref e) = result {
                 error!("Recording loop failed: {}", e);
             }
+            result
         });
 
         Ok(Self {
             run_id,
             config,
+            events,
             storage,
             shutdown_tx,
+            loop_handle,
         })
     }
 
@@ -74,17 +127,69 @@ impl RecordingSession {
         &self.run_id
     }
 
+    /// The bus this session publishes committed events to.
+    pub fn events(&self) -> &EventBus {
+        &self.events
+    }
+
     /// Stop the recording session gracefully and return a summary.
     pub async fn stop(self) -> Result<RecordingSummary> {
+        self.stop_with_timeout(DEFAULT_STOP_TIMEOUT).await
+    }
+
+    /// Stop the recording session, waiting at most `timeout` for the
+    /// recording loop to write RUN_END and flush.
+    ///
+    /// If the loop fails, panics or exceeds the timeout, it is aborted and
+    /// a RUN_END marked `unclean_shutdown` is written here instead, so every
+    /// sealed run ends with RUN_END.
+    pub async fn stop_with_timeout(self, timeout: Duration) -> Result<RecordingSummary> {
         info!("Stopping recording session: {}", self.run_id.0);
 
+        // The loop may already have exited (e.g. gateway disconnect), in
+        // which case the receiver is gone and the send fails harmlessly.
         let _ = self.shutdown_tx.send(()).await;
 
-        // Give the recording loop time to write RUN_END and flush
-        tokio::time::sleep(Duration::from_millis(500)).await;
+        let mut loop_handle = self.loop_handle;
+        let unclean_reason = match tokio::time::timeout(timeout, &mut loop_handle).await {
+            Ok(Ok(Ok(()))) => None,
+            Ok(Ok(Err(e))) => Some(format!("recording loop failed: {}", e)),
+            Ok(Err(e)) => Some(format!("recording loop panicked: {}", e)),
+            Err(_) => {
+                warn!("Recording loop did not stop within {:?}, aborting", timeout);
+                loop_handle.abort();
+                let _ = loop_handle.await;
+                Some(format!("recording loop timed out after {:?}", timeout))
+            }
+        };
 
         // Finalize storage
         let mut storage = self.storage.lock().await;
+
+        let last_event = storage.last_event()?;
+        let ended_cleanly = last_event.as_ref().map(|e| e.kind) == Some(EventKind::RunEnd);
+        if !ended_cleanly {
+            let reason = unclean_reason
+                .clone()
+                .unwrap_or_else(|| "recording loop exited without RUN_END".to_string());
+            warn!("Unclean shutdown for run {}: {}", self.run_id.0, reason);
+            let next_id = last_event.map(|e| e.event_id.0 + 1).unwrap_or(1);
+            let end_event = Event::new(
+                self.run_id.clone(),
+                EventId(next_id),
+                EventKind::RunEnd,
+                serde_json::json!({
+                    "unclean_shutdown": true,
+                    "reason": reason,
+                    "total_events": next_id,
+                }),
+                None,
+            );
+            storage.write_event(end_event)?;
+        }
+
+        // Flush before reading counts so RunMeta reflects every event
+        storage.flush()?;
         let root_hash = storage.root_hash().unwrap_or_default();
 
         let started_at = storage
@@ -102,6 +207,12 @@ impl RecordingSession {
             root_hash,
             gateway_url: self.config.gateway_url.clone(),
             version: env!("CARGO_PKG_VERSION").to_string(),
+            status: if ended_cleanly {
+                RunStatus::Complete
+            } else {
+                RunStatus::Incomplete
+            },
+            ledger_range: None,
         };
 
         storage.finalize(&meta)?;
@@ -116,26 +227,47 @@ impl RecordingSession {
             event_count: meta.event_count,
             size_bytes,
             valid,
+            clean_shutdown: ended_cleanly && unclean_reason.is_none(),
             out_dir: self.config.output_dir.clone(),
         })
     }
 }
 
+/// Aborts a task when dropped.
+struct AbortOnDrop(tokio::task::AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
 /// Main recording loop
 async fn recording_loop(
     run_id: RunId,
     config: Config,
     storage: Arc<Mutex<RunStorage>>,
     mut shutdown_rx: mpsc::Receiver<()>,
+    observer: Arc<dyn Observer>,
 ) -> Result<()> {
     let auth_token = config.auth_token.as_deref()
         .ok_or_else(|| anyhow::anyhow!("No auth token provided. Use --token or set gateway.auth.token in ~/.openclaw/openclaw.json"))?;
+    let redactor = config
+        .redact_secrets
+        .then(|| Redactor::new(&config.redaction)?.with_vault(&config.output_dir))
+        .transpose()?
+        .map(Arc::new);
+    if let Some(redactor) = &redactor {
+        storage.lock().await.set_redactor(redactor.clone());
+    }
 
     // Connect to gateway
     let mut client = GatewayClient::new(&config.gateway_url, auth_token)?;
+    observer.on_progress(&Progress::Connecting);
     let conn_id = client.connect().await?;
 
     info!("Recording loop started, connId: {}", conn_id);
+    observer.on_progress(&Progress::Connected { conn_id: &conn_id });
 
     // Write RUN_START event
     let start_event = Event::new(
@@ -154,37 +286,43 @@ async fn recording_loop(
         storage.write_event(start_event)?;
     }
 
-    // Spawn the gateway event loop on a separate task
+    let mut event_counter: u64 = 2;
+
+    // Record the ingest filter policy in the chain so omissions are auditable
+    let mut filter = IngestFilter::new(config.filter.clone());
+    if !config.filter.is_empty() {
+        let mut storage = storage.lock().await;
+        storage.write_event(config.filter.to_event(&run_id, EventId(event_counter)))?;
+        event_counter += 1;
+    }
+
+    // Spawn the gateway event loop on a separate task, keeping its handle
+    // so it can be torn down before RUN_END is written
     let (event_tx, mut event_rx) = mpsc::channel::<GatewayEvent>(1000);
-    tokio::spawn(async move {
+    let reader = tokio::spawn(async move {
         if let Err(e) = client.run(event_tx).await {
             error!("Gateway event loop ended: {}", e);
         }
     });
+    // If this loop is aborted (stop timeout), the reader goes with it
+    let _reader_guard = AbortOnDrop(reader.abort_handle());
 
-    let mut event_counter: u64 = 2;
     let mut flush_interval = interval(Duration::from_millis(config.flush_interval_ms));
-    let redact = config.redact_secrets;
-
-    // Progress spinner on stderr
-    let pb = ProgressBar::new_spinner();
-    pb.set_style(
-        ProgressStyle::default_spinner()
-            .template("{spinner:.green} [{elapsed_precise}] {msg}")
-            .unwrap(),
-    );
-    pb.enable_steady_tick(Duration::from_millis(100));
 
-    let event_count_shared = Arc::new(AtomicU64::new(1));
-    let last_kind = Arc::new(Mutex::new("RUN_START".to_string()));
+    // Traces captured, counting RUN_START
+    let mut captured: u64 = 1;
 
-    loop {
+    let end_reason = loop {
         tokio::select! {
             msg = event_rx.recv() => {
                 match msg {
                     Some(gw_event) => {
                         debug!("Gateway event: {} (seq={:?})", gw_event.event, gw_event.seq);
 
+                        if filter.admit(&gw_event, chrono::Utc::now()) == Admission::Omit {
+                            continue;
+                        }
+
                         let kind_name = match gw_event.event.as_str() {
                             "agent" => "AGENT_EVENT",
                             "chat" => "OUTPUT_CHUNK",
@@ -198,7 +336,7 @@ async fn recording_loop(
                             &run_id,
                             EventId(event_counter),
                             gw_event,
-                            redact,
+                            redactor.as_deref(),
                         );
 
                         {
@@ -209,23 +347,30 @@ async fn recording_loop(
                         }
 
                         event_counter += 1;
-                        let count = event_count_shared.fetch_add(1, Ordering::Relaxed) + 1;
-                        *last_kind.lock().await = kind_name.to_string();
-
-                        pb.set_message(format!(
-                            "{} events captured | Last: {}",
-                            count, kind_name
-                        ));
+                        captured += 1;
+                        observer.on_progress(&Progress::Event {
+                            total: captured,
+                            kind: kind_name,
+                        });
                     }
                     None => {
                         warn!("Gateway connection lost");
-                        break;
+                        break "gateway_disconnected";
                     }
                 }
             }
 
             _ = flush_interval.tick() => {
                 let mut storage = storage.lock().await;
+                for summary in filter.take_summaries(chrono::Utc::now()) {
+                    if let Err(e) = storage.write_event(summary.to_event(&run_id, EventId(event_counter))) {
+                        error!("Failed to write filter summary: {}", e);
+                    }
+                    event_counter += 1;
+                }
+                if let Err(e) = storage.sync_spool() {
+                    error!("Failed to sync spool: {}", e);
+                }
                 if let Err(e) = storage.flush() {
                     error!("Failed to flush: {}", e);
                 }
@@ -233,22 +378,38 @@ async fn recording_loop(
 
             _ = shutdown_rx.recv() => {
                 info!("Received shutdown signal");
-                break;
+                break "shutdown";
             }
         }
-    }
+    };
 
-    pb.finish_and_clear();
+    // Stop the gateway reader so nothing races the final writes
+    reader.abort();
+    let _ = reader.await;
+
+    // Close any open aggregation windows before sealing the run
+    {
+        let mut storage = storage.lock().await;
+        for summary in filter.drain() {
+            storage.write_event(summary.to_event(&run_id, EventId(event_counter)))?;
+            event_counter += 1;
+        }
+    }
 
     // Write RUN_END event
+    let mut end_payload = serde_json::json!({
+        "conn_id": conn_id,
+        "total_events": event_counter,
+        "reason": end_reason,
+    });
+    if !filter.omitted_counts().is_empty() {
+        end_payload["filter_omitted"] = serde_json::json!(filter.omitted_counts());
+    }
     let end_event = Event::new(
         run_id.clone(),
         EventId(event_counter),
         EventKind::RunEnd,
-        serde_json::json!({
-            "conn_id": conn_id,
-            "total_events": event_counter,
-        }),
+        end_payload,
         None,
     );
 
@@ -262,12 +423,14 @@ async fn recording_loop(
     Ok(())
 }
 
-/// Map a gateway event to a clawprint Event.
+/// Map a gateway event to a clawprint Event, redacting the payload with
+/// `redactor` if given. What was redacted is recorded in the payload's
+/// `redactions` manifest, inside the hashed event.
 pub fn gateway_event_to_event(
     run_id: &RunId,
     event_id: EventId,
     gw: GatewayEvent,
-    redact: bool,
+    redactor: Option<&Redactor>,
 ) -> Event {
     let kind = match gw.event.as_str() {
         "agent" => EventKind::AgentEvent,
@@ -283,8 +446,22 @@ pub fn gateway_event_to_event(
         "data": gw.payload,
     });
 
-    if redact {
-        redact_json(&mut payload);
+    if let Some(redactor) = redactor {
+        let (detections, manifest) = redactor.redact_json_manifest(&mut payload);
+        if let Some(manifest) = manifest {
+            payload["redactions"] =
+                serde_json::to_value(manifest).expect("manifest must be JSON-serializable");
+        }
+        for d in detections {
+            if d.redacted {
+                debug!("Redacted {} at {} (seq={:?})", d.rule, d.path, gw.seq);
+            } else {
+                warn!(
+                    "Possible secret ({}) at {} (seq={:?})",
+                    d.rule, d.path, gw.seq
+                );
+            }
+        }
     }
 
     let mut event = Event::new(
@@ -301,3 +478,74 @@ pub fn gateway_event_to_event(
 
     event
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A loop that fails before writing anything still yields a sealed run
+    /// ending in an unclean RUN_END.
+    #[tokio::test]
+    async fn test_stop_writes_unclean_run_end_when_loop_fails() {
+        let temp = TempDir::new().unwrap();
+        let config = Config {
+            output_dir: temp.path().to_path_buf(),
+            auth_token: None, // recording loop fails immediately
+            ..Config::default()
+        };
+
+        let session = RecordingSession::start(config, Some("unclean".to_string()))
+            .await
+            .unwrap();
+        let summary = session
+            .stop_with_timeout(Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert!(!summary.clean_shutdown);
+        assert!(summary.valid);
+        assert_eq!(summary.event_count, 1);
+
+        let storage = RunStorage::open(RunId("unclean".to_string()), temp.path()).unwrap();
+        let last = storage.last_event().unwrap().unwrap();
+        assert_eq!(last.kind, EventKind::RunEnd);
+        assert_eq!(last.payload["unclean_shutdown"], true);
+
+        let runs = crate::storage::list_runs(temp.path()).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].1.event_count, 1);
+        assert_eq!(runs[0].1.root_hash, last.hash_self);
+        assert_eq!(runs[0].1.status, RunStatus::Incomplete);
+    }
+
+    #[tokio::test]
+    async fn test_abort_on_drop() {
+        let task = tokio::spawn(std::future::pending::<()>());
+        drop(AbortOnDrop(task.abort_handle()));
+        assert!(task.await.unwrap_err().is_cancelled());
+    }
+
+    #[test]
+    fn test_redaction_manifest_is_hashed() {
+        let gw = GatewayEvent {
+            event: "agent".to_string(),
+            payload: serde_json::json!({"args": {"password": "hunter2"}}),
+            seq: Some(7),
+        };
+        let redactor = Redactor::builtin();
+        let mut event = gateway_event_to_event(&RunId("r".into()), EventId(1), gw, Some(&redactor));
+        assert_eq!(event.payload["data"]["args"]["password"], "[REDACTED]");
+        let manifest = &event.payload["redactions"];
+        assert_eq!(manifest["entries"][0]["path"], "$.data.args.password");
+        assert_eq!(manifest["entries"][0]["rules"][0], "sensitive-fields");
+
+        // Storage hashes the event once it is linked into the chain
+        event.hash_self = event.compute_hash();
+        event.payload["redactions"]["entries"] = serde_json::json!([]);
+        assert!(
+            !event.verify(),
+            "the manifest is part of the hashed payload"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+//! Live configuration reload for the daemon
+//!
+//! Restarting the daemon to change a filter or a token drops the gateway
+//! connection and leaves a gap in the ledger. Instead, a [`ReloadHandle`]
+//! re-reads the configuration (on SIGHUP or `POST /admin/reload`) and hands
+//! it to the running daemon, which applies what is safe to change live and
+//! reconnects only when the gateway settings changed.
+//!
+//! Every accepted reload is written to the ledger as a `CONFIG_CHANGE` event
+//! carrying the hash of the new configuration and the fields that changed.
+//! Secrets never enter the ledger; the token only contributes its hash.
+
+use anyhow::{Result, anyhow};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use tokio::sync::{mpsc, oneshot};
+use tracing::{info, warn};
+
+use crate::{Config, Event, EventId, EventKind, RunId};
+
+/// Builds a fresh `Config` (config file merged with the original CLI flags).
+pub type ConfigLoader = Arc<dyn Fn() -> Result<Config> + Send + Sync>;
+
+/// A reload delivered to the daemon.
+pub struct ReloadRequest {
+    pub config: Config,
+    /// What triggered the reload ("sighup", "admin")
+    pub source: &'static str,
+    /// Receives the outcome, if the caller is waiting for it
+    pub reply: Option<oneshot::Sender<Result<ConfigChange>>>,
+}
+
+/// Outcome of a reload, also the payload of the `CONFIG_CHANGE` event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ConfigChange {
+    pub source: String,
+    pub config_hash: String,
+    pub previous_hash: String,
+    /// Fields applied without interrupting the connection
+    pub applied: Vec<&'static str>,
+    /// Gateway fields; applying them reconnects to the gateway
+    pub reconnect: Vec<&'static str>,
+    /// Fields that only take effect after a restart (kept at their old value)
+    pub restart_required: Vec<&'static str>,
+}
+
+impl ConfigChange {
+    /// Compare the running config with a reloaded one.
+    pub fn between(old: &Config, new: &Config, source: &str) -> Self {
+        let mut applied = Vec::new();
+        let mut reconnect = Vec::new();
+        let mut restart_required = Vec::new();
+
+        if old.gateway_url != new.gateway_url {
+            reconnect.push("gateway_url");
+        }
+        if old.auth_token != new.auth_token {
+            reconnect.push("auth_token");
+        }
+        if old.redact_secrets != new.redact_secrets {
+            applied.push("redact_secrets");
+        }
+        if old.redaction != new.redaction {
+            applied.push("redaction");
+        }
+        if old.filter != new.filter {
+            applied.push("filter");
+        }
+        if old.flush_interval_ms != new.flush_interval_ms {
+            applied.push("flush_interval_ms");
+        }
+        if old.assign != new.assign {
+            applied.push("assign");
+        }
+        if old.sessionize != new.sessionize {
+            applied.push("sessionize");
+        }
+        if old.output_dir != new.output_dir {
+            restart_required.push("output_dir");
+        }
+        if old.batch_size != new.batch_size {
+            restart_required.push("batch_size");
+        }
+        if old.spool_sync != new.spool_sync {
+            restart_required.push("spool_sync");
+        }
+        if old.sinks != new.sinks {
+            restart_required.push("sinks");
+        }
+
+        Self {
+            source: source.to_string(),
+            config_hash: config_hash(new),
+            previous_hash: config_hash(old),
+            applied,
+            reconnect,
+            restart_required,
+        }
+    }
+
+    /// True if nothing the daemon would act on changed.
+    pub fn is_empty(&self) -> bool {
+        self.applied.is_empty() && self.reconnect.is_empty() && self.restart_required.is_empty()
+    }
+
+    /// Build the CONFIG_CHANGE event recording this reload in the chain.
+    pub fn to_event(&self, run_id: &RunId, event_id: EventId) -> Event {
+        let payload = serde_json::to_value(self).expect("config change must be JSON-serializable");
+        Event::new(
+            run_id.clone(),
+            event_id,
+            EventKind::ConfigChange,
+            payload,
+            None,
+        )
+    }
+}
+
+/// SHA-256 over the recorder-relevant config. The auth token is included
+/// only as its own hash so the result can be published.
+pub fn config_hash(config: &Config) -> String {
+    let token_hash = config
+        .auth_token
+        .as_ref()
+        .map(|t| hex::encode(Sha256::digest(t.as_bytes())));
+    let spool_sync_ms = match config.spool_sync {
+        crate::spool::SpoolSync::EveryEvent => 0,
+        crate::spool::SpoolSync::Interval(d) => d.as_millis() as u64,
+    };
+    let canonical = serde_json::json!({
+        "output_dir": config.output_dir,
+        "redact_secrets": config.redact_secrets,
+        "redaction": config.redaction,
+        "gateway_url": config.gateway_url,
+        "auth_token_sha256": token_hash,
+        "batch_size": config.batch_size,
+        "flush_interval_ms": config.flush_interval_ms,
+        "filter": config.filter,
+        "spool_sync_ms": spool_sync_ms,
+        "assign": config.assign,
+        "sessionize": config.sessionize,
+        "sinks": config.sinks.iter().map(|s| s.masked()).collect::<Vec<_>>(),
+    });
+    hex::encode(Sha256::digest(canonical.to_string().as_bytes()))
+}
+
+/// Cloneable handle that loads the configuration and sends it to the daemon.
+#[derive(Clone)]
+pub struct ReloadHandle {
+    loader: ConfigLoader,
+    tx: mpsc::Sender<ReloadRequest>,
+}
+
+impl ReloadHandle {
+    /// Create a handle and the receiver to pass to the daemon.
+    pub fn new(loader: ConfigLoader) -> (Self, mpsc::Receiver<ReloadRequest>) {
+        let (tx, rx) = mpsc::channel(4);
+        (Self { loader, tx }, rx)
+    }
+
+    /// Reload and wait for the daemon to apply the new configuration.
+    pub async fn reload(&self, source: &'static str) -> Result<ConfigChange> {
+        let config = (self.loader)()?;
+        let (reply, outcome) = oneshot::channel();
+        self.tx
+            .send(ReloadRequest {
+                config,
+                source,
+                reply: Some(reply),
+            })
+            .await
+            .map_err(|_| anyhow!("Daemon is not running"))?;
+        outcome
+            .await
+            .map_err(|_| anyhow!("Daemon stopped before applying the reload"))?
+    }
+
+    /// Reload on every SIGHUP until `ct` is cancelled. No-op off Unix.
+    pub fn spawn_sighup(&self, ct: tokio_util::sync::CancellationToken) {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{SignalKind, signal};
+
+            let handle = self.clone();
+            tokio::spawn(async move {
+                let mut hup = match signal(SignalKind::hangup()) {
+                    Ok(s) => s,
+                    Err(e) => {
+                        warn!("Cannot listen for SIGHUP: {}", e);
+                        return;
+                    }
+                };
+                loop {
+                    tokio::select! {
+                        _ = hup.recv() => {
+                            info!("SIGHUP received, reloading configuration");
+                            match handle.reload("sighup").await {
+                                Ok(change) => info!(
+                                    "Configuration reloaded ({})",
+                                    &change.config_hash[..12]
+                                ),
+                                Err(e) => warn!("Reload failed, keeping current configuration: {}", e),
+                            }
+                        }
+                        _ = ct.cancelled() => break,
+                    }
+                }
+            });
+        }
+        #[cfg(not(unix))]
+        let _ = ct;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::{FilterPolicy, FilterRule};
+
+    #[test]
+    fn test_change_classification() {
+        let old = Config {
+            auth_token: Some("t1".into()),
+            ..Config::default()
+        };
+        let mut new = old.clone();
+        assert!(ConfigChange::between(&old, &new, "test").is_empty());
+        assert_eq!(config_hash(&old), config_hash(&new));
+
+        new.filter = FilterPolicy::new(vec!["tick=drop".parse::<FilterRule>().unwrap()]);
+        new.auth_token = Some("t2".into());
+        new.batch_size = 500;
+        let change = ConfigChange::between(&old, &new, "test");
+        assert_eq!(change.applied, vec!["filter"]);
+        assert_eq!(change.reconnect, vec!["auth_token"]);
+        assert_eq!(change.restart_required, vec!["batch_size"]);
+        assert_ne!(change.config_hash, change.previous_hash);
+
+        let event = change.to_event(&RunId("daemon".into()), EventId(0));
+        assert_eq!(event.kind, EventKind::ConfigChange);
+        let json = serde_json::to_string(&event.payload).unwrap();
+        assert!(!json.contains("t2"), "token must not be recorded");
+    }
+}
@@ -9,6 +9,7 @@ use std::collections::HashMap;
 use std::path::Path;
 use tracing::{info, warn};
 
+use crate::assign::{AssignPolicy, RunAssigner};
 use crate::storage::{RunStorage, verify_event_chain};
 use crate::{Event, EventKind, RunId};
 
@@ -63,9 +64,11 @@ pub fn replay_run(run_id: &RunId, base_path: &Path, offline: bool) -> Result<Rep
         agent_runs: Vec::new(),
     };
 
+    // Attribute events (including chat output) to agent runs
+    let mut assigner = RunAssigner::new(AssignPolicy::correlated());
+
     for event in &events {
-        // Extract agent runId from gateway event payload
-        let agent_run_id = extract_agent_run_id(event);
+        let agent_run_id = assigner.assign(event);
 
         match event.kind {
             EventKind::AgentEvent => {
@@ -166,15 +169,6 @@ pub fn replay_run(run_id: &RunId, base_path: &Path, offline: bool) -> Result<Rep
     Ok(result)
 }
 
-/// Extract the agent runId from a gateway event payload
-fn extract_agent_run_id(event: &Event) -> Option<String> {
-    event
-        .payload
-        .pointer("/data/runId")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string())
-}
-
 /// Extract chat content from an OUTPUT_CHUNK event.
 /// Prefers "state: final" chunks which contain the complete message text.
 fn extract_chat_content(event: &Event) -> Option<String> {
@@ -0,0 +1,676 @@
+//! Retroactive re-redaction
+//!
+//! A new redaction rule only protects what is recorded from then on. A
+//! redaction pass applies rules to what is already in a ledger, either the
+//! daemon ledger or a run's case file. A dry run only reports what would be
+//! redacted. Applying it rewrites the ledger as a new generation:
+//!
+//! 1. The current database is archived unchanged as
+//!    `generations/ledger.<n>.sqlite`, made read-only, and its SHA-256 is
+//!    recorded.
+//! 2. Payloads are redacted and the hash chain is rebuilt. Each event whose
+//!    hash changed is listed in the `generation_map` table, mapping its old
+//!    hash to the new one, with the paths the pass redacted.
+//! 3. A `REDACTION_PASS` event is appended to the new chain. It commits to
+//!    the archived generation (root hash and file digest), the rules, and a
+//!    digest of the map. Like every event it is hash-chained, not signed.
+//! 4. The live file is vacuumed with `secure_delete` on and its WAL
+//!    truncated, so the removed values are left only in the archive.
+//!
+//! The old generation still verifies against its own chain, the new one
+//! against its chain, and the map connects the two. A pass refuses to run
+//! on a chain that doesn't verify, so it can't launder tampering.
+
+use anyhow::{Result, anyhow, bail};
+use chrono::Utc;
+use rusqlite::{Connection, params};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::info;
+
+use crate::ledger::{self, Ledger};
+use crate::lock::LedgerLocked;
+use crate::redact::{self, Detection, RedactionRules, Redactor};
+use crate::storage::RunStorage;
+use crate::{Event, EventId, EventKind, RunId, RunMeta};
+
+/// Directory, next to `ledger.sqlite`, holding archived generations
+pub const GENERATIONS_DIR: &str = "generations";
+
+/// The ledger a pass runs over
+#[derive(Debug, Clone)]
+pub enum Target {
+    /// The daemon ledger in this directory
+    Ledger(PathBuf),
+    /// A run's case file under `<out>/runs/<id>`
+    Run { out: PathBuf, run_id: RunId },
+}
+
+impl Target {
+    pub(crate) fn dir(&self) -> PathBuf {
+        match self {
+            Self::Ledger(dir) => dir.clone(),
+            Self::Run { out, run_id } => out.join("runs").join(&run_id.0),
+        }
+    }
+}
+
+/// An event the pass redacted (or would redact).
+#[derive(Debug, Clone, Serialize)]
+pub struct EventMatch {
+    pub event_id: u64,
+    pub kind: EventKind,
+    /// Only what the pass changed; values already redacted are not listed
+    pub detections: Vec<Detection>,
+}
+
+/// The generation an applied pass created.
+#[derive(Debug, Clone, Serialize)]
+pub struct Generation {
+    pub number: u64,
+    /// The previous generation, archived unchanged
+    pub archive: PathBuf,
+    pub previous_root_hash: Option<String>,
+    /// Hash of the `REDACTION_PASS` event that ends the new chain
+    pub root_hash: String,
+    /// Events whose payload was redacted
+    pub rewritten: u64,
+    /// Events after the first rewritten one whose hash changed only
+    /// because the chain was rebuilt
+    pub relinked: u64,
+}
+
+/// What a pass found, and did when applied.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PassReport {
+    /// Events examined
+    pub scanned: u64,
+    pub matches: Vec<EventMatch>,
+    /// New redactions per rule
+    pub by_rule: BTreeMap<String, u64>,
+    /// Set when the pass was applied and changed something
+    pub generation: Option<Generation>,
+}
+
+/// How a pass is run.
+pub struct PassOptions<'a> {
+    /// Compiled from `rules`
+    pub redactor: &'a Redactor,
+    /// Committed to in the `REDACTION_PASS` event
+    pub rules: &'a RedactionRules,
+    /// Where the new rules came from, if a file
+    pub rules_file: Option<&'a Path>,
+    /// Who ran the pass, e.g. `cli alice`
+    pub actor: &'a str,
+    /// Report only; write nothing
+    pub dry_run: bool,
+}
+
+/// Redact an event's payload with `redactor`, leaving its manifest alone.
+/// Returns the detections for values that actually changed.
+pub fn redact_event(redactor: &Redactor, event: &mut Event) -> Vec<Detection> {
+    if event.kind == EventKind::RedactionPass {
+        return Vec::new();
+    }
+    let manifest = event
+        .payload
+        .as_object_mut()
+        .and_then(|map| map.remove("redactions"));
+    let before = event.payload.clone();
+    let detections = redactor.redact_json_report(&mut event.payload);
+
+    let mut changed = HashSet::new();
+    changed_paths(&before, &event.payload, &mut Vec::new(), &mut changed);
+    if let (Some(manifest), Some(map)) = (manifest, event.payload.as_object_mut()) {
+        map.insert("redactions".to_string(), manifest);
+    }
+    detections
+        .into_iter()
+        .filter(|d| d.redacted && changed.contains(&d.path))
+        .collect()
+}
+
+/// Paths (in [`Detection`] form) where `new` differs from `old`
+fn changed_paths(old: &Value, new: &Value, path: &mut Vec<String>, out: &mut HashSet<String>) {
+    match (old, new) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in a {
+                path.push(key.clone());
+                match b.get(key) {
+                    Some(other) => changed_paths(value, other, path, out),
+                    None => {
+                        out.insert(redact::json_path(path));
+                    }
+                }
+                path.pop();
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (i, (x, y)) in a.iter().zip(b).enumerate() {
+                path.push(i.to_string());
+                changed_paths(x, y, path, out);
+                path.pop();
+            }
+        }
+        (a, b) if a != b => {
+            out.insert(redact::json_path(path));
+        }
+        _ => {}
+    }
+}
+
+/// A rewritten event, held until the pass is applied
+struct Rewrite {
+    event_id: u64,
+    old_hash: String,
+    new_hash: String,
+    hash_prev: Option<String>,
+    /// `None` if only relinked
+    payload: Option<String>,
+    redacted: Vec<String>,
+}
+
+/// Run a redaction pass over `target`.
+pub fn redaction_pass(target: &Target, options: &PassOptions) -> Result<PassReport> {
+    let dir = target.dir();
+    let db_path = dir.join("ledger.sqlite");
+    if !db_path.exists() {
+        bail!("Ledger not found at {:?}", dir);
+    }
+
+    // Hold the writer lock (replaying any crash spool) for the whole pass
+    let _ledger =
+        match target {
+            Target::Ledger(dir) => Some(Ledger::open(dir, 1).map_err(|e| {
+                match e.downcast_ref::<LedgerLocked>() {
+                    Some(_) => anyhow!(
+                        "The ledger is in use by a running daemon; stop it before a redaction pass"
+                    ),
+                    None => e,
+                }
+            })?),
+            Target::Run { run_id, .. } => {
+                if !dir.join("meta.json").exists() {
+                    bail!(
+                        "Run {} was never finalized; run `clawprint recover` first",
+                        run_id.0
+                    );
+                }
+                None
+            }
+        };
+
+    let mut db = Connection::open(&db_path)?;
+    let mut report = PassReport::default();
+    let mut rewrites = Vec::new();
+    let mut old_root: Option<String> = None;
+    let mut new_root: Option<String> = None;
+    let mut last_id = 0;
+    {
+        let (sql, run_id) = match target {
+            Target::Ledger(_) => (
+                "SELECT event_id, run_id, ts, kind, agent_run, span_id, parent_span_id, actor,
+                        payload, artifact_refs, hash_prev, hash_self
+                 FROM events ORDER BY event_id",
+                None,
+            ),
+            Target::Run { run_id, .. } => (
+                "SELECT event_id, ts, kind, span_id, parent_span_id, actor,
+                        payload, artifact_refs, hash_prev, hash_self
+                 FROM events ORDER BY event_id",
+                Some(run_id),
+            ),
+        };
+        let mut stmt = db.prepare(sql)?;
+        let mut rows = stmt.query([])?;
+        while let Some(row) = rows.next()? {
+            let mut event = match run_id {
+                Some(run_id) => RunStorage::row_to_event(row, run_id)?,
+                None => ledger::row_to_event(row)?,
+            };
+            let event_id = event.event_id.0;
+            if !event.verify() || (old_root.is_some() && event.hash_prev != old_root) {
+                bail!(
+                    "Event {} fails chain verification; refusing to rewrite a broken chain",
+                    event_id
+                );
+            }
+            report.scanned += 1;
+            last_id = event_id;
+            let old_hash = std::mem::take(&mut event.hash_self);
+            old_root = Some(old_hash.clone());
+
+            let detections = redact_event(options.redactor, &mut event);
+            if new_root.is_some() {
+                event.hash_prev = new_root.clone();
+            }
+            event.hash_self = event.compute_hash();
+            new_root = Some(event.hash_self.clone());
+
+            if !detections.is_empty() {
+                for d in &detections {
+                    *report.by_rule.entry(d.rule.clone()).or_default() += 1;
+                }
+                report.matches.push(EventMatch {
+                    event_id,
+                    kind: event.kind,
+                    detections: detections.clone(),
+                });
+            }
+            if event.hash_self != old_hash {
+                rewrites.push(Rewrite {
+                    event_id,
+                    old_hash,
+                    new_hash: event.hash_self.clone(),
+                    hash_prev: event.hash_prev.clone(),
+                    payload: (!detections.is_empty())
+                        .then(|| serde_json::to_string(&event.payload))
+                        .transpose()?,
+                    redacted: detections
+                        .iter()
+                        .map(|d| format!("{} {}", d.rule, d.path))
+                        .collect(),
+                });
+            }
+        }
+    }
+
+    if options.dry_run || report.matches.is_empty() {
+        return Ok(report);
+    }
+
+    // 1. Archive the current generation as it is
+    db.execute_batch(
+        "CREATE TABLE IF NOT EXISTS generations (
+            generation          INTEGER PRIMARY KEY,
+            archive             TEXT NOT NULL,
+            archive_sha256      TEXT NOT NULL,
+            previous_root_hash  TEXT,
+            root_hash           TEXT NOT NULL,
+            pass_event_id       INTEGER NOT NULL,
+            created_at          TEXT NOT NULL
+        );
+        CREATE TABLE IF NOT EXISTS generation_map (
+            generation  INTEGER NOT NULL,
+            event_id    INTEGER NOT NULL,
+            old_hash    TEXT NOT NULL,
+            new_hash    TEXT NOT NULL,
+            redacted    TEXT,
+            PRIMARY KEY (generation, event_id)
+        );",
+    )?;
+    let previous: u64 = db.query_row(
+        "SELECT COALESCE(MAX(generation), 0) FROM generations",
+        [],
+        |row| row.get(0),
+    )?;
+    let number = previous + 1;
+    let archive_rel = PathBuf::from(GENERATIONS_DIR).join(format!("ledger.{}.sqlite", previous));
+    let archive = dir.join(&archive_rel);
+    if archive.exists() {
+        bail!("Generation archive {:?} already exists", archive);
+    }
+    fs::create_dir_all(dir.join(GENERATIONS_DIR))?;
+    db.execute(
+        "VACUUM INTO ?1",
+        params![archive.to_string_lossy().into_owned()],
+    )?;
+    let mut permissions = fs::metadata(&archive)?.permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&archive, permissions)?;
+    let archive_sha256 = {
+        let mut hasher = Sha256::new();
+        std::io::copy(&mut fs::File::open(&archive)?, &mut hasher)?;
+        hex::encode(hasher.finalize())
+    };
+
+    // 2. Rewrite payloads and relink the chain. Overwritten payloads hold
+    // the very values the pass removes, so freed pages are zeroed.
+    db.pragma_update(None, "secure_delete", true)?;
+    let tx = db.transaction()?;
+    let mut map_hasher = Sha256::new();
+    let mut rewritten = 0;
+    for r in &rewrites {
+        match &r.payload {
+            Some(payload) => {
+                rewritten += 1;
+                tx.execute(
+                    "UPDATE events SET payload = ?1, hash_prev = ?2, hash_self = ?3 WHERE event_id = ?4",
+                    params![payload, r.hash_prev, r.new_hash, r.event_id as i64],
+                )?;
+            }
+            None => {
+                tx.execute(
+                    "UPDATE events SET hash_prev = ?1, hash_self = ?2 WHERE event_id = ?3",
+                    params![r.hash_prev, r.new_hash, r.event_id as i64],
+                )?;
+            }
+        }
+        let redacted = (!r.redacted.is_empty())
+            .then(|| serde_json::to_string(&r.redacted))
+            .transpose()?;
+        tx.execute(
+            "INSERT INTO generation_map (generation, event_id, old_hash, new_hash, redacted)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                number as i64,
+                r.event_id as i64,
+                r.old_hash,
+                r.new_hash,
+                redacted
+            ],
+        )?;
+        map_hasher.update(format!("{} {} {}\n", r.event_id, r.old_hash, r.new_hash));
+    }
+
+    let relinked = rewrites.len() as u64 - rewritten;
+
+    // 3. Record the pass on the new chain
+    let rules_sha256 = hex::encode(Sha256::digest(serde_json::to_vec(options.rules)?));
+    let payload = serde_json::json!({
+        "generation": number,
+        "previous": {
+            "generation": previous,
+            "archive": archive_rel,
+            "sha256": archive_sha256,
+            "root_hash": old_root,
+            "events": report.scanned,
+        },
+        "rules_sha256": rules_sha256,
+        "rules_file": options.rules_file,
+        "rewritten": rewritten,
+        "relinked": relinked,
+        "detections": report.by_rule,
+        "map_sha256": hex::encode(map_hasher.finalize()),
+        "actor": options.actor,
+        "at": Utc::now(),
+    });
+    let run_id = match target {
+        Target::Ledger(_) => RunId("daemon".to_string()),
+        Target::Run { run_id, .. } => run_id.clone(),
+    };
+    let mut pass = Event::new(
+        run_id,
+        EventId(last_id + 1),
+        EventKind::RedactionPass,
+        payload,
+        new_root,
+    );
+    pass.actor = Some(options.actor.to_string());
+    pass.hash_self = pass.compute_hash();
+    let kind = "REDACTION_PASS";
+    match target {
+        Target::Ledger(_) => tx.execute(
+            "INSERT INTO events
+             (event_id, run_id, ts, kind, agent_run, span_id, parent_span_id, actor,
+              payload, artifact_refs, hash_prev, hash_self)
+             VALUES (?1, ?2, ?3, ?4, NULL, NULL, NULL, ?5, ?6, '[]', ?7, ?8)",
+            params![
+                pass.event_id.0 as i64,
+                pass.run_id.0,
+                pass.ts.to_rfc3339(),
+                kind,
+                pass.actor,
+                serde_json::to_string(&pass.payload)?,
+                pass.hash_prev,
+                pass.hash_self,
+            ],
+        )?,
+        Target::Run { .. } => tx.execute(
+            "INSERT INTO events
+             (event_id, ts, kind, span_id, parent_span_id, actor,
+              payload, artifact_refs, hash_prev, hash_self)
+             VALUES (?1, ?2, ?3, NULL, NULL, ?4, ?5, '[]', ?6, ?7)",
+            params![
+                pass.event_id.0 as i64,
+                pass.ts.to_rfc3339(),
+                kind,
+                pass.actor,
+                serde_json::to_string(&pass.payload)?,
+                pass.hash_prev,
+                pass.hash_self,
+            ],
+        )?,
+    };
+    tx.execute(
+        "INSERT INTO generations
+         (generation, archive, archive_sha256, previous_root_hash, root_hash, pass_event_id, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        params![
+            number as i64,
+            archive_rel.to_string_lossy().into_owned(),
+            archive_sha256,
+            old_root,
+            pass.hash_self,
+            pass.event_id.0 as i64,
+            pass.ts.to_rfc3339(),
+        ],
+    )?;
+    tx.commit()?;
+
+    // Old payloads can survive in WAL frames and in the free list;
+    // rebuild the file and truncate the WAL so only the archive keeps them
+    db.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    db.execute_batch("VACUUM")?;
+    db.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+
+    // A case file's meta.json carries its root hash
+    if let Target::Run { .. } = target {
+        let meta_path = dir.join("meta.json");
+        let mut meta: RunMeta = serde_json::from_str(&fs::read_to_string(&meta_path)?)?;
+        meta.root_hash = pass.hash_self.clone();
+        meta.event_count += 1;
+        fs::write(&meta_path, serde_json::to_string_pretty(&meta)?)?;
+    }
+
+    info!(
+        "Redaction pass: generation {} rewrote {} of {} events",
+        number, rewritten, report.scanned
+    );
+    report.generation = Some(Generation {
+        number,
+        archive,
+        previous_root_hash: old_root,
+        root_hash: pass.hash_self,
+        rewritten,
+        relinked,
+    });
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn new_rules() -> RedactionRules {
+        toml::from_str(
+            r#"
+            [[rules]]
+            name = "acme-token"
+            value = "acme_live_[a-z0-9]{8}"
+            label = "ACME"
+            "#,
+        )
+        .unwrap()
+    }
+
+    /// A daemon ledger whose second event holds a token `new_rules` catches.
+    fn seed_ledger(dir: &Path) {
+        let mut ledger = Ledger::open(dir, 10).unwrap();
+        for (i, data) in [
+            serde_json::json!({ "cmd": "ls" }),
+            serde_json::json!({ "cmd": "deploy --token acme_live_abcd1234", "password": "[REDACTED]" }),
+            serde_json::json!({ "cmd": "echo done" }),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let payload = serde_json::json!({ "gateway_event": "agent", "data": data });
+            ledger
+                .append_event(Event::new(
+                    RunId("daemon".into()),
+                    EventId(i as u64 + 1),
+                    EventKind::AgentEvent,
+                    payload,
+                    None,
+                ))
+                .unwrap();
+        }
+        ledger.flush().unwrap();
+    }
+
+    fn run_pass(target: &Target, dry_run: bool) -> Result<PassReport> {
+        let rules = new_rules();
+        let redactor = Redactor::new(&rules).unwrap();
+        let options = PassOptions {
+            redactor: &redactor,
+            rules: &rules,
+            rules_file: None,
+            actor: "test",
+            dry_run,
+        };
+        redaction_pass(target, &options)
+    }
+
+    #[test]
+    fn test_dry_run_reports_without_writing() {
+        let temp = TempDir::new().unwrap();
+        seed_ledger(temp.path());
+
+        let report = run_pass(&Target::Ledger(temp.path().into()), true).unwrap();
+        assert_eq!(report.scanned, 3);
+        assert_eq!(report.matches.len(), 1);
+        // The already-masked password is not a new finding
+        assert_eq!(report.matches[0].detections.len(), 1);
+        assert_eq!(report.matches[0].detections[0].path, "$.data.cmd");
+        assert!(report.generation.is_none());
+        assert!(!temp.path().join(GENERATIONS_DIR).exists());
+    }
+
+    #[test]
+    fn test_pass_rewrites_and_relinks_chain() {
+        let temp = TempDir::new().unwrap();
+        seed_ledger(temp.path());
+
+        let report = run_pass(&Target::Ledger(temp.path().into()), false).unwrap();
+        let generation = report.generation.unwrap();
+        assert_eq!(generation.number, 1);
+        assert_eq!((generation.rewritten, generation.relinked), (1, 1));
+
+        // The new generation verifies and ends in the pass record
+        let ledger = Ledger::open_readonly(temp.path()).unwrap();
+        assert_eq!(ledger.verify_chain().unwrap(), (true, 4));
+        let event = ledger.get_event(2).unwrap().unwrap();
+        assert_eq!(
+            event.payload["data"]["cmd"],
+            "deploy --token [REDACTED-ACME]"
+        );
+        let pass = ledger.get_event(4).unwrap().unwrap();
+        assert_eq!(pass.kind, EventKind::RedactionPass);
+        assert_eq!(pass.hash_self, generation.root_hash);
+        assert_eq!(
+            pass.payload["previous"]["root_hash"].as_str(),
+            generation.previous_root_hash.as_deref()
+        );
+    }
+
+    #[test]
+    fn test_pass_scrubs_live_file_and_wal() {
+        let temp = TempDir::new().unwrap();
+        seed_ledger(temp.path());
+        run_pass(&Target::Ledger(temp.path().into()), false).unwrap();
+
+        for name in ["ledger.sqlite", "ledger.sqlite-wal"] {
+            let bytes = fs::read(temp.path().join(name)).unwrap_or_default();
+            assert!(
+                !bytes.windows(18).any(|w| w == b"acme_live_abcd1234"),
+                "{name} still holds the secret"
+            );
+        }
+    }
+
+    #[test]
+    fn test_archived_generation_still_verifies() {
+        let temp = TempDir::new().unwrap();
+        seed_ledger(temp.path());
+        let report = run_pass(&Target::Ledger(temp.path().into()), false).unwrap();
+        let generation = report.generation.unwrap();
+
+        let archive_dir = TempDir::new().unwrap();
+        fs::copy(
+            &generation.archive,
+            archive_dir.path().join("ledger.sqlite"),
+        )
+        .unwrap();
+        let old = Ledger::open_readonly(archive_dir.path()).unwrap();
+        assert_eq!(old.verify_chain().unwrap(), (true, 3));
+        assert_eq!(old.root_hash(), generation.previous_root_hash);
+    }
+
+    #[test]
+    fn test_second_pass_is_a_no_op() {
+        let temp = TempDir::new().unwrap();
+        seed_ledger(temp.path());
+        let target = Target::Ledger(temp.path().into());
+        run_pass(&target, false).unwrap();
+
+        let report = run_pass(&target, false).unwrap();
+        assert!(report.matches.is_empty() && report.generation.is_none());
+    }
+
+    #[test]
+    fn test_pass_refuses_broken_chain() {
+        let temp = TempDir::new().unwrap();
+        seed_ledger(temp.path());
+        let db = Connection::open(temp.path().join("ledger.sqlite")).unwrap();
+        db.execute(
+            "UPDATE events SET payload = '{\"tampered\":true}' WHERE event_id = 1",
+            [],
+        )
+        .unwrap();
+        drop(db);
+
+        let err = run_pass(&Target::Ledger(temp.path().into()), false).unwrap_err();
+        assert!(err.to_string().contains("fails chain verification"));
+        assert!(!temp.path().join(GENERATIONS_DIR).exists());
+    }
+
+    #[test]
+    fn test_pass_refuses_locked_ledger() {
+        let temp = TempDir::new().unwrap();
+        seed_ledger(temp.path());
+
+        let _daemon = Ledger::open(temp.path(), 10).unwrap();
+        let err = run_pass(&Target::Ledger(temp.path().into()), false).unwrap_err();
+        assert!(err.to_string().contains("in use by a running daemon"));
+    }
+
+    #[test]
+    fn test_pass_refuses_unfinalized_run() {
+        let temp = TempDir::new().unwrap();
+        let run_id = RunId("rec".into());
+        let mut storage = RunStorage::new(run_id.clone(), temp.path(), 10).unwrap();
+        storage
+            .write_event(Event::new(
+                run_id.clone(),
+                EventId(1),
+                EventKind::ToolCall,
+                serde_json::json!({ "cmd": "acme_live_abcd1234" }),
+                None,
+            ))
+            .unwrap();
+        storage.flush().unwrap();
+
+        let target = Target::Run {
+            out: temp.path().to_path_buf(),
+            run_id,
+        };
+        let err = run_pass(&target, false).unwrap_err();
+        assert!(err.to_string().contains("never finalized"));
+    }
+}
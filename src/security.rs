@@ -4,13 +4,13 @@
 //! prompt injection attempts, privilege escalation, external access, etc.
 
 use chrono::{DateTime, Utc};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use crate::Event;
 
 /// Severity of a security finding.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum Severity {
     Info,
     Low,
@@ -32,7 +32,7 @@ impl std::fmt::Display for Severity {
 }
 
 /// Category of a security finding.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum Category {
     DestructiveOp,
     PromptInjection,
@@ -107,7 +107,7 @@ impl SecurityReport {
         // Findings by severity (critical first)
         out.push('\n');
         let mut sorted = self.findings.clone();
-        sorted.sort_by(|a, b| b.severity.cmp(&a.severity));
+        sorted.sort_by_key(|f| std::cmp::Reverse(f.severity));
 
         for finding in &sorted {
             out.push_str(&format!(
@@ -129,8 +129,47 @@ impl SecurityReport {
     }
 }
 
-/// Scan a slice of events for security issues.
+/// An extra substring to flag, matched case-insensitively against payloads.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CustomPattern {
+    pub pattern: String,
+    pub severity: Severity,
+    pub category: Category,
+    pub description: String,
+}
+
+/// Tunable scanner rules (the `[scanner]` section of `clawprint.toml`).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScanRules {
+    /// Flag agent runs with more tool calls than this
+    pub max_tool_calls_per_run: u64,
+    /// Flag minutes with more events than this
+    pub max_events_per_minute: u64,
+    /// Categories whose findings are dropped
+    pub disabled: Vec<Category>,
+    /// Patterns checked in addition to the built-in ones
+    pub patterns: Vec<CustomPattern>,
+}
+
+impl Default for ScanRules {
+    fn default() -> Self {
+        Self {
+            max_tool_calls_per_run: 50,
+            max_events_per_minute: 100,
+            disabled: Vec::new(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// Scan a slice of events for security issues with the default rules.
 pub fn scan_events(events: &[Event]) -> SecurityReport {
+    scan_events_with(events, &ScanRules::default())
+}
+
+/// Scan a slice of events for security issues.
+pub fn scan_events_with(events: &[Event], rules: &ScanRules) -> SecurityReport {
     let mut findings = Vec::new();
     let scanned = events.len() as u64;
 
@@ -441,6 +480,21 @@ pub fn scan_events(events: &[Event]) -> SecurityReport {
             }
         }
 
+        // --- Configured patterns ---
+        for custom in &rules.patterns {
+            check_pattern(
+                &mut findings,
+                &payload_lower,
+                &custom.pattern.to_lowercase(),
+                custom.severity,
+                custom.category,
+                &custom.description,
+                eid,
+                ts,
+                &payload_str,
+            );
+        }
+
         // --- External Access ---
         // Only flag in tool use events (not every URL mention)
         let is_tool_event = event
@@ -495,7 +549,7 @@ pub fn scan_events(events: &[Event]) -> SecurityReport {
 
     // --- Cost Anomaly: high tool call count per run ---
     for (run_id, count) in &run_tool_counts {
-        if *count > 50 {
+        if *count > rules.max_tool_calls_per_run {
             findings.push(SecurityFinding {
                 severity: Severity::Medium,
                 category: Category::CostAnomaly,
@@ -509,7 +563,7 @@ pub fn scan_events(events: &[Event]) -> SecurityReport {
 
     // --- Cost Anomaly: high event rate per minute ---
     for (minute, count) in &minute_counts {
-        if *count > 100 {
+        if *count > rules.max_events_per_minute {
             findings.push(SecurityFinding {
                 severity: Severity::Medium,
                 category: Category::CostAnomaly,
@@ -521,6 +575,8 @@ pub fn scan_events(events: &[Event]) -> SecurityReport {
         }
     }
 
+    findings.retain(|f| !rules.disabled.contains(&f.category));
+
     // Build summary
     let mut summary: HashMap<Category, u64> = HashMap::new();
     for f in &findings {
@@ -791,6 +847,39 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_scan_rules_custom_pattern_and_disabled() {
+        let events = vec![make_event(
+            1,
+            EventKind::AgentEvent,
+            serde_json::json!({
+                "data": {"type": "tool_use", "tool": "bash", "args": {"command": "sudo kubectl delete ns prod"}}
+            }),
+        )];
+        let rules = ScanRules {
+            disabled: vec![Category::PrivilegeEscalation],
+            patterns: vec![CustomPattern {
+                pattern: "KUBECTL DELETE".into(),
+                severity: Severity::High,
+                category: Category::DestructiveOp,
+                description: "Kubernetes resource deletion".into(),
+            }],
+            ..ScanRules::default()
+        };
+        let report = scan_events_with(&events, &rules);
+        assert_eq!(report.findings.len(), 1);
+        assert_eq!(
+            report.findings[0].description,
+            "Kubernetes resource deletion"
+        );
+        assert!(
+            scan_events(&events)
+                .findings
+                .iter()
+                .any(|f| f.category == Category::PrivilegeEscalation)
+        );
+    }
+
     #[test]
     fn test_severity_ordering() {
         assert!(Severity::Critical > Severity::High);
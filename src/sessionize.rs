@@ -0,0 +1,431 @@
+//! Sessionizing — materialize daemon agent runs as per-run case files
+//!
+//! The daemon writes everything into one continuous ledger, while `list`,
+//! `view`, `replay`, `diff` and the viewer dashboard work on `runs/<id>`
+//! case files. With sessionizing enabled, each agent run is copied into its
+//! own `RunStorage` once its lifecycle ends, wrapped in RUN_START / RUN_END
+//! and sealed with a `meta.json` whose `ledger_range` points back at the
+//! ledger events it was built from.
+//!
+//! The case file has its own hash chain; the ledger remains the primary
+//! record and is never modified.
+
+use anyhow::{Result, bail};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::ledger::Ledger;
+use crate::spool::SpoolSync;
+use crate::storage::RunStorage;
+use crate::{Event, EventId, EventKind, LedgerRange, RunId, RunMeta, RunStatus};
+
+/// How long to wait after a run's lifecycle ends before materializing it,
+/// so trailing chat output lands in the case file.
+pub const DEFAULT_GRACE: Duration = Duration::from_secs(5);
+
+/// Case files can be rebuilt from the ledger, so their crash spool is
+/// synced on an interval rather than after every event.
+const CASE_FILE_SPOOL_SYNC: SpoolSync = SpoolSync::Interval(Duration::from_secs(1));
+
+/// Case-file directory name for an agent run ID. Anything outside
+/// `[A-Za-z0-9_-]` is replaced so gateway-supplied IDs can't escape `runs/`.
+/// A replaced ID gets a short hash of the original, so `a/b` and `a_b`
+/// don't share a directory.
+pub fn case_file_id(agent_run: &str) -> RunId {
+    let id: String = agent_run
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !id.is_empty() && id == agent_run {
+        return RunId(id);
+    }
+    let digest = hex::encode(Sha256::digest(agent_run.as_bytes()));
+    RunId(format!(
+        "{}-{}",
+        if id.is_empty() { "_" } else { &id },
+        &digest[..8]
+    ))
+}
+
+/// Copy one agent run from the ledger into `runs/<id>` and seal it.
+///
+/// Returns `None` if the run has no events or was already materialized.
+/// A half-written case file from an earlier crash is replaced.
+pub fn materialize_agent_run(
+    ledger: &Ledger,
+    agent_run: &str,
+    base_path: &Path,
+    gateway_url: &str,
+    batch_size: usize,
+) -> Result<Option<RunMeta>> {
+    let events = ledger.get_agent_run_events(agent_run)?;
+    write_case_file(&events, agent_run, base_path, gateway_url, batch_size)
+}
+
+/// Write a case file from a snapshot of an agent run's ledger events.
+fn write_case_file(
+    events: &[Event],
+    agent_run: &str,
+    base_path: &Path,
+    gateway_url: &str,
+    batch_size: usize,
+) -> Result<Option<RunMeta>> {
+    let (Some(first), Some(last)) = (events.first(), events.last()) else {
+        return Ok(None);
+    };
+
+    let run_id = case_file_id(agent_run);
+    let run_path = base_path.join("runs").join(&run_id.0);
+    let meta_path = run_path.join("meta.json");
+    if meta_path.exists() {
+        let meta: RunMeta = serde_json::from_str(&fs::read_to_string(&meta_path)?)?;
+        let existing = meta.ledger_range.as_ref().map(|r| r.agent_run.as_str());
+        if existing == Some(agent_run) {
+            return Ok(None);
+        }
+        bail!(
+            "Case file {:?} already holds {}, not agent run {}",
+            run_path,
+            existing.map_or("a recorded run".to_string(), |r| format!("agent run {}", r)),
+            agent_run
+        );
+    }
+    if run_path.exists() {
+        warn!("Replacing unsealed case file for agent run {}", agent_run);
+        fs::remove_dir_all(&run_path)?;
+    }
+
+    let range = LedgerRange {
+        agent_run: agent_run.to_string(),
+        first_event_id: first.event_id.0,
+        last_event_id: last.event_id.0,
+        event_count: events.len() as u64,
+        last_hash: last.hash_self.clone(),
+    };
+
+    let mut storage =
+        RunStorage::new_with_spool(run_id.clone(), base_path, batch_size, CASE_FILE_SPOOL_SYNC)?;
+
+    let mut start = Event::new(
+        run_id.clone(),
+        EventId(1),
+        EventKind::RunStart,
+        serde_json::json!({
+            "gateway_url": gateway_url,
+            "source": "ledger",
+            "agent_run": agent_run,
+            "ledger_first_event_id": range.first_event_id,
+            "ledger_last_event_id": range.last_event_id,
+        }),
+        None,
+    );
+    start.ts = first.ts;
+    storage.write_event(start)?;
+
+    let mut next_id = 2;
+    for event in events {
+        let mut copy = event.clone();
+        copy.run_id = run_id.clone();
+        copy.event_id = EventId(next_id);
+        storage.write_event(copy)?;
+        next_id += 1;
+    }
+
+    let mut end = Event::new(
+        run_id.clone(),
+        EventId(next_id),
+        EventKind::RunEnd,
+        serde_json::json!({
+            "total_events": next_id,
+            "source": "ledger",
+            "ledger_last_hash": range.last_hash,
+        }),
+        None,
+    );
+    end.ts = last.ts;
+    storage.write_event(end)?;
+    storage.flush()?;
+
+    let meta = RunMeta {
+        run_id,
+        started_at: first.ts,
+        ended_at: Some(last.ts),
+        event_count: storage.event_count(),
+        root_hash: storage.root_hash().unwrap_or_default(),
+        gateway_url: gateway_url.to_string(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        status: RunStatus::Complete,
+        ledger_range: Some(range),
+    };
+    storage.finalize(&meta)?;
+
+    info!(
+        "Materialized agent run {} ({} events) to {:?}",
+        agent_run, meta.event_count, run_path
+    );
+    Ok(Some(meta))
+}
+
+/// One agent run's ledger events, snapshotted so its case file can be
+/// written without holding the ledger.
+pub struct CaseFileJob {
+    agent_run: String,
+    events: Vec<Event>,
+    base_path: PathBuf,
+    gateway_url: String,
+    batch_size: usize,
+}
+
+impl CaseFileJob {
+    /// Write and seal the case file. Blocking: fsyncs and SQLite writes.
+    pub fn write(self) -> Result<Option<RunMeta>> {
+        write_case_file(
+            &self.events,
+            &self.agent_run,
+            &self.base_path,
+            &self.gateway_url,
+            self.batch_size,
+        )
+    }
+
+    /// Write every job, logging failures.
+    pub fn write_all(jobs: Vec<CaseFileJob>) -> Vec<RunMeta> {
+        jobs.into_iter()
+            .filter_map(|job| {
+                let run = job.agent_run.clone();
+                job.write().unwrap_or_else(|e| {
+                    warn!("Failed to materialize agent run {}: {}", run, e);
+                    None
+                })
+            })
+            .collect()
+    }
+}
+
+/// Tracks completed agent runs and materializes them after a grace period.
+pub struct Sessionizer {
+    base_path: PathBuf,
+    gateway_url: String,
+    batch_size: usize,
+    grace: Duration,
+    pending: Vec<(String, Instant)>,
+}
+
+impl Sessionizer {
+    pub fn new(base_path: &Path, gateway_url: &str, batch_size: usize, grace: Duration) -> Self {
+        Self {
+            base_path: base_path.to_path_buf(),
+            gateway_url: gateway_url.to_string(),
+            batch_size,
+            grace,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Pick up runs the ledger has seen complete; their grace period starts
+    /// at `now`. Call after `Ledger::flush`.
+    pub fn collect(&mut self, ledger: &mut Ledger, now: Instant) {
+        for run in ledger.take_completed_agent_runs() {
+            if !self.pending.iter().any(|(r, _)| *r == run) {
+                self.pending.push((run, now));
+            }
+        }
+    }
+
+    /// Pick up completed runs, and snapshot those whose grace period has
+    /// passed. Only the snapshot needs the ledger; write the returned jobs
+    /// after releasing it.
+    pub fn take_due(&mut self, ledger: &mut Ledger, now: Instant) -> Vec<CaseFileJob> {
+        self.collect(ledger, now);
+        let grace = self.grace;
+        let (due, waiting): (Vec<_>, Vec<_>) = self
+            .pending
+            .drain(..)
+            .partition(|(_, at)| now.duration_since(*at) >= grace);
+        self.pending = waiting;
+        self.snapshot(ledger, due.into_iter().map(|(run, _)| run))
+    }
+
+    /// [`take_due`](Self::take_due) and write the case files in place.
+    pub fn poll(&mut self, ledger: &mut Ledger, now: Instant) -> Vec<RunMeta> {
+        CaseFileJob::write_all(self.take_due(ledger, now))
+    }
+
+    /// Materialize every pending run regardless of grace (on shutdown).
+    pub fn drain(&mut self, ledger: &mut Ledger) -> Vec<RunMeta> {
+        let mut runs: Vec<String> = self.pending.drain(..).map(|(run, _)| run).collect();
+        for run in ledger.take_completed_agent_runs() {
+            if !runs.contains(&run) {
+                runs.push(run);
+            }
+        }
+        CaseFileJob::write_all(self.snapshot(ledger, runs.into_iter()))
+    }
+
+    fn snapshot(&self, ledger: &Ledger, runs: impl Iterator<Item = String>) -> Vec<CaseFileJob> {
+        runs.filter_map(|run| match ledger.get_agent_run_events(&run) {
+            Ok(events) => Some(CaseFileJob {
+                agent_run: run,
+                events,
+                base_path: self.base_path.clone(),
+                gateway_url: self.gateway_url.clone(),
+                batch_size: self.batch_size,
+            }),
+            Err(e) => {
+                warn!("Failed to read agent run {}: {}", run, e);
+                None
+            }
+        })
+        .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{list_runs, verify_event_chain};
+    use tempfile::TempDir;
+
+    fn agent_event(data: serde_json::Value) -> Event {
+        Event::new(
+            RunId("daemon".into()),
+            EventId(0),
+            EventKind::AgentEvent,
+            serde_json::json!({ "gateway_event": "agent", "data": data }),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_case_file_id_is_path_safe() {
+        assert_eq!(case_file_id("run-abc_1").0, "run-abc_1");
+        let escaped = case_file_id("../../etc").0;
+        assert!(escaped.starts_with("______etc-") && escaped.len() == 18);
+        assert!(case_file_id("").0.starts_with("_-"));
+    }
+
+    #[test]
+    fn test_case_file_ids_dont_collide() {
+        assert_ne!(case_file_id("a/b"), case_file_id("a_b"));
+        assert_ne!(case_file_id("a/b"), case_file_id("a.b"));
+        assert_eq!(case_file_id("a/b"), case_file_id("a/b"));
+    }
+
+    #[test]
+    fn test_existing_case_file_for_other_run_is_an_error() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        ledger
+            .append_event(agent_event(serde_json::json!({
+                "runId": "r1", "stream": "lifecycle", "data": {"phase": "end"}
+            })))
+            .unwrap();
+        ledger.flush().unwrap();
+
+        // A recorded run that happens to use the same name
+        let run_id = RunId("r1".into());
+        let mut storage = RunStorage::new(run_id.clone(), temp.path(), 10).unwrap();
+        storage
+            .finalize(&RunMeta::new(run_id, "ws://gw".into()))
+            .unwrap();
+
+        let err = materialize_agent_run(&ledger, "r1", temp.path(), "ws://gw", 100)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("a recorded run"), "{}", err);
+    }
+
+    #[test]
+    fn test_case_file_written_from_snapshot_without_ledger() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+        ledger
+            .append_event(agent_event(serde_json::json!({
+                "runId": "r1", "stream": "lifecycle", "data": {"phase": "end"}
+            })))
+            .unwrap();
+        ledger.flush().unwrap();
+
+        let mut s = Sessionizer::new(temp.path(), "ws://gw", 100, Duration::ZERO);
+        let jobs = s.take_due(&mut ledger, Instant::now());
+        assert_eq!(jobs.len(), 1);
+        assert!(
+            list_runs(temp.path()).unwrap().is_empty(),
+            "nothing written yet"
+        );
+
+        // Events committed after the snapshot don't leak into the case file
+        ledger
+            .append_event(agent_event(
+                serde_json::json!({ "runId": "r1", "late": true }),
+            ))
+            .unwrap();
+        ledger.flush().unwrap();
+        drop(ledger);
+
+        let sealed = CaseFileJob::write_all(jobs);
+        assert_eq!(sealed.len(), 1);
+        assert_eq!(sealed[0].ledger_range.as_ref().unwrap().event_count, 1);
+        assert_eq!(sealed[0].event_count, 3);
+    }
+
+    #[test]
+    fn test_completed_run_becomes_case_file() {
+        let temp = TempDir::new().unwrap();
+        let mut ledger = Ledger::open(temp.path(), 100).unwrap();
+
+        ledger
+            .append_event(agent_event(serde_json::json!({
+                "runId": "r1", "stream": "lifecycle", "data": {"phase": "start"}
+            })))
+            .unwrap();
+        ledger
+            .append_event(agent_event(serde_json::json!({
+                "runId": "other", "type": "tool_use", "tool": "bash"
+            })))
+            .unwrap();
+        ledger
+            .append_event(agent_event(serde_json::json!({
+                "runId": "r1", "stream": "lifecycle", "data": {"phase": "end"}
+            })))
+            .unwrap();
+        ledger.flush().unwrap();
+
+        let mut s = Sessionizer::new(temp.path(), "ws://gw", 100, Duration::from_secs(60));
+        let t0 = Instant::now();
+        assert!(s.poll(&mut ledger, t0).is_empty(), "still in grace period");
+
+        let sealed = s.poll(&mut ledger, t0 + Duration::from_secs(61));
+        assert_eq!(sealed.len(), 1);
+        let range = sealed[0].ledger_range.as_ref().unwrap();
+        assert_eq!((range.first_event_id, range.last_event_id), (1, 3));
+        assert_eq!(range.event_count, 2);
+
+        let runs = list_runs(temp.path()).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].0.0, "r1");
+
+        let storage = RunStorage::open(RunId("r1".into()), temp.path()).unwrap();
+        let events = storage.load_events(None).unwrap();
+        assert_eq!(events.len(), 4);
+        assert_eq!(events[0].kind, EventKind::RunStart);
+        assert_eq!(events[3].kind, EventKind::RunEnd);
+        assert!(verify_event_chain(&events).unwrap());
+
+        // Already sealed: not materialized twice
+        assert!(
+            materialize_agent_run(&ledger, "r1", temp.path(), "ws://gw", 100)
+                .unwrap()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,574 @@
+//! Configuration file — `clawprint.toml`
+//!
+//! Every command reads the same file, so the gateway, storage, redaction,
+//! ingest, scanner, viewer/MCP, admin and sink settings don't have to be repeated as flags.
+//! Precedence is CLI flag > config file > built-in default.
+//!
+//! The file is looked up in order:
+//!
+//! 1. `./clawprint.toml`
+//! 2. `$XDG_CONFIG_HOME/clawprint/clawprint.toml` (or `~/.config/clawprint/`)
+//! 3. `/etc/clawprint/clawprint.toml`
+//!
+//! unless `--config PATH` names one explicitly.
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::Config;
+use crate::assign::{AssignPolicy, AssignStrategy};
+use crate::filter::{FilterPolicy, FilterRule};
+use crate::pii::PiiRules;
+use crate::redact::{EntropyRules, RedactionMode, RedactionRule, RedactionRules, Redactor};
+use crate::security::ScanRules;
+use crate::sink::SinkConfig;
+use crate::spool::SpoolSync;
+use crate::vault::VaultSettings;
+
+/// Config file name searched for in each location
+pub const FILE_NAME: &str = "clawprint.toml";
+
+/// Placeholder shown instead of secrets by `config show`
+pub const MASK: &str = "********";
+
+/// Contents of `clawprint.toml`. Every section and field is optional.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    pub gateway: GatewaySettings,
+    pub storage: StorageSettings,
+    pub redaction: RedactionSettings,
+    pub ingest: IngestSettings,
+    pub scanner: ScanRules,
+    pub viewer: ViewerSettings,
+    pub mcp: McpSettings,
+    pub admin: AdminSettings,
+    /// Output sinks (`[[sinks]]` tables), fed by the daemon
+    pub sinks: Vec<SinkConfig>,
+}
+
+/// `[gateway]` — the OpenClaw gateway to record from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GatewaySettings {
+    /// Gateway WebSocket URL
+    pub url: String,
+    /// Gateway auth token (falls back to ~/.openclaw/openclaw.json)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl Default for GatewaySettings {
+    fn default() -> Self {
+        Self {
+            url: "ws://127.0.0.1:18789".to_string(),
+            token: None,
+        }
+    }
+}
+
+/// `[storage]` — where and how events are written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageSettings {
+    /// Output directory for runs and the daemon ledger
+    pub out: PathBuf,
+    /// Batch size for SQLite commits
+    pub batch_size: usize,
+    /// How often buffered events are flushed, in milliseconds
+    pub flush_interval_ms: u64,
+    /// fsync the crash spool at most every N ms (0 = after every event)
+    pub spool_sync_ms: u64,
+}
+
+impl Default for StorageSettings {
+    fn default() -> Self {
+        Self {
+            out: PathBuf::from("./clawprints"),
+            batch_size: 100,
+            flush_interval_ms: 200,
+            spool_sync_ms: 0,
+        }
+    }
+}
+
+/// `[redaction]` — secret redaction before events are stored. Rule keys
+/// are the same as in a rules file; see [`RedactionRules`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionSettings {
+    pub enabled: bool,
+    /// TOML rules file applied before the rules below; relative paths are
+    /// resolved against the config file's directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rules_file: Option<PathBuf>,
+    /// Start from the built-in rules and allowlist
+    pub builtin: bool,
+    pub rules: Vec<RedactionRule>,
+    pub allow_fields: Vec<String>,
+    pub allow_values: Vec<String>,
+    pub entropy: EntropyRules,
+    pub pii: PiiRules,
+    pub mode: RedactionMode,
+    /// HMAC key for `pseudonymize` mode; relative paths are resolved like
+    /// `rules_file`. Must live outside the ledger directory.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_file: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vault: Option<VaultSettings>,
+    /// Contents of `rules_file`, once loaded
+    #[serde(skip)]
+    file_rules: Option<RedactionRules>,
+}
+
+impl Default for RedactionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            rules_file: None,
+            builtin: true,
+            rules: Vec::new(),
+            allow_fields: Vec::new(),
+            allow_values: Vec::new(),
+            entropy: EntropyRules::default(),
+            pii: PiiRules::default(),
+            mode: RedactionMode::Mask,
+            key_file: None,
+            vault: None,
+            file_rules: None,
+        }
+    }
+}
+
+impl RedactionSettings {
+    /// Read `rules_file`, resolving relative `rules_file` and `key_file`
+    /// paths against `base`.
+    pub fn load_rules_file(&mut self, base: &Path) -> Result<()> {
+        if let Some(path) = &self.key_file {
+            self.key_file = Some(base.join(path));
+        }
+        if let Some(path) = &self.rules_file {
+            let path = base.join(path);
+            self.file_rules = Some(RedactionRules::from_file(&path)?);
+            self.rules_file = Some(path);
+        }
+        Ok(())
+    }
+
+    /// The rules file (if loaded) followed by the inline rules.
+    pub fn to_rules(&self) -> RedactionRules {
+        let inline = RedactionRules {
+            builtin: self.builtin,
+            rules: self.rules.clone(),
+            allow_fields: self.allow_fields.clone(),
+            allow_values: self.allow_values.clone(),
+            entropy: self.entropy.clone(),
+            pii: self.pii.clone(),
+            mode: self.mode,
+            key_file: self.key_file.clone(),
+            vault: self.vault.clone(),
+        };
+        match &self.file_rules {
+            Some(file) => {
+                let mut rules = file.clone();
+                rules.merge(inline);
+                rules
+            }
+            None => inline,
+        }
+    }
+}
+
+/// `[ingest]` — filtering and run grouping. Rules and strategies accept the
+/// same strings as `--filter` and `--assign`, or their table form.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IngestSettings {
+    /// Write each completed agent run to its own case file (daemon)
+    pub sessionize: bool,
+    #[serde(serialize_with = "as_strings", deserialize_with = "string_or_table")]
+    pub filter: Vec<FilterRule>,
+    #[serde(serialize_with = "as_strings", deserialize_with = "string_or_table")]
+    pub assign: Vec<AssignStrategy>,
+}
+
+/// `[viewer]` — web viewer bind address and auth
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ViewerSettings {
+    pub host: String,
+    pub port: u16,
+    /// Bearer token required by the viewer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl Default for ViewerSettings {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            token: None,
+        }
+    }
+}
+
+/// `[mcp]` — MCP server transport, bind address and auth
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct McpSettings {
+    /// "stdio" or "sse"
+    pub transport: String,
+    pub host: String,
+    pub port: u16,
+    /// Bearer token required by the SSE server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl Default for McpSettings {
+    fn default() -> Self {
+        Self {
+            transport: "stdio".to_string(),
+            host: "0.0.0.0".to_string(),
+            port: 3000,
+            token: None,
+        }
+    }
+}
+
+/// `[admin]` — daemon admin API (`POST /admin/reload`), off unless `port` is set
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdminSettings {
+    pub host: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// Bearer token required by the admin API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+}
+
+impl Default for AdminSettings {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: None,
+            token: None,
+        }
+    }
+}
+
+impl Settings {
+    /// Candidate config file locations, highest priority first.
+    pub fn search_paths() -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::from(FILE_NAME)];
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|h| PathBuf::from(h).join(".config")));
+        if let Some(dir) = config_home {
+            paths.push(dir.join("clawprint").join(FILE_NAME));
+        }
+        paths.push(PathBuf::from("/etc/clawprint").join(FILE_NAME));
+        paths
+    }
+
+    /// The first config file that exists in the search path.
+    pub fn discover() -> Option<PathBuf> {
+        Self::search_paths().into_iter().find(|p| p.is_file())
+    }
+
+    /// Load `explicit` if given (it must exist), otherwise the discovered
+    /// file, otherwise defaults. Returns the settings and the file used.
+    pub fn load(explicit: Option<&Path>) -> Result<(Self, Option<PathBuf>)> {
+        let path = match explicit {
+            Some(p) if !p.is_file() => {
+                return Err(anyhow!("Config file not found: {:?}", p));
+            }
+            Some(p) => Some(p.to_path_buf()),
+            None => Self::discover(),
+        };
+        match path {
+            Some(p) => Ok((Self::from_file(&p)?, Some(p))),
+            None => Ok((Self::default(), None)),
+        }
+    }
+
+    /// Read and validate a config file.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config {:?}: {}", path, e))?;
+        let mut settings =
+            Self::parse(&content).map_err(|e| anyhow!("Invalid config {:?}: {}", path, e))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        settings.redaction.load_rules_file(base)?;
+        let rules = settings.redaction.to_rules();
+        rules
+            .check_key_outside(&settings.storage.out)
+            .map_err(|e| anyhow!("Invalid config {:?}: {}", path, e))?;
+        Redactor::new(&rules).map_err(|e| anyhow!("Invalid config {:?}: {}", path, e))?;
+        Ok(settings)
+    }
+
+    /// Parse and validate TOML text.
+    pub fn parse(content: &str) -> Result<Self> {
+        let settings: Settings = toml::from_str(content)?;
+        if settings.storage.batch_size == 0 {
+            return Err(anyhow!("storage.batch_size must be greater than 0"));
+        }
+        if settings.storage.flush_interval_ms == 0 {
+            return Err(anyhow!("storage.flush_interval_ms must be greater than 0"));
+        }
+        crate::sink::build_all(&settings.sinks)?;
+        // Pseudonymization and the vault are checked by `from_file`, once the
+        // key path is resolved
+        Redactor::new(&RedactionRules {
+            mode: RedactionMode::Mask,
+            vault: None,
+            ..settings.redaction.to_rules()
+        })?;
+        Ok(settings)
+    }
+
+    /// Recorder configuration from the file alone; the CLI overrides fields.
+    pub fn to_config(&self) -> Config {
+        Config {
+            output_dir: self.storage.out.clone(),
+            redact_secrets: self.redaction.enabled,
+            redaction: self.redaction.to_rules(),
+            gateway_url: self.gateway.url.clone(),
+            auth_token: self.gateway.token.clone(),
+            batch_size: self.storage.batch_size,
+            flush_interval_ms: self.storage.flush_interval_ms,
+            filter: FilterPolicy::new(self.ingest.filter.clone()),
+            spool_sync: SpoolSync::from_millis(self.storage.spool_sync_ms),
+            assign: AssignPolicy::new(self.ingest.assign.clone()),
+            sessionize: self.ingest.sessionize,
+            sinks: self.sinks.clone(),
+        }
+    }
+
+    /// Copy with every secret replaced by `MASK`.
+    pub fn masked(&self) -> Self {
+        let mask = |t: &Option<String>| t.as_ref().map(|_| MASK.to_string());
+        let mut s = self.clone();
+        s.gateway.token = mask(&s.gateway.token);
+        s.viewer.token = mask(&s.viewer.token);
+        s.mcp.token = mask(&s.mcp.token);
+        s.admin.token = mask(&s.admin.token);
+        s.sinks = s.sinks.iter().map(SinkConfig::masked).collect();
+        s
+    }
+
+    /// Render as TOML.
+    pub fn to_toml(&self) -> Result<String> {
+        Ok(toml::to_string(self)?)
+    }
+}
+
+fn as_strings<S, T>(items: &[T], serializer: S) -> std::result::Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    T: Display,
+{
+    serializer.collect_seq(items.iter().map(|i| i.to_string()))
+}
+
+fn string_or_table<'de, D, T>(deserializer: D) -> std::result::Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + FromStr<Err = anyhow::Error>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Item<T> {
+        Short(String),
+        Table(T),
+    }
+
+    Vec::<Item<T>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|item| match item {
+            Item::Short(s) => s.parse().map_err(serde::de::Error::custom),
+            Item::Table(t) => Ok(t),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::filter::FilterAction;
+    use crate::security::Category;
+    use tempfile::TempDir;
+
+    const EXAMPLE: &str = r#"
+[gateway]
+url = "ws://10.0.0.5:18789"
+token = "gw-secret"
+
+[storage]
+out = "/var/lib/clawprint"
+flush_interval_ms = 500
+
+[redaction]
+enabled = false
+
+[ingest]
+sessionize = true
+filter = ["tick=aggregate:60", { event = "presence", action = "drop" }]
+assign = ["run-id", "seq-window:3"]
+
+[scanner]
+max_tool_calls_per_run = 200
+disabled = ["ExternalAccess"]
+
+[viewer]
+port = 9090
+token = "view-secret"
+
+[[sinks]]
+name = "archive"
+type = "ndjson"
+path = "/var/log/clawprint/events.ndjson"
+
+[[sinks]]
+name = "siem"
+type = "splunk"
+url = "https://splunk:8088"
+token = "hec-secret"
+batch_size = 500
+"#;
+
+    #[test]
+    fn test_parse_example() {
+        let s = Settings::parse(EXAMPLE).unwrap();
+        assert_eq!(s.gateway.url, "ws://10.0.0.5:18789");
+        assert_eq!(s.storage.batch_size, 100, "unset fields keep defaults");
+        assert_eq!(s.ingest.filter.len(), 2);
+        assert_eq!(s.ingest.filter[1].action, FilterAction::Drop);
+        assert_eq!(s.ingest.assign[1], AssignStrategy::SeqWindow { window: 3 });
+        assert_eq!(s.scanner.disabled, vec![Category::ExternalAccess]);
+        assert_eq!(s.scanner.max_events_per_minute, 100);
+        assert_eq!(s.viewer.host, "127.0.0.1");
+        assert_eq!(s.sinks.len(), 2);
+        assert_eq!(s.sinks[0].batch_size, 100);
+        assert_eq!(s.sinks[1].batch_size, 500);
+
+        let config = s.to_config();
+        assert_eq!(config.flush_interval_ms, 500);
+        assert!(!config.redact_secrets);
+        assert!(config.sessionize);
+        assert_eq!(config.auth_token.as_deref(), Some("gw-secret"));
+    }
+
+    #[test]
+    fn test_rejects_unknown_and_invalid() {
+        assert!(Settings::parse("[gateway]\nurll = \"x\"").is_err());
+        assert!(Settings::parse("[ingest]\nfilter = [\"tick=bogus\"]").is_err());
+        assert!(Settings::parse("[storage]\nflush_interval_ms = 0").is_err());
+        let sink = "[[sinks]]\nname = \"a\"\ntype = \"webhook\"\nurl = \"http://x\"\n";
+        assert!(Settings::parse(sink).is_ok());
+        assert!(Settings::parse(&sink.repeat(2)).is_err(), "duplicate name");
+        assert!(Settings::parse("[[sinks]]\nname = \"a\"\ntype = \"kafka\"").is_err());
+    }
+
+    #[test]
+    fn test_masked_round_trip() {
+        let s = Settings::parse(EXAMPLE).unwrap();
+        let shown = s.masked().to_toml().unwrap();
+        assert!(!shown.contains("gw-secret"));
+        assert!(!shown.contains("view-secret"));
+        assert!(!shown.contains("hec-secret"));
+        assert!(shown.contains(MASK));
+        assert!(shown.contains("\"tick=aggregate:60\""));
+
+        let reparsed = Settings::parse(&shown).unwrap();
+        assert_eq!(reparsed.ingest, s.ingest);
+        assert_eq!(reparsed.scanner, s.scanner);
+        assert_eq!(reparsed.sinks[0], s.sinks[0]);
+    }
+
+    #[test]
+    fn test_load_explicit_path() {
+        let temp = TempDir::new().unwrap();
+        let missing = temp.path().join("nope.toml");
+        assert!(Settings::load(Some(&missing)).is_err());
+
+        let path = temp.path().join(FILE_NAME);
+        std::fs::write(&path, "[viewer]\nport = 1234\n").unwrap();
+        let (s, used) = Settings::load(Some(&path)).unwrap();
+        assert_eq!(s.viewer.port, 1234);
+        assert_eq!(used, Some(path));
+    }
+
+    #[test]
+    fn test_redaction_rules_file() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(
+            temp.path().join("redaction.toml"),
+            "allow_fields = [\"token_budget\"]\n\n[[rules]]\nname = \"acme\"\nvalue = \"acme_[0-9]+\"\nlabel = \"ACME\"\n",
+        )
+        .unwrap();
+        let path = temp.path().join(FILE_NAME);
+        std::fs::write(
+            &path,
+            "[redaction]\nrules_file = \"redaction.toml\"\n\n[[redaction.rules]]\nname = \"jwt\"\nenabled = false\n",
+        )
+        .unwrap();
+        let (s, _) = Settings::load(Some(&path)).unwrap();
+        let rules = s.to_config().redaction;
+        assert_eq!(rules.rules[0].name, "acme", "file rules come first");
+        assert!(!rules.rules[1].enabled);
+        assert_eq!(rules.allow_fields, ["token_budget"]);
+
+        let redactor = Redactor::new(&rules).unwrap();
+        let mut value = serde_json::json!({"note": "acme_42", "token_budget": 9});
+        redactor.redact_json(&mut value);
+        assert_eq!(
+            value,
+            serde_json::json!({"note": "[REDACTED-ACME]", "token_budget": 9})
+        );
+
+        std::fs::write(
+            temp.path().join("redaction.toml"),
+            "[[rules]]\nname = \"bad\"\nvalue = \"(\"\n",
+        )
+        .unwrap();
+        let err = Settings::load(Some(&path)).unwrap_err().to_string();
+        assert!(err.contains("'bad'"), "{}", err);
+        assert!(Settings::parse("[[redaction.rules]]\nname = \"x\"\nfields = [\"$\"]").is_err());
+
+        // Pseudonymization key: resolved against the config directory, kept
+        // out of the ledger directory
+        std::fs::write(temp.path().join("redaction.toml"), "").unwrap();
+        std::fs::write(temp.path().join("redaction.key"), "00".repeat(32)).unwrap();
+        let config = |out: &str| {
+            format!(
+                "[storage]\nout = {:?}\n\n[redaction]\nmode = \"pseudonymize\"\nkey_file = \"redaction.key\"\n",
+                temp.path().join(out)
+            )
+        };
+        std::fs::write(&path, config("ledger")).unwrap();
+        let (s, _) = Settings::load(Some(&path)).unwrap();
+        let rules = s.to_config().redaction;
+        assert_eq!(rules.key_file, Some(temp.path().join("redaction.key")));
+        // An --out override that contains the key is caught once merged
+        assert!(rules.check_key_outside(&temp.path().join("ledger")).is_ok());
+        let err = rules
+            .check_key_outside(temp.path())
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("outside the ledger directory"), "{}", err);
+        std::fs::write(&path, config("")).unwrap();
+        let err = Settings::load(Some(&path)).unwrap_err().to_string();
+        assert!(err.contains("outside the ledger directory"), "{}", err);
+        std::fs::write(&path, "[redaction]\nmode = \"pseudonymize\"\n").unwrap();
+        let err = Settings::load(Some(&path)).unwrap_err().to_string();
+        assert!(err.contains("needs a key_file"), "{}", err);
+    }
+}
@@ -0,0 +1,1228 @@
+//! Output sinks — forward sealed ledger events to external systems
+//!
+//! Each `[[sinks]]` entry in `clawprint.toml` becomes a [`Sink`] driven by
+//! [`run_sink`] inside the daemon. A runner reads committed events from the
+//! [`EventBus`], catches up from the ledger after a restart or when it falls
+//! behind, and stores the last delivered `event_id` in the ledger `meta`
+//! table under `sink.<name>.offset`. A sink that is down holds its position
+//! instead of dropping events: failed batches are retried with backoff.
+//!
+//! Delivery is at-least-once. A crash between delivery and the offset write
+//! can repeat one batch; the NDJSON sink checks its file tail and the
+//! Elastic sink uses `hash_self` as the document `_id`, so neither repeats.
+
+use anyhow::{Context, Result, anyhow, bail};
+use futures::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Seek, SeekFrom, Write as _};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::ledger::Ledger;
+use crate::otlp::{OtlpExporter, OtlpSink};
+use crate::settings::MASK;
+use crate::subscribe::EventStream;
+use crate::{Event, EventKind};
+
+/// How long a runner waits to fill a batch once it has one event
+pub const BATCH_WAIT: Duration = Duration::from_secs(1);
+/// How long a runner keeps delivering after the daemon stops
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+const RETRY_MIN: Duration = Duration::from_secs(1);
+const RETRY_MAX: Duration = Duration::from_secs(60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Private enterprise number for syslog structured data. 32473 is the
+/// number RFC 5612 reserves for documentation and examples.
+const SYSLOG_SD_ID: &str = "clawprint@32473";
+
+/// A destination for committed events.
+pub trait Sink: Send {
+    /// Unique name; the delivery offset is stored under it.
+    fn name(&self) -> &str;
+
+    /// Most events handed to one [`deliver`](Sink::deliver) call.
+    fn batch_size(&self) -> usize {
+        1
+    }
+
+    /// Deliver a batch in chain order. An error leaves the offset where it
+    /// was and the same batch is retried.
+    fn deliver<'a>(&'a mut self, events: &'a [Arc<Event>]) -> BoxFuture<'a, Result<()>>;
+
+    /// Called once by [`run_sink`] before delivery starts, with the ledger
+    /// directory. Sinks that read back from the ledger open it here.
+    fn open(&mut self, _dir: &Path) -> Result<()> {
+        Ok(())
+    }
+
+    /// Highest event_id the destination is known to hold, if the sink can
+    /// tell. Used on startup in addition to the stored offset.
+    fn delivered_through(&mut self) -> Result<Option<u64>> {
+        Ok(None)
+    }
+}
+
+/// `[[sinks]]` — one output sink
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SinkConfig {
+    /// Unique name, used for the stored offset and in logs
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: SinkKind,
+    /// Most events per delivery (HTTP sinks send them in one request)
+    #[serde(default = "default_batch_size")]
+    pub batch_size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SinkKind {
+    /// One JSON event per line, rotated by size
+    Ndjson {
+        path: PathBuf,
+        /// Rotate once the file would grow past this many bytes
+        #[serde(default = "default_max_bytes")]
+        max_bytes: u64,
+        /// Rotated files to keep (`path.1` … `path.N`)
+        #[serde(default = "default_keep")]
+        keep: usize,
+    },
+    /// RFC 5424 syslog messages
+    Syslog {
+        /// `host:port` for UDP/TCP, a socket path for unix
+        address: String,
+        #[serde(default)]
+        transport: SyslogTransport,
+        /// Syslog facility number (16 = local0)
+        #[serde(default = "default_facility")]
+        facility: u8,
+    },
+    /// JSON array of events POSTed to a URL
+    Webhook {
+        url: String,
+        /// Sent as `Authorization: Bearer <token>`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        token: Option<String>,
+    },
+    /// Elasticsearch / OpenSearch `_bulk` API
+    Elastic {
+        /// Cluster URL, e.g. `http://localhost:9200`
+        url: String,
+        #[serde(default = "default_index")]
+        index: String,
+        /// Sent as `Authorization: ApiKey <api_key>`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        api_key: Option<String>,
+    },
+    /// Splunk HTTP Event Collector
+    Splunk {
+        /// HEC URL, e.g. `https://splunk:8088`
+        url: String,
+        token: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        index: Option<String>,
+        #[serde(default = "default_sourcetype")]
+        sourcetype: String,
+    },
+    /// Agent runs as OpenTelemetry traces over OTLP/HTTP, sent when each
+    /// run ends
+    Otlp {
+        /// Collector URL, e.g. `http://localhost:4318`
+        endpoint: String,
+        /// Extra request headers, e.g. for collector authentication
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        headers: BTreeMap<String, String>,
+        #[serde(default = "default_service_name")]
+        service_name: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyslogTransport {
+    #[default]
+    Udp,
+    Tcp,
+    Unix,
+}
+
+fn default_batch_size() -> usize {
+    100
+}
+fn default_max_bytes() -> u64 {
+    100 * 1024 * 1024
+}
+fn default_keep() -> usize {
+    5
+}
+fn default_facility() -> u8 {
+    16
+}
+fn default_index() -> String {
+    "clawprint".to_string()
+}
+fn default_sourcetype() -> String {
+    "clawprint".to_string()
+}
+fn default_service_name() -> String {
+    "clawprint".to_string()
+}
+
+impl SinkConfig {
+    /// Create the sink this entry describes.
+    pub fn build(&self) -> Result<Box<dyn Sink>> {
+        if self.name.is_empty() {
+            bail!("sink name must not be empty");
+        }
+        let name = self.name.clone();
+        let batch_size = self.batch_size.max(1);
+        Ok(match &self.kind {
+            SinkKind::Ndjson {
+                path,
+                max_bytes,
+                keep,
+            } => Box::new(NdjsonSink::new(
+                name,
+                batch_size,
+                path.clone(),
+                *max_bytes,
+                *keep,
+            )),
+            SinkKind::Syslog {
+                address,
+                transport,
+                facility,
+            } => {
+                if *facility > 23 {
+                    bail!("sink '{}': syslog facility must be 0-23", self.name);
+                }
+                Box::new(SyslogSink::new(
+                    name,
+                    batch_size,
+                    address.clone(),
+                    *transport,
+                    *facility,
+                ))
+            }
+            SinkKind::Webhook { url, token } => Box::new(HttpSink::new(
+                name,
+                batch_size,
+                HttpFormat::Webhook,
+                url.clone(),
+                token.as_ref().map(|t| format!("Bearer {}", t)),
+            )?),
+            SinkKind::Elastic {
+                url,
+                index,
+                api_key,
+            } => Box::new(HttpSink::new(
+                name,
+                batch_size,
+                HttpFormat::Elastic {
+                    index: index.clone(),
+                },
+                format!("{}/_bulk", url.trim_end_matches('/')),
+                api_key.as_ref().map(|k| format!("ApiKey {}", k)),
+            )?),
+            SinkKind::Splunk {
+                url,
+                token,
+                index,
+                sourcetype,
+            } => Box::new(HttpSink::new(
+                name,
+                batch_size,
+                HttpFormat::Splunk {
+                    index: index.clone(),
+                    sourcetype: sourcetype.clone(),
+                    host: crate::lock::hostname(),
+                },
+                format!("{}/services/collector/event", url.trim_end_matches('/')),
+                Some(format!("Splunk {}", token)),
+            )?),
+            SinkKind::Otlp {
+                endpoint,
+                headers,
+                service_name,
+            } => Box::new(OtlpSink::new(
+                name,
+                batch_size,
+                OtlpExporter::new(endpoint, headers.clone(), service_name.clone())?,
+            )),
+        })
+    }
+
+    /// Copy with tokens and API keys replaced by `MASK`.
+    pub fn masked(&self) -> Self {
+        let mut masked = self.clone();
+        match &mut masked.kind {
+            SinkKind::Webhook { token, .. } => {
+                if token.is_some() {
+                    *token = Some(MASK.to_string());
+                }
+            }
+            SinkKind::Elastic { api_key, .. } => {
+                if api_key.is_some() {
+                    *api_key = Some(MASK.to_string());
+                }
+            }
+            SinkKind::Splunk { token, .. } => *token = MASK.to_string(),
+            SinkKind::Otlp { headers, .. } => {
+                headers.values_mut().for_each(|v| *v = MASK.to_string())
+            }
+            SinkKind::Ndjson { .. } | SinkKind::Syslog { .. } => {}
+        }
+        masked
+    }
+}
+
+/// Build every configured sink, rejecting duplicate names.
+pub fn build_all(configs: &[SinkConfig]) -> Result<Vec<Box<dyn Sink>>> {
+    let mut names = HashSet::new();
+    configs
+        .iter()
+        .map(|config| {
+            if !names.insert(config.name.as_str()) {
+                bail!("duplicate sink name '{}'", config.name);
+            }
+            config.build()
+        })
+        .collect()
+}
+
+/// Meta key holding a sink's last delivered event_id
+pub fn offset_key(name: &str) -> String {
+    format!("sink.{}.offset", name)
+}
+
+/// Deliver committed events from the ledger in `dir` to `sink` until `ct`
+/// is cancelled, then for up to [`SHUTDOWN_GRACE`] deliver whatever the
+/// writer committed last. `stream` must have been taken from the ledger's
+/// bus before this is called so no event falls between catch-up and live
+/// delivery.
+pub async fn run_sink(
+    mut sink: Box<dyn Sink>,
+    dir: PathBuf,
+    ledger: Arc<Mutex<Ledger>>,
+    mut stream: EventStream,
+    ct: CancellationToken,
+) -> Result<()> {
+    sink.open(&dir)?;
+    let key = offset_key(sink.name());
+    let stored = ledger
+        .lock()
+        .await
+        .get_meta(&key)?
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(0);
+    let mut offset = stored.max(sink.delivered_through()?.unwrap_or(0));
+    let reader = Ledger::open_readonly(&dir)?;
+    let batch_size = sink.batch_size().max(1);
+    info!("Sink {} delivering from event #{}", sink.name(), offset + 1);
+
+    let mut catching_up = true;
+    let mut backoff = RETRY_MIN;
+    'live: loop {
+        let batch = if catching_up {
+            let batch: Vec<_> = reader
+                .events_after(offset, batch_size)?
+                .into_iter()
+                .map(Arc::new)
+                .collect();
+            catching_up = batch.len() == batch_size;
+            batch
+        } else {
+            match next_batch(&mut stream, offset, batch_size, &ct).await {
+                Some((batch, gap)) => {
+                    catching_up = gap;
+                    batch
+                }
+                None => break 'live,
+            }
+        };
+        let Some(last) = batch.last().map(|e| e.event_id.0) else {
+            continue;
+        };
+
+        loop {
+            let result = tokio::select! {
+                _ = ct.cancelled() => break 'live,
+                result = sink.deliver(&batch) => result,
+            };
+            match result {
+                Ok(()) => break,
+                Err(e) => {
+                    warn!(
+                        "Sink {} failed to deliver events #{}-#{}: {:#}. Retrying in {}s",
+                        sink.name(),
+                        offset + 1,
+                        last,
+                        e,
+                        backoff.as_secs()
+                    );
+                    tokio::select! {
+                        _ = ct.cancelled() => break 'live,
+                        _ = tokio::time::sleep(backoff) => {}
+                    }
+                    backoff = (backoff * 2).min(RETRY_MAX);
+                }
+            }
+        }
+        backoff = RETRY_MIN;
+        offset = last;
+        ledger.lock().await.set_meta(&key, &offset.to_string())?;
+        debug!("Sink {} delivered through event #{}", sink.name(), offset);
+    }
+
+    // The writer has made its final flush; one attempt per batch from here
+    let deadline = Instant::now() + SHUTDOWN_GRACE;
+    let reason = loop {
+        let batch: Vec<_> = match reader.events_after(offset, batch_size) {
+            Ok(batch) => batch.into_iter().map(Arc::new).collect(),
+            Err(e) => break format!("{:#}", e),
+        };
+        let Some(last) = batch.last().map(|e| e.event_id.0) else {
+            return Ok(());
+        };
+        match tokio::time::timeout_at(deadline, sink.deliver(&batch)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => break format!("{:#}", e),
+            Err(_) => break format!("not done after {}s", SHUTDOWN_GRACE.as_secs()),
+        }
+        offset = last;
+        ledger.lock().await.set_meta(&key, &offset.to_string())?;
+    };
+    warn!(
+        "Sink {} stopped before catching up ({}); delivery resumes after event #{} on next start",
+        sink.name(),
+        reason,
+        offset
+    );
+    Ok(())
+}
+
+/// The next contiguous batch after `offset` from the live stream. The flag
+/// is set if events were missed and the runner must catch up from the
+/// ledger. `None` once cancelled or the stream closes.
+async fn next_batch(
+    stream: &mut EventStream,
+    offset: u64,
+    batch_size: usize,
+    ct: &CancellationToken,
+) -> Option<(Vec<Arc<Event>>, bool)> {
+    let mut batch = Vec::new();
+    let mut expected = offset + 1;
+    let mut deadline: Option<Instant> = None;
+    loop {
+        let recv = async {
+            match deadline {
+                Some(at) => tokio::time::timeout_at(at, stream.recv()).await.ok(),
+                None => Some(stream.recv().await),
+            }
+        };
+        let event = tokio::select! {
+            _ = ct.cancelled() => return None,
+            received = recv => match received {
+                // Batch window closed
+                None => return Some((batch, false)),
+                Some(None) => return None,
+                Some(Some(event)) => event,
+            },
+        };
+        let id = event.event_id.0;
+        if id < expected {
+            // Already delivered during catch-up
+            continue;
+        }
+        if id > expected {
+            return Some((batch, true));
+        }
+        expected += 1;
+        batch.push(event);
+        if batch.len() >= batch_size {
+            return Some((batch, false));
+        }
+        deadline.get_or_insert_with(|| Instant::now() + BATCH_WAIT);
+    }
+}
+
+/// NDJSON file with size-based rotation.
+pub struct NdjsonSink {
+    name: String,
+    batch_size: usize,
+    path: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    file: Option<(File, u64)>,
+}
+
+impl NdjsonSink {
+    pub fn new(
+        name: String,
+        batch_size: usize,
+        path: PathBuf,
+        max_bytes: u64,
+        keep: usize,
+    ) -> Self {
+        Self {
+            name,
+            batch_size,
+            path,
+            max_bytes,
+            keep,
+            file: None,
+        }
+    }
+
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", n));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> Result<()> {
+        self.file = None;
+        if self.keep == 0 {
+            std::fs::remove_file(&self.path)?;
+            return Ok(());
+        }
+        for n in (1..self.keep).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated(n + 1))?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated(1))?;
+        info!("Sink {} rotated {:?}", self.name, self.path);
+        Ok(())
+    }
+
+    /// Append a batch. On error the file is cut back to where this
+    /// batch started in it, so the retry doesn't land after a torn line.
+    fn write(&mut self, events: &[Arc<Event>]) -> Result<()> {
+        let mut start = self.file.as_ref().map(|(_, size)| *size);
+        let result = self.write_lines(events, &mut start);
+        if let Err(e) = &result {
+            // Reopen next time, at the truncated length
+            self.file = None;
+            if let Some(len) = start
+                && let Err(truncate) = OpenOptions::new()
+                    .write(true)
+                    .open(&self.path)
+                    .and_then(|file| file.set_len(len))
+            {
+                warn!(
+                    "Sink {} could not roll back a failed write ({}): {}",
+                    self.name, e, truncate
+                );
+            }
+        }
+        result
+    }
+
+    fn write_lines(&mut self, events: &[Arc<Event>], start: &mut Option<u64>) -> Result<()> {
+        for event in events {
+            let mut line = serde_json::to_vec(event.as_ref())?;
+            line.push(b'\n');
+            if let Some((_, size)) = &self.file
+                && *size > 0
+                && *size + line.len() as u64 > self.max_bytes
+            {
+                self.rotate()?;
+                *start = None;
+            }
+            let (file, size) = match &mut self.file {
+                Some(open) => open,
+                None => {
+                    if let Some(dir) = self.path.parent().filter(|d| !d.as_os_str().is_empty()) {
+                        std::fs::create_dir_all(dir)?;
+                    }
+                    let file = OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&self.path)
+                        .with_context(|| format!("Cannot open {:?}", self.path))?;
+                    let size = file.metadata()?.len();
+                    start.get_or_insert(size);
+                    self.file.insert((file, size))
+                }
+            };
+            file.write_all(&line)?;
+            *size += line.len() as u64;
+        }
+        if let Some((file, _)) = &self.file {
+            file.sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for NdjsonSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    fn deliver<'a>(&'a mut self, events: &'a [Arc<Event>]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move { self.write(events) })
+    }
+
+    fn delivered_through(&mut self) -> Result<Option<u64>> {
+        let Ok(mut file) = File::open(&self.path) else {
+            return Ok(None);
+        };
+        // The last complete line is within the final few KB for any sane event
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(64 * 1024)))?;
+        let last = BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str::<Event>(&line).ok())
+            .last();
+        Ok(last.map(|event| event.event_id.0))
+    }
+}
+
+/// RFC 5424 syslog over UDP, TCP (octet-counted framing) or a unix
+/// datagram socket such as `/dev/log`.
+pub struct SyslogSink {
+    name: String,
+    batch_size: usize,
+    address: String,
+    transport: SyslogTransport,
+    facility: u8,
+    hostname: String,
+    conn: Option<SyslogConn>,
+}
+
+enum SyslogConn {
+    Udp(tokio::net::UdpSocket),
+    Tcp(tokio::net::TcpStream),
+    #[cfg(unix)]
+    Unix(tokio::net::UnixDatagram),
+}
+
+impl SyslogSink {
+    pub fn new(
+        name: String,
+        batch_size: usize,
+        address: String,
+        transport: SyslogTransport,
+        facility: u8,
+    ) -> Self {
+        Self {
+            name,
+            batch_size,
+            address,
+            transport,
+            facility,
+            hostname: crate::lock::hostname(),
+            conn: None,
+        }
+    }
+
+    async fn connect(&self) -> Result<SyslogConn> {
+        Ok(match self.transport {
+            SyslogTransport::Udp => {
+                let socket = tokio::net::UdpSocket::bind("0.0.0.0:0").await?;
+                socket.connect(&self.address).await?;
+                SyslogConn::Udp(socket)
+            }
+            SyslogTransport::Tcp => {
+                SyslogConn::Tcp(tokio::net::TcpStream::connect(&self.address).await?)
+            }
+            #[cfg(unix)]
+            SyslogTransport::Unix => {
+                let socket = tokio::net::UnixDatagram::unbound()?;
+                socket.connect(&self.address)?;
+                SyslogConn::Unix(socket)
+            }
+            #[cfg(not(unix))]
+            SyslogTransport::Unix => bail!("unix syslog transport is not supported here"),
+        })
+    }
+
+    async fn send(&mut self, events: &[Arc<Event>]) -> Result<()> {
+        let conn = match &mut self.conn {
+            Some(conn) => conn,
+            None => {
+                let conn = self
+                    .connect()
+                    .await
+                    .with_context(|| format!("Cannot reach syslog at {}", self.address))?;
+                self.conn.insert(conn)
+            }
+        };
+        for event in events {
+            let message = format_syslog(event, self.facility, &self.hostname)?;
+            match conn {
+                SyslogConn::Udp(socket) => {
+                    socket.send(message.as_bytes()).await?;
+                }
+                SyslogConn::Tcp(stream) => {
+                    let framed = format!("{} {}", message.len(), message);
+                    stream.write_all(framed.as_bytes()).await?;
+                }
+                #[cfg(unix)]
+                SyslogConn::Unix(socket) => {
+                    socket.send(message.as_bytes()).await?;
+                }
+            }
+        }
+        if let SyslogConn::Tcp(stream) = conn {
+            stream.flush().await?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for SyslogSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    fn deliver<'a>(&'a mut self, events: &'a [Arc<Event>]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let result = self.send(events).await;
+            if result.is_err() {
+                self.conn = None;
+            }
+            result
+        })
+    }
+}
+
+/// Format one event as an RFC 5424 message: kind as MSGID, run, event_id
+/// and hash as structured data, the event JSON as MSG.
+pub fn format_syslog(event: &Event, facility: u8, hostname: &str) -> Result<String> {
+    // AUDIT traces are notice (5), everything else informational (6)
+    let severity = if event.kind == EventKind::Audit { 5 } else { 6 };
+    let kind = serde_json::to_value(event.kind)?;
+    Ok(format!(
+        "<{}>1 {} {} clawprint {} {} [{} run=\"{}\" event_id=\"{}\" hash=\"{}\"] {}",
+        u16::from(facility) * 8 + severity,
+        event
+            .ts
+            .to_rfc3339_opts(chrono::SecondsFormat::Micros, true),
+        syslog_field(hostname),
+        std::process::id(),
+        kind.as_str().unwrap_or("EVENT"),
+        SYSLOG_SD_ID,
+        sd_escape(&event.run_id.0),
+        event.event_id.0,
+        event.hash_self,
+        serde_json::to_string(event)?,
+    ))
+}
+
+/// Header fields are printable ASCII without spaces, or "-" if empty.
+fn syslog_field(value: &str) -> String {
+    let field: String = value
+        .chars()
+        .filter(|c| c.is_ascii_graphic())
+        .take(255)
+        .collect();
+    if field.is_empty() {
+        "-".to_string()
+    } else {
+        field
+    }
+}
+
+fn sd_escape(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace(']', "\\]")
+}
+
+enum HttpFormat {
+    /// `[event, ...]`
+    Webhook,
+    /// `_bulk` NDJSON: a `create` action with `_id = hash_self`, then the document
+    Elastic { index: String },
+    /// Concatenated HEC event objects
+    Splunk {
+        index: Option<String>,
+        sourcetype: String,
+        host: String,
+    },
+}
+
+/// Webhook, Elastic bulk and Splunk HEC posts.
+pub struct HttpSink {
+    name: String,
+    batch_size: usize,
+    format: HttpFormat,
+    url: String,
+    authorization: Option<String>,
+    client: reqwest::Client,
+}
+
+impl HttpSink {
+    fn new(
+        name: String,
+        batch_size: usize,
+        format: HttpFormat,
+        url: String,
+        authorization: Option<String>,
+    ) -> Result<Self> {
+        reqwest::Url::parse(&url).with_context(|| format!("sink '{}': invalid URL", name))?;
+        Ok(Self {
+            name,
+            batch_size,
+            format,
+            url,
+            authorization,
+            client: reqwest::Client::builder().timeout(HTTP_TIMEOUT).build()?,
+        })
+    }
+
+    fn body(&self, events: &[Arc<Event>]) -> Result<(&'static str, Vec<u8>)> {
+        let mut body = Vec::new();
+        match &self.format {
+            HttpFormat::Webhook => {
+                let events: Vec<&Event> = events.iter().map(|e| e.as_ref()).collect();
+                serde_json::to_writer(&mut body, &events)?;
+                Ok(("application/json", body))
+            }
+            HttpFormat::Elastic { index } => {
+                for event in events {
+                    let action = serde_json::json!({
+                        "create": { "_index": index, "_id": event.hash_self }
+                    });
+                    let mut doc = serde_json::to_value(event.as_ref())?;
+                    doc["@timestamp"] = event.ts.to_rfc3339().into();
+                    serde_json::to_writer(&mut body, &action)?;
+                    body.push(b'\n');
+                    serde_json::to_writer(&mut body, &doc)?;
+                    body.push(b'\n');
+                }
+                Ok(("application/x-ndjson", body))
+            }
+            HttpFormat::Splunk {
+                index,
+                sourcetype,
+                host,
+            } => {
+                for event in events {
+                    let mut hec = serde_json::json!({
+                        "time": event.ts.timestamp_micros() as f64 / 1e6,
+                        "host": host,
+                        "source": "clawprint",
+                        "sourcetype": sourcetype,
+                        "event": event.as_ref(),
+                    });
+                    if let Some(index) = index {
+                        hec["index"] = index.clone().into();
+                    }
+                    serde_json::to_writer(&mut body, &hec)?;
+                    body.push(b'\n');
+                }
+                Ok(("application/json", body))
+            }
+        }
+    }
+
+    async fn post(&self, events: &[Arc<Event>]) -> Result<()> {
+        let (content_type, body) = self.body(events)?;
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, content_type)
+            .body(body);
+        if let Some(auth) = &self.authorization {
+            request = request.header(reqwest::header::AUTHORIZATION, auth);
+        }
+        let response = request.send().await?;
+        let status = response.status();
+        let text = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            bail!("{} returned {}: {}", self.url, status, truncate(&text));
+        }
+        if let HttpFormat::Elastic { .. } = self.format {
+            check_bulk_response(&text)?;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for HttpSink {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    fn deliver<'a>(&'a mut self, events: &'a [Arc<Event>]) -> BoxFuture<'a, Result<()>> {
+        Box::pin(self.post(events))
+    }
+}
+
+/// A `_bulk` response can be 200 with per-item failures. A 409 on `create`
+/// means the document was delivered before and is not an error.
+fn check_bulk_response(text: &str) -> Result<()> {
+    let response: serde_json::Value =
+        serde_json::from_str(text).context("invalid _bulk response")?;
+    if response["errors"] != serde_json::Value::Bool(true) {
+        return Ok(());
+    }
+    let failed = response["items"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|item| item.get("create").or_else(|| item.get("index")))
+        .find(|item| {
+            item["status"]
+                .as_u64()
+                .is_some_and(|s| s >= 300 && s != 409)
+        });
+    match failed {
+        Some(item) => Err(anyhow!(
+            "bulk item {} failed with {}: {}",
+            item["_id"],
+            item["status"],
+            item["error"]
+        )),
+        None => Ok(()),
+    }
+}
+
+fn truncate(text: &str) -> &str {
+    match text.char_indices().nth(200) {
+        Some((i, _)) => &text[..i],
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::subscribe::EventBus;
+    use crate::{EventId, RunId};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+    use tokio::net::TcpListener;
+
+    fn event(n: u64) -> Event {
+        Event::new(
+            RunId("daemon".into()),
+            EventId(0),
+            EventKind::AgentEvent,
+            serde_json::json!({ "n": n }),
+            None,
+        )
+    }
+
+    /// Minimal HTTP/1.1 server: answers each request with the next status
+    /// and body from `replies`, passing (path, headers, body) to the channel.
+    async fn http_listener(
+        replies: Vec<(u16, &'static str)>,
+    ) -> (
+        String,
+        tokio::sync::mpsc::UnboundedReceiver<(String, String, String)>,
+    ) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            for (status, reply) in replies {
+                let (socket, _) = listener.accept().await.unwrap();
+                let mut reader = tokio::io::BufReader::new(socket);
+                let mut head = String::new();
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).await.unwrap();
+                    if line == "\r\n" {
+                        break;
+                    }
+                    head.push_str(&line);
+                }
+                let length = head
+                    .lines()
+                    .find_map(|l| {
+                        l.to_ascii_lowercase()
+                            .strip_prefix("content-length: ")
+                            .map(|v| v.trim().parse::<usize>().unwrap())
+                    })
+                    .unwrap_or(0);
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).await.unwrap();
+                let path = head.split_whitespace().nth(1).unwrap().to_string();
+                tx.send((path, head, String::from_utf8(body).unwrap()))
+                    .unwrap();
+                let response = format!(
+                    "HTTP/1.1 {} X\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    status,
+                    reply.len(),
+                    reply
+                );
+                reader
+                    .get_mut()
+                    .write_all(response.as_bytes())
+                    .await
+                    .unwrap();
+            }
+        });
+        (url, rx)
+    }
+
+    fn parse(toml: &str) -> SinkConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_sink_resumes_from_offset_and_rotates() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("out/events.ndjson");
+        let config = parse(&format!(
+            "name = \"file\"\ntype = \"ndjson\"\npath = {:?}\nmax_bytes = 1000\nkeep = 3",
+            out
+        ));
+
+        let bus = EventBus::new();
+        let mut ledger = Ledger::open(dir.path(), 1).unwrap();
+        ledger.set_events(bus.clone());
+        // Committed before the sink ever ran: delivered by catch-up
+        ledger.append_event(event(1)).unwrap();
+        ledger.append_event(event(2)).unwrap();
+        let ledger = Arc::new(Mutex::new(ledger));
+
+        let ct = CancellationToken::new();
+        let runner = tokio::spawn(run_sink(
+            config.build().unwrap(),
+            dir.path().to_path_buf(),
+            ledger.clone(),
+            bus.stream(),
+            ct.clone(),
+        ));
+        for n in 3..=6 {
+            ledger.lock().await.append_event(event(n)).unwrap();
+        }
+        let offset = || async {
+            ledger
+                .lock()
+                .await
+                .get_meta(&offset_key("file"))
+                .unwrap()
+                .unwrap_or_default()
+        };
+        for _ in 0..50 {
+            if offset().await == "6" {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(offset().await, "6");
+        ct.cancel();
+        runner.await.unwrap().unwrap();
+
+        let read = |path: PathBuf| -> Vec<u64> {
+            std::fs::read_to_string(path)
+                .unwrap_or_default()
+                .lines()
+                .map(|l| serde_json::from_str::<Event>(l).unwrap().event_id.0)
+                .collect()
+        };
+        let rotated = |n| read(PathBuf::from(format!("{}.{}", out.display(), n)));
+        assert!(!rotated(1).is_empty(), "rotated at 1000 bytes");
+        assert_eq!(
+            [rotated(3), rotated(2), rotated(1), read(out.clone())].concat(),
+            [1, 2, 3, 4, 5, 6]
+        );
+
+        // After a restart the file tail counts as delivered even if the
+        // offset write was lost
+        assert_eq!(
+            config.build().unwrap().delivered_through().unwrap(),
+            Some(6)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_failed_write_leaves_no_partial_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let out = dir.path().join("events.ndjson");
+        let mut sink = NdjsonSink::new("file".into(), 10, out.clone(), 1, 1);
+        let events: Vec<Arc<Event>> = (1..=3)
+            .map(|n| {
+                let mut e = event(n);
+                e.event_id = EventId(n);
+                Arc::new(e)
+            })
+            .collect();
+        // Rotation fails after the batch's first line is written: a
+        // non-empty directory sits where the rotated file would go
+        let blocker = dir.path().join("events.ndjson.1");
+        std::fs::create_dir(&blocker).unwrap();
+        std::fs::write(blocker.join("x"), "").unwrap();
+        assert!(sink.deliver(&events[..2]).await.is_err());
+        assert_eq!(
+            std::fs::read(&out).unwrap(),
+            b"",
+            "the batch was rolled back"
+        );
+
+        // The retry appends the batch once, every line whole
+        std::fs::remove_dir_all(&blocker).unwrap();
+        sink.max_bytes = u64::MAX;
+        sink.deliver(&events).await.unwrap();
+        let ids: Vec<u64> = std::fs::read_to_string(&out)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<Event>(l).unwrap().event_id.0)
+            .collect();
+        assert_eq!(ids, [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_syslog_udp_and_tcp() {
+        let mut events = vec![event(1), event(2)];
+        for (i, e) in events.iter_mut().enumerate() {
+            e.event_id = EventId(i as u64 + 1);
+            e.hash_self = e.compute_hash();
+        }
+        let events: Vec<Arc<Event>> = events.into_iter().map(Arc::new).collect();
+
+        let udp = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut sink = parse(&format!(
+            "name = \"syslog\"\ntype = \"syslog\"\naddress = \"{}\"",
+            udp.local_addr().unwrap()
+        ))
+        .build()
+        .unwrap();
+        sink.deliver(&events).await.unwrap();
+        let mut buf = vec![0; 65536];
+        let n = udp.recv(&mut buf).await.unwrap();
+        let message = String::from_utf8_lossy(&buf[..n]).to_string();
+        assert!(message.starts_with("<134>1 "), "{}", message);
+        assert!(message.contains(" clawprint "), "{}", message);
+        assert!(
+            message.contains(" AGENT_EVENT [clawprint@32473 run=\"daemon\" event_id=\"1\" hash=\"")
+        );
+        let json = &message[message.find("] ").unwrap() + 2..];
+        let parsed: Event = serde_json::from_str(json).unwrap();
+        assert_eq!(parsed.hash_self, events[0].hash_self);
+
+        let tcp = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut sink = parse(&format!(
+            "name = \"syslog\"\ntype = \"syslog\"\ntransport = \"tcp\"\nfacility = 4\naddress = \"{}\"",
+            tcp.local_addr().unwrap()
+        ))
+        .build()
+        .unwrap();
+        let deliver = tokio::spawn(async move { sink.deliver(&events).await });
+        let (mut socket, _) = tcp.accept().await.unwrap();
+        deliver.await.unwrap().unwrap();
+        let mut received = Vec::new();
+        socket.read_to_end(&mut received).await.ok();
+        let received = String::from_utf8(received).unwrap();
+        // Octet counting: "<len> <message>" for each event
+        let (len, rest) = received.split_once(' ').unwrap();
+        let first = &rest[..len.parse::<usize>().unwrap()];
+        assert!(first.starts_with("<38>1 "), "{}", first);
+        assert!(rest[first.len()..].contains("event_id=\"2\""));
+    }
+
+    #[tokio::test]
+    async fn test_http_sinks() {
+        let events: Vec<Arc<Event>> = (1..=2)
+            .map(|n| {
+                let mut e = event(n);
+                e.event_id = EventId(n);
+                e.hash_self = e.compute_hash();
+                Arc::new(e)
+            })
+            .collect();
+
+        // Webhook: bearer token, JSON array, 5xx is an error for the runner to retry
+        let (url, mut rx) = http_listener(vec![(503, "busy"), (200, "ok")]).await;
+        let mut sink = parse(&format!(
+            "name = \"hook\"\ntype = \"webhook\"\nurl = \"{}/hook\"\ntoken = \"s3cret\"",
+            url
+        ))
+        .build()
+        .unwrap();
+        let err = sink.deliver(&events).await.unwrap_err();
+        assert!(err.to_string().contains("503"), "{}", err);
+        sink.deliver(&events).await.unwrap();
+        let (path, head, body) = rx.recv().await.unwrap();
+        assert_eq!(path, "/hook");
+        assert!(
+            head.to_ascii_lowercase()
+                .contains("authorization: bearer s3cret")
+        );
+        let posted: Vec<Event> = serde_json::from_str(&body).unwrap();
+        assert_eq!(posted.len(), 2);
+
+        // Elastic: create actions keyed by hash; 409 means already indexed
+        let bulk = r#"{"errors":true,"items":[{"create":{"_id":"a","status":409}},{"create":{"_id":"b","status":201}}]}"#;
+        let rejected = r#"{"errors":true,"items":[{"create":{"_id":"a","status":400,"error":{"type":"mapper_parsing_exception"}}}]}"#;
+        let (url, mut rx) = http_listener(vec![(200, bulk), (200, rejected)]).await;
+        let mut sink = parse(&format!(
+            "name = \"es\"\ntype = \"elastic\"\nurl = \"{}/\"\nindex = \"evidence\"",
+            url
+        ))
+        .build()
+        .unwrap();
+        sink.deliver(&events).await.unwrap();
+        assert!(sink.deliver(&events).await.is_err());
+        let (path, head, body) = rx.recv().await.unwrap();
+        assert_eq!(path, "/_bulk");
+        assert!(head.contains("application/x-ndjson"));
+        let lines: Vec<serde_json::Value> = body
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0]["create"]["_index"], "evidence");
+        assert_eq!(lines[0]["create"]["_id"], events[0].hash_self.as_str());
+        assert!(lines[1]["@timestamp"].is_string());
+
+        // Splunk HEC
+        let (url, mut rx) = http_listener(vec![(200, r#"{"text":"Success","code":0}"#)]).await;
+        let mut sink = parse(&format!(
+            "name = \"splunk\"\ntype = \"splunk\"\nurl = \"{}\"\ntoken = \"hec\"\nindex = \"main\"",
+            url
+        ))
+        .build()
+        .unwrap();
+        sink.deliver(&events).await.unwrap();
+        let (path, head, body) = rx.recv().await.unwrap();
+        assert_eq!(path, "/services/collector/event");
+        assert!(
+            head.to_ascii_lowercase()
+                .contains("authorization: splunk hec")
+        );
+        let first: serde_json::Value = serde_json::from_str(body.lines().next().unwrap()).unwrap();
+        assert_eq!(first["index"], "main");
+        assert_eq!(first["event"]["hash_self"], events[0].hash_self.as_str());
+
+        // Masking and duplicate names
+        let masked = sink_config_with_token().masked();
+        assert!(!toml::to_string(&masked).unwrap().contains("hec"));
+        let dup = vec![sink_config_with_token(), sink_config_with_token()];
+        assert!(build_all(&dup).is_err());
+    }
+
+    fn sink_config_with_token() -> SinkConfig {
+        parse("name = \"s\"\ntype = \"splunk\"\nurl = \"http://x\"\ntoken = \"hec\"")
+    }
+}
@@ -0,0 +1,258 @@
+//! Crash-safe spool for events not yet committed to SQLite
+//!
+//! Both `Ledger` and `RunStorage` buffer up to `batch_size` events in memory
+//! before a transaction. Every event is also appended to a newline-delimited
+//! JSON spool file next to the database and fsynced, so a crash, OOM kill or
+//! host reboot loses nothing: on the next writable open the spool is replayed
+//! into SQLite. The spool is truncated after each successful commit.
+
+use anyhow::Result;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::Event;
+
+/// When spooled events are forced to disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SpoolSync {
+    /// fsync after every event (safest, default)
+    #[default]
+    EveryEvent,
+    /// fsync at most once per interval. Appends sync when the interval has
+    /// passed, and the owner's flush tick calls [`Spool::sync_if_due`] so a
+    /// quiet tail is synced too; a crash can lose up to one interval plus
+    /// one flush tick
+    Interval(Duration),
+}
+
+impl SpoolSync {
+    /// Build from a millisecond interval, where 0 means every event.
+    pub fn from_millis(ms: u64) -> Self {
+        if ms == 0 {
+            SpoolSync::EveryEvent
+        } else {
+            SpoolSync::Interval(Duration::from_millis(ms))
+        }
+    }
+}
+
+/// Append-only NDJSON spool file.
+pub struct Spool {
+    path: PathBuf,
+    file: File,
+    sync: SpoolSync,
+    last_sync: Instant,
+    dirty: bool,
+}
+
+impl Spool {
+    /// Open (or create) a spool file for appending.
+    pub fn open(path: &Path, sync: SpoolSync) -> Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            sync,
+            last_sync: Instant::now(),
+            dirty: false,
+        })
+    }
+
+    /// Append an event, syncing according to the spool's policy.
+    pub fn append(&mut self, event: &Event) -> Result<()> {
+        let mut line = serde_json::to_vec(event)?;
+        line.push(b'\n');
+        self.file.write_all(&line)?;
+        self.dirty = true;
+
+        self.sync_if_due()
+    }
+
+    /// Sync if there are unsynced appends and the policy says it is time.
+    pub fn sync_if_due(&mut self) -> Result<()> {
+        let due = match self.sync {
+            SpoolSync::EveryEvent => true,
+            SpoolSync::Interval(every) => self.last_sync.elapsed() >= every,
+        };
+        if self.dirty && due {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Force pending writes to disk.
+    pub fn sync(&mut self) -> Result<()> {
+        if self.dirty {
+            self.file.sync_data()?;
+            self.dirty = false;
+        }
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+
+    /// Discard spooled events once they are durably committed elsewhere.
+    pub fn clear(&mut self) -> Result<()> {
+        self.file.set_len(0)?;
+        self.file.sync_data()?;
+        self.dirty = false;
+        self.last_sync = Instant::now();
+        debug!("Cleared spool {:?}", self.path);
+        Ok(())
+    }
+
+    /// Read all events from a spool file. A torn final line (from a crash
+    /// mid-write) ends the read; everything before it is returned.
+    pub fn read_events(path: &Path) -> Result<Vec<Event>> {
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let reader = BufReader::new(File::open(path)?);
+        let mut events = Vec::new();
+        for (i, line) in reader.lines().enumerate() {
+            let line = match line {
+                Ok(l) => l,
+                Err(e) => {
+                    warn!("Spool {:?} unreadable at line {}: {}", path, i + 1, e);
+                    break;
+                }
+            };
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<Event>(&line) {
+                Ok(event) => events.push(event),
+                Err(e) => {
+                    warn!("Spool {:?} truncated at line {}: {}", path, i + 1, e);
+                    break;
+                }
+            }
+        }
+        Ok(events)
+    }
+}
+
+/// Select the spooled events that still need to be committed: those past
+/// `last_committed_id` that verify and chain onto `last_hash`. Replay stops
+/// at the first event that breaks the chain.
+pub fn pending_events(
+    spooled: Vec<Event>,
+    last_committed_id: u64,
+    last_hash: Option<&str>,
+) -> Vec<Event> {
+    let mut prev = last_hash.map(|h| h.to_string());
+    let mut out = Vec::new();
+    for event in spooled {
+        if event.event_id.0 <= last_committed_id {
+            continue;
+        }
+        if !event.verify() || event.hash_prev != prev {
+            warn!(
+                "Spooled event {} does not chain onto the ledger, discarding the rest of the spool",
+                event.event_id.0
+            );
+            break;
+        }
+        prev = Some(event.hash_self.clone());
+        out.push(event);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{EventId, EventKind, RunId};
+    use tempfile::TempDir;
+
+    fn chained(n: u64) -> Vec<Event> {
+        let mut prev: Option<String> = None;
+        (1..=n)
+            .map(|i| {
+                let e = Event::new(
+                    RunId("r".into()),
+                    EventId(i),
+                    EventKind::Tick,
+                    serde_json::json!({"n": i}),
+                    prev.clone(),
+                );
+                prev = Some(e.hash_self.clone());
+                e
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_spool_round_trip_and_clear() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("spool.ndjson");
+        let mut spool = Spool::open(&path, SpoolSync::EveryEvent).unwrap();
+        for e in chained(3) {
+            spool.append(&e).unwrap();
+        }
+        let read = Spool::read_events(&path).unwrap();
+        assert_eq!(read.len(), 3);
+        assert!(read.iter().all(|e| e.verify()));
+
+        spool.clear().unwrap();
+        assert!(Spool::read_events(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_torn_last_line_is_ignored() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("spool.ndjson");
+        let mut spool = Spool::open(&path, SpoolSync::Interval(Duration::from_secs(60))).unwrap();
+        for e in chained(2) {
+            spool.append(&e).unwrap();
+        }
+        spool.sync().unwrap();
+        drop(spool);
+
+        let mut f = OpenOptions::new().append(true).open(&path).unwrap();
+        f.write_all(br#"{"run_id":"r","event_id":3,"ts":"#).unwrap();
+
+        assert_eq!(Spool::read_events(&path).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_sync_if_due_syncs_quiet_tail() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("spool.ndjson");
+        let mut spool = Spool::open(&path, SpoolSync::Interval(Duration::from_millis(20))).unwrap();
+        for e in chained(2) {
+            spool.append(&e).unwrap();
+        }
+        assert!(spool.dirty);
+
+        // Not yet due: the tail stays pending
+        spool.sync_if_due().unwrap();
+        assert!(spool.dirty);
+
+        std::thread::sleep(Duration::from_millis(30));
+        spool.sync_if_due().unwrap();
+        assert!(!spool.dirty);
+    }
+
+    #[test]
+    fn test_pending_events_skips_committed_and_stops_at_break() {
+        let events = chained(4);
+        let committed_hash = events[1].hash_self.clone();
+
+        let pending = pending_events(events.clone(), 2, Some(&committed_hash));
+        assert_eq!(pending.len(), 2);
+        assert_eq!(pending[0].event_id.0, 3);
+
+        // Wrong anchor: nothing chains on
+        assert!(pending_events(events.clone(), 2, Some("bogus")).is_empty());
+
+        // Tampered middle event stops replay
+        let mut tampered = events;
+        tampered[2].payload = serde_json::json!({"n": 999});
+        let pending = pending_events(tampered, 2, Some(&committed_hash));
+        assert!(pending.is_empty());
+    }
+}
@@ -6,13 +6,75 @@
 use anyhow::{Result, anyhow};
 use chrono::{DateTime, Utc};
 use rusqlite::{Connection, OptionalExtension, params};
+use sha2::{Digest, Sha256};
 use std::collections::HashMap;
 use std::fs;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use tracing::{debug, info, warn};
 
-use crate::{Event, EventId, EventKind, RunId, RunMeta};
+use crate::artifact::{self, ArtifactReport};
+use crate::redact::Redactor;
+use crate::spool::{self, Spool, SpoolSync};
+use crate::subscribe::EventBus;
+use crate::{Event, EventId, EventKind, RunId, RunMeta, RunStatus};
+
+/// Redaction reports of stored artifacts, one row per store
+const ARTIFACTS_TABLE: &str = "CREATE TABLE IF NOT EXISTS artifacts (
+    hash        TEXT NOT NULL,
+    name        TEXT,
+    format      TEXT NOT NULL,
+    unredacted  INTEGER NOT NULL,
+    detections  TEXT NOT NULL,
+    stored_at   TEXT NOT NULL
+)";
+
+/// An artifact as stored by [`RunStorage::store_artifact_from`]
+#[derive(Debug, Clone)]
+pub struct StoredArtifact {
+    /// SHA-256 of the stored content
+    pub hash: String,
+    /// Stored size in bytes, before compression
+    pub size: u64,
+    /// What redaction did; `None` if no redactor is set
+    pub report: Option<ArtifactReport>,
+}
+
+/// Suffix of an artifact ref whose content was stored without redaction
+pub const UNREDACTED_SUFFIX: &str = "#unredacted";
+
+impl StoredArtifact {
+    /// The entry for an event's `artifact_refs`: the hash, plus
+    /// [`UNREDACTED_SUFFIX`] if redaction had to skip the content, so the
+    /// flag is covered by the event hash.
+    pub fn artifact_ref(&self) -> String {
+        match &self.report {
+            Some(report) if report.unredacted => format!("{}{}", self.hash, UNREDACTED_SUFFIX),
+            _ => self.hash.clone(),
+        }
+    }
+}
+
+/// Hashes and counts what passes through to the compressor
+struct HashingWriter<W: Write> {
+    inner: W,
+    hasher: Sha256,
+    len: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.len += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
 
 /// Storage manager for a single run
 pub struct RunStorage {
@@ -23,11 +85,27 @@ pub struct RunStorage {
     event_count: u64,
     batch_buffer: Vec<Event>,
     batch_size: usize,
+    spool: Option<Spool>,
+    events: Option<EventBus>,
+    redactor: Option<Arc<Redactor>>,
 }
 
 impl RunStorage {
     /// Create new storage for a run
     pub fn new(run_id: RunId, base_path: &Path, batch_size: usize) -> Result<Self> {
+        Self::new_with_spool(run_id, base_path, batch_size, SpoolSync::default())
+    }
+
+    /// Create new storage for a run, syncing its crash spool
+    /// (`runs/<id>/spool.ndjson`) with the given policy. Events an earlier
+    /// process left in the run's database and spool are kept: the spool is
+    /// replayed and new events chain on.
+    pub fn new_with_spool(
+        run_id: RunId,
+        base_path: &Path,
+        batch_size: usize,
+        sync: SpoolSync,
+    ) -> Result<Self> {
         let run_path = base_path.join("runs").join(&run_id.0);
         fs::create_dir_all(&run_path)?;
         fs::create_dir_all(run_path.join("artifacts"))?;
@@ -63,18 +141,26 @@ impl RunStorage {
 
         info!("Created storage for run {} at {:?}", run_id.0, run_path);
 
-        Ok(Self {
+        let (last_hash, event_count) = chain_tip(&db)?;
+        let mut storage = Self {
             run_id,
             base_path: run_path,
             db,
-            last_hash: None,
-            event_count: 0,
+            last_hash,
+            event_count,
             batch_buffer: Vec::with_capacity(batch_size),
             batch_size,
-        })
+            spool: None,
+            events: None,
+            redactor: None,
+        };
+        storage.replay_spool(sync)?;
+        Ok(storage)
     }
 
-    /// Open existing run storage
+    /// Open existing run storage for reading. The crash spool is left
+    /// alone, since its recorder may still be running; to append to a run,
+    /// reopen it with [`RunStorage::recover`].
     pub fn open(run_id: RunId, base_path: &Path) -> Result<Self> {
         let run_path = base_path.join("runs").join(&run_id.0);
         let db_path = run_path.join("ledger.sqlite");
@@ -84,17 +170,7 @@ impl RunStorage {
         }
 
         let db = Connection::open(&db_path)?;
-
-        // Get last hash for chain continuation
-        let last_hash: Option<String> = db
-            .query_row(
-                "SELECT hash_self FROM events ORDER BY event_id DESC LIMIT 1",
-                [],
-                |row| row.get(0),
-            )
-            .optional()?;
-
-        let event_count: u64 = db.query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))?;
+        let (last_hash, event_count) = chain_tip(&db)?;
 
         info!(
             "Opened storage for run {} ({} events)",
@@ -109,9 +185,55 @@ impl RunStorage {
             event_count,
             batch_buffer: Vec::with_capacity(100),
             batch_size: 100,
+            spool: None,
+            events: None,
+            redactor: None,
         })
     }
 
+    /// Reopen an existing run for writing, replaying any events a crashed
+    /// writer left in its spool into SQLite first
+    pub fn recover(run_id: RunId, base_path: &Path) -> Result<Self> {
+        let mut storage = Self::open(run_id, base_path)?;
+        storage.replay_spool(SpoolSync::default())?;
+        Ok(storage)
+    }
+
+    /// Commit the spooled events that chain onto the database, then start
+    /// a fresh spool with `sync`.
+    fn replay_spool(&mut self, sync: SpoolSync) -> Result<()> {
+        let spool_path = self.base_path.join("spool.ndjson");
+        let recovered = spool::pending_events(
+            Spool::read_events(&spool_path)?,
+            self.event_count,
+            self.last_hash.as_deref(),
+        );
+        if !recovered.is_empty() {
+            warn!(
+                "Recovering {} unflushed events for run {}",
+                recovered.len(),
+                self.run_id.0
+            );
+            self.batch_buffer.extend(recovered);
+            self.flush()?;
+        }
+
+        let mut spool = Spool::open(&spool_path, sync)?;
+        spool.clear()?;
+        self.spool = Some(spool);
+        Ok(())
+    }
+
+    /// Publish each event to `events` once its batch is committed.
+    pub fn set_events(&mut self, events: EventBus) {
+        self.events = Some(events);
+    }
+
+    /// Redact artifacts with `redactor` before they are stored.
+    pub fn set_redactor(&mut self, redactor: Arc<Redactor>) {
+        self.redactor = Some(redactor);
+    }
+
     /// Write event to storage, chaining it to the previous event's hash
     pub fn write_event(&mut self, mut event: Event) -> Result<()> {
         // Determine the previous hash: from the last buffered event, or from storage
@@ -125,6 +247,9 @@ impl RunStorage {
         event.hash_prev = prev_hash;
         event.hash_self = event.compute_hash();
 
+        if let Some(spool) = self.spool.as_mut() {
+            spool.append(&event)?;
+        }
         self.batch_buffer.push(event);
 
         if self.batch_buffer.len() >= self.batch_size {
@@ -134,6 +259,15 @@ impl RunStorage {
         Ok(())
     }
 
+    /// Sync the crash spool if its interval has passed. Call on the flush
+    /// tick, so events stay durable when no further appends arrive.
+    pub fn sync_spool(&mut self) -> Result<()> {
+        match self.spool.as_mut() {
+            Some(spool) => spool.sync_if_due(),
+            None => Ok(()),
+        }
+    }
+
     /// Flush batch to database
     pub fn flush(&mut self) -> Result<()> {
         if self.batch_buffer.is_empty() {
@@ -170,7 +304,13 @@ impl RunStorage {
 
         let flushed = self.batch_buffer.len();
         tx.commit()?;
+        if let Some(events) = &self.events {
+            events.publish(&self.batch_buffer);
+        }
         self.batch_buffer.clear();
+        if let Some(spool) = self.spool.as_mut() {
+            spool.clear()?;
+        }
 
         debug!(
             "Flushed {} events to storage (total: {})",
@@ -180,48 +320,118 @@ impl RunStorage {
         Ok(())
     }
 
-    /// Store artifact (compressed with zstd, content-addressed by SHA-256)
+    /// Store artifact (compressed with zstd, content-addressed by SHA-256).
+    /// Returns its [artifact ref](StoredArtifact::artifact_ref).
     pub fn store_artifact(&self, data: &[u8]) -> Result<String> {
-        if data.is_empty() {
-            return Err(anyhow!("Cannot store empty artifact"));
-        }
+        Ok(self.store_artifact_from(None, data)?.artifact_ref())
+    }
 
-        // Compute hash
-        use sha2::{Digest, Sha256};
-        let hash = {
-            let mut hasher = Sha256::new();
-            hasher.update(data);
-            hex::encode(hasher.finalize())
+    /// Stream an artifact into storage, redacting it on the way if a
+    /// redactor is set. `name` is the file it came from, if any, and picks
+    /// the redaction format (see [`crate::artifact`]). The hash is over the
+    /// stored, redacted content. The redaction report is kept in the run's
+    /// database (see [`artifact_report`](Self::artifact_report)).
+    pub fn store_artifact_from(
+        &self,
+        name: Option<&str>,
+        reader: impl Read,
+    ) -> Result<StoredArtifact> {
+        let artifacts = self.base_path.join("artifacts");
+        let temp = tempfile::NamedTempFile::new_in(&artifacts)?;
+        let mut writer = HashingWriter {
+            inner: zstd::Encoder::new(temp.as_file(), 3)?,
+            hasher: Sha256::new(),
+            len: 0,
         };
+        let report = match &self.redactor {
+            Some(redactor) => Some(artifact::redact_artifact(
+                redactor,
+                name,
+                reader,
+                &mut writer,
+            )?),
+            None => {
+                let mut reader = reader;
+                std::io::copy(&mut reader, &mut writer)?;
+                None
+            }
+        };
+        let HashingWriter { inner, hasher, len } = writer;
+        inner.finish()?;
+        if len == 0 {
+            return Err(anyhow!("Cannot store empty artifact"));
+        }
+        let hash = hex::encode(hasher.finalize());
 
-        // Check if already exists (hash is always 64 hex chars)
+        // Hash is always 64 hex chars
         let prefix = &hash[..2];
-        let artifact_dir = self.base_path.join("artifacts").join(prefix);
+        let artifact_dir = artifacts.join(prefix);
         let artifact_path = artifact_dir.join(format!("{}.zst", &hash));
 
         if artifact_path.exists() {
             debug!("Artifact {} already exists", hash);
-            return Ok(hash);
+        } else {
+            fs::create_dir_all(&artifact_dir)?;
+            temp.as_file().sync_all()?;
+            temp.persist(&artifact_path)?;
+            debug!("Stored artifact {} ({} bytes)", hash, len);
         }
 
-        // Compress and store
-        fs::create_dir_all(&artifact_dir)?;
-        let compressed = zstd::encode_all(data, 3)?;
-        let mut file = fs::File::create(&artifact_path)?;
-        file.write_all(&compressed)?;
+        if let Some(report) = &report {
+            self.db.execute_batch(ARTIFACTS_TABLE)?;
+            self.db.execute(
+                "INSERT INTO artifacts (hash, name, format, unredacted, detections, stored_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    hash,
+                    name,
+                    serde_json::to_string(&report.format)?.trim_matches('"'),
+                    report.unredacted,
+                    serde_json::to_string(&report.detections)?,
+                    Utc::now().to_rfc3339(),
+                ],
+            )?;
+        }
 
-        debug!(
-            "Stored artifact {} ({} bytes -> {} bytes)",
+        Ok(StoredArtifact {
             hash,
-            data.len(),
-            compressed.len()
-        );
+            size: len,
+            report,
+        })
+    }
 
-        Ok(hash)
+    /// What redaction did to artifact `hash` when it was last stored, if a
+    /// redactor was set.
+    pub fn artifact_report(&self, hash: &str) -> Result<Option<ArtifactReport>> {
+        self.db.execute_batch(ARTIFACTS_TABLE)?;
+        let hash = hash.trim_end_matches(UNREDACTED_SUFFIX);
+        self.db
+            .query_row(
+                "SELECT format, unredacted, detections FROM artifacts
+                 WHERE hash = ?1 ORDER BY rowid DESC LIMIT 1",
+                [hash],
+                |row| {
+                    Ok((
+                        row.get::<_, String>(0)?,
+                        row.get::<_, bool>(1)?,
+                        row.get::<_, String>(2)?,
+                    ))
+                },
+            )
+            .optional()?
+            .map(|(format, unredacted, detections)| {
+                Ok(ArtifactReport {
+                    format: serde_json::from_value(serde_json::Value::String(format))?,
+                    detections: serde_json::from_str(&detections)?,
+                    unredacted,
+                })
+            })
+            .transpose()
     }
 
-    /// Retrieve artifact and verify its hash
+    /// Retrieve artifact (by hash or artifact ref) and verify its hash
     pub fn get_artifact(&self, hash: &str) -> Result<Vec<u8>> {
+        let hash = hash.trim_end_matches(UNREDACTED_SUFFIX);
         if hash.len() < 2 {
             return Err(anyhow!("Invalid artifact hash: too short"));
         }
@@ -241,7 +451,6 @@ impl RunStorage {
         let data = zstd::decode_all(&compressed[..])?;
 
         // Verify integrity: recompute hash and compare
-        use sha2::{Digest, Sha256};
         let actual_hash = {
             let mut hasher = Sha256::new();
             hasher.update(&data);
@@ -276,6 +485,45 @@ impl RunStorage {
         Ok(events)
     }
 
+    /// Page through events in a time range, `limit` at a time after
+    /// `after`. The range is applied in SQL against the `ts` index.
+    pub fn events_in_range_after(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+        after: u64,
+        limit: usize,
+    ) -> Result<Vec<Event>> {
+        let mut where_clauses = vec!["event_id > ?".to_string()];
+        let mut param_values: Vec<Box<dyn rusqlite::types::ToSql>> = vec![Box::new(after as i64)];
+        if let Some(s) = since {
+            where_clauses.push("ts >= ?".to_string());
+            param_values.push(Box::new(s.to_rfc3339()));
+        }
+        if let Some(u) = until {
+            where_clauses.push("ts <= ?".to_string());
+            param_values.push(Box::new(u.to_rfc3339()));
+        }
+        param_values.push(Box::new(limit as i64));
+
+        let sql = format!(
+            "SELECT event_id, ts, kind, span_id, parent_span_id, actor,
+                    payload, artifact_refs, hash_prev, hash_self
+             FROM events WHERE {} ORDER BY event_id LIMIT ?",
+            where_clauses.join(" AND ")
+        );
+        let params_ref: Vec<&dyn rusqlite::types::ToSql> =
+            param_values.iter().map(|p| p.as_ref()).collect();
+        let run_id = self.run_id.clone();
+        let mut stmt = self.db.prepare(&sql)?;
+        let events = stmt
+            .query_map(params_ref.as_slice(), |row| {
+                Self::row_to_event(row, &run_id)
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(events)
+    }
+
     /// Verify hash chain integrity
     pub fn verify_chain(&self) -> Result<bool> {
         let events = self.load_events(None)?;
@@ -292,6 +540,40 @@ impl RunStorage {
         self.event_count
     }
 
+    /// Get a single event by ID
+    pub fn get_event(&self, event_id: u64) -> Result<Option<Event>> {
+        let run_id = self.run_id.clone();
+        self.db
+            .query_row(
+                "SELECT event_id, ts, kind, span_id, parent_span_id, actor,
+                        payload, artifact_refs, hash_prev, hash_self
+                 FROM events WHERE event_id = ?1",
+                [event_id as i64],
+                |row| Self::row_to_event(row, &run_id),
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
+    /// Most recent event, including any still in the unflushed batch buffer
+    pub fn last_event(&self) -> Result<Option<Event>> {
+        if let Some(last) = self.batch_buffer.last() {
+            return Ok(Some(last.clone()));
+        }
+
+        let run_id = self.run_id.clone();
+        self.db
+            .query_row(
+                "SELECT event_id, ts, kind, span_id, parent_span_id, actor,
+                        payload, artifact_refs, hash_prev, hash_self
+                 FROM events ORDER BY event_id DESC LIMIT 1",
+                [],
+                |row| Self::row_to_event(row, &run_id),
+            )
+            .optional()
+            .map_err(Into::into)
+    }
+
     /// Finalize run and write meta.json
     pub fn finalize(&mut self, meta: &RunMeta) -> Result<()> {
         self.flush()?;
@@ -300,6 +582,11 @@ impl RunStorage {
         let meta_json = serde_json::to_string_pretty(meta)?;
         fs::write(&meta_path, meta_json)?;
 
+        // Everything is committed; the sealed case file needs no spool
+        if self.spool.take().is_some() {
+            let _ = fs::remove_file(self.base_path.join("spool.ndjson"));
+        }
+
         info!("Finalized run {} at {:?}", self.run_id.0, meta_path);
 
         Ok(())
@@ -437,7 +724,7 @@ impl RunStorage {
     }
 
     /// Parse a single row into an Event (shared by load_events and load_events_filtered)
-    fn row_to_event(row: &rusqlite::Row, run_id: &RunId) -> rusqlite::Result<Event> {
+    pub(crate) fn row_to_event(row: &rusqlite::Row, run_id: &RunId) -> rusqlite::Result<Event> {
         let event_id: i64 = row.get(0)?;
         let ts_str: String = row.get(1)?;
         let kind_str: String = row.get(2)?;
@@ -459,18 +746,7 @@ impl RunStorage {
             })?
             .with_timezone(&Utc);
 
-        let kind = match kind_str.as_str() {
-            "RUN_START" => EventKind::RunStart,
-            "RUN_END" => EventKind::RunEnd,
-            "AGENT_EVENT" => EventKind::AgentEvent,
-            "TOOL_CALL" => EventKind::ToolCall,
-            "TOOL_RESULT" => EventKind::ToolResult,
-            "OUTPUT_CHUNK" => EventKind::OutputChunk,
-            "PRESENCE" => EventKind::Presence,
-            "TICK" => EventKind::Tick,
-            "SHUTDOWN" => EventKind::Shutdown,
-            _ => EventKind::Custom,
-        };
+        let kind = EventKind::from_name(&kind_str);
 
         let payload: serde_json::Value = serde_json::from_str(&payload_str).map_err(|e| {
             rusqlite::Error::FromSqlConversionFailure(6, rusqlite::types::Type::Text, Box::new(e))
@@ -495,6 +771,19 @@ impl RunStorage {
     }
 }
 
+/// Hash of the last stored event and the number of events, to chain on from
+fn chain_tip(db: &Connection) -> Result<(Option<String>, u64)> {
+    let last_hash: Option<String> = db
+        .query_row(
+            "SELECT hash_self FROM events ORDER BY event_id DESC LIMIT 1",
+            [],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let event_count: u64 = db.query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))?;
+    Ok((last_hash, event_count))
+}
+
 /// List all recorded runs in a directory
 pub fn list_runs(base_path: &Path) -> Result<Vec<(RunId, RunMeta)>> {
     let runs_dir = base_path.join("runs");
@@ -517,11 +806,64 @@ pub fn list_runs(base_path: &Path) -> Result<Vec<(RunId, RunMeta)>> {
     }
 
     // Sort by start time descending
-    runs.sort_by(|a, b| b.1.started_at.cmp(&a.1.started_at));
+    runs.sort_by_key(|r| std::cmp::Reverse(r.1.started_at));
 
     Ok(runs)
 }
 
+/// Find runs that were never finalized (a ledger but no meta.json),
+/// typically because the recorder crashed or was killed.
+pub fn orphaned_runs(base_path: &Path) -> Result<Vec<RunId>> {
+    let runs_dir = base_path.join("runs");
+    if !runs_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut orphans = Vec::new();
+    for entry in fs::read_dir(&runs_dir)? {
+        let path = entry?.path();
+        if path.join("ledger.sqlite").exists()
+            && !path.join("meta.json").exists()
+            && let Some(name) = path.file_name().and_then(|n| n.to_str())
+        {
+            orphans.push(RunId(name.to_string()));
+        }
+    }
+    orphans.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(orphans)
+}
+
+/// Finalize an orphaned run: replay its spool, then write meta.json with
+/// `incomplete` status and the root hash of what was actually recorded.
+/// No events are added, so the chain is exactly as the recorder left it.
+pub fn recover_run(run_id: RunId, base_path: &Path) -> Result<RunMeta> {
+    let mut storage = RunStorage::recover(run_id.clone(), base_path)?;
+
+    let first = storage.load_events(Some(1))?.into_iter().next();
+    let last = storage.last_event()?;
+    let gateway_url = first
+        .as_ref()
+        .filter(|e| e.kind == EventKind::RunStart)
+        .and_then(|e| e.payload.get("gateway_url"))
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let meta = RunMeta {
+        run_id,
+        started_at: first.map(|e| e.ts).unwrap_or_else(Utc::now),
+        ended_at: last.map(|e| e.ts),
+        event_count: storage.event_count(),
+        root_hash: storage.root_hash().unwrap_or_default(),
+        gateway_url,
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        status: RunStatus::Incomplete,
+        ledger_range: None,
+    };
+    storage.finalize(&meta)?;
+    Ok(meta)
+}
+
 /// List all recorded runs with storage size
 /// Resolve a (possibly short) run ID prefix to a full run ID.
 /// Scans the `runs/` directory for directories starting with the given prefix.
@@ -587,7 +929,7 @@ pub fn list_runs_with_stats(base_path: &Path) -> Result<Vec<(RunId, RunMeta, u64
         }
     }
 
-    runs.sort_by(|a, b| b.1.started_at.cmp(&a.1.started_at));
+    runs.sort_by_key(|r| std::cmp::Reverse(r.1.started_at));
     Ok(runs)
 }
 
@@ -795,6 +1137,73 @@ mod tests {
         assert!(storage.verify_chain().unwrap());
     }
 
+    /// A crashed run is found, its spool replayed, and it is sealed as incomplete
+    #[test]
+    fn test_recover_orphaned_run() {
+        let temp_dir = TempDir::new().unwrap();
+        let run_id = RunId::new();
+        {
+            let mut storage = RunStorage::new(run_id.clone(), temp_dir.path(), 2).unwrap();
+            for i in 1..=3 {
+                let event = crate::Event::new(
+                    run_id.clone(),
+                    crate::EventId(i),
+                    EventKind::AgentEvent,
+                    serde_json::json!({"n": i}),
+                    None,
+                );
+                storage.write_event(event).unwrap();
+            }
+            // Event 3 is only in the spool when the process dies
+        }
+
+        assert_eq!(
+            orphaned_runs(temp_dir.path()).unwrap(),
+            vec![run_id.clone()]
+        );
+        assert!(list_runs(temp_dir.path()).unwrap().is_empty());
+
+        let meta = recover_run(run_id.clone(), temp_dir.path()).unwrap();
+        assert_eq!(meta.status, RunStatus::Incomplete);
+        assert_eq!(meta.event_count, 3);
+
+        let storage = RunStorage::open(run_id, temp_dir.path()).unwrap();
+        assert!(storage.verify_chain().unwrap());
+        assert_eq!(storage.root_hash().unwrap(), meta.root_hash);
+        assert!(orphaned_runs(temp_dir.path()).unwrap().is_empty());
+        assert_eq!(list_runs(temp_dir.path()).unwrap().len(), 1);
+    }
+
+    /// Reusing a crashed run's directory replays its spool instead of dropping it
+    #[test]
+    fn test_new_replays_existing_spool() {
+        let temp_dir = TempDir::new().unwrap();
+        let run_id = RunId::new();
+        let event = |i| {
+            crate::Event::new(
+                run_id.clone(),
+                crate::EventId(i),
+                EventKind::AgentEvent,
+                serde_json::json!({"n": i}),
+                None,
+            )
+        };
+        {
+            let mut storage = RunStorage::new(run_id.clone(), temp_dir.path(), 2).unwrap();
+            for i in 1..=3 {
+                storage.write_event(event(i)).unwrap();
+            }
+            // Event 3 is only in the spool when the process dies
+        }
+
+        let mut storage = RunStorage::new(run_id.clone(), temp_dir.path(), 2).unwrap();
+        assert_eq!(storage.event_count(), 3);
+        storage.write_event(event(4)).unwrap();
+        storage.flush().unwrap();
+        assert_eq!(storage.load_events(None).unwrap().len(), 4);
+        assert!(storage.verify_chain().unwrap());
+    }
+
     /// Verify artifact integrity check catches corruption
     #[test]
     fn test_artifact_integrity_check() {
@@ -831,6 +1240,35 @@ mod tests {
         );
     }
 
+    /// last_event sees buffered events before they are flushed
+    #[test]
+    fn test_last_event_includes_buffer() {
+        let temp_dir = TempDir::new().unwrap();
+        let run_id = RunId::new();
+        let mut storage = RunStorage::new(run_id.clone(), temp_dir.path(), 100).unwrap();
+        assert!(storage.last_event().unwrap().is_none());
+
+        for (i, kind) in [EventKind::RunStart, EventKind::RunEnd].iter().enumerate() {
+            let event = crate::Event::new(
+                run_id.clone(),
+                crate::EventId((i + 1) as u64),
+                *kind,
+                serde_json::json!({}),
+                None,
+            );
+            storage.write_event(event).unwrap();
+        }
+
+        let last = storage.last_event().unwrap().unwrap();
+        assert_eq!(last.kind, EventKind::RunEnd);
+        assert_eq!(storage.event_count(), 0);
+
+        storage.flush().unwrap();
+        let last = storage.last_event().unwrap().unwrap();
+        assert_eq!(last.event_id.0, 2);
+        assert_eq!(last.kind, EventKind::RunEnd);
+    }
+
     /// Verify empty artifact is rejected
     #[test]
     fn test_empty_artifact_rejected() {
@@ -854,4 +1292,57 @@ mod tests {
         let hash2 = storage.store_artifact(data).unwrap();
         assert_eq!(hash1, hash2);
     }
+
+    #[test]
+    fn test_artifact_redacted_before_storage() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = RunStorage::new(RunId::new(), temp_dir.path(), 10).unwrap();
+        storage.set_redactor(Arc::new(Redactor::builtin()));
+
+        let stored = storage
+            .store_artifact_from(Some(".env"), &b"API_KEY=abc123\nDEBUG=1\n"[..])
+            .unwrap();
+        let data = storage.get_artifact(&stored.hash).unwrap();
+        assert_eq!(data, b"API_KEY=[REDACTED]\nDEBUG=1\n");
+        let report = stored.report.unwrap();
+        assert_eq!(report.detections.len(), 1);
+        assert!(!report.unredacted);
+
+        // Binary is kept as-is and flagged
+        let stored = storage
+            .store_artifact_from(None, &[0u8, 1, 2, 3][..])
+            .unwrap();
+        assert!(stored.report.as_ref().unwrap().unredacted);
+        assert_eq!(storage.get_artifact(&stored.hash).unwrap(), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_artifact_report_is_recorded() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut storage = RunStorage::new(RunId::new(), temp_dir.path(), 10).unwrap();
+        storage.set_redactor(Arc::new(Redactor::builtin()));
+
+        let binary = storage
+            .store_artifact_from(Some("dump.bin"), &[0u8, 1, 2, 3][..])
+            .unwrap();
+        let artifact_ref = binary.artifact_ref();
+        assert_eq!(artifact_ref, format!("{}#unredacted", binary.hash));
+        assert_eq!(storage.get_artifact(&artifact_ref).unwrap(), [0, 1, 2, 3]);
+        let report = storage.artifact_report(&artifact_ref).unwrap().unwrap();
+        assert_eq!(report.format, artifact::ArtifactFormat::Binary);
+        assert!(report.unredacted);
+
+        let text = storage
+            .store_artifact_from(Some(".env"), &b"API_KEY=abc123\n"[..])
+            .unwrap();
+        assert_eq!(text.artifact_ref(), text.hash);
+        let report = storage.artifact_report(&text.hash).unwrap().unwrap();
+        assert_eq!(report.detections.len(), 1);
+        assert!(!report.unredacted);
+
+        // Without a redactor there is no report
+        let plain = RunStorage::new(RunId::new(), temp_dir.path(), 10).unwrap();
+        let hash = plain.store_artifact(b"hello").unwrap();
+        assert!(plain.artifact_report(&hash).unwrap().is_none());
+    }
 }
@@ -0,0 +1,249 @@
+//! Event subscribers for embedding Clawprint as a library
+//!
+//! An [`EventBus`] handed to the daemon ([`crate::daemon::DaemonOptions`]) or
+//! to a [`crate::record::RecordingSession`] publishes every event once it is
+//! committed to SQLite, with its final `event_id`, `hash_prev` and
+//! `hash_self`. Consumers either register a [`Subscriber`], called inline
+//! from the writer, or take an [`EventStream`] and read it from their own
+//! task. Alerting, live dashboards and custom sinks build on this.
+
+use futures::Stream;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::Event;
+
+/// Events buffered per stream before a slow reader starts missing them
+pub const STREAM_CAPACITY: usize = 4096;
+
+/// Called with each committed event. Runs on the ledger writer between
+/// batches, so implementations must return quickly and hand slow work
+/// (network calls, disk I/O) to their own task.
+pub trait Subscriber: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+impl<F> Subscriber for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn on_event(&self, event: &Event) {
+        self(event)
+    }
+}
+
+/// Cloneable fan-out point for committed events. Publishing costs nothing
+/// while nobody subscribes.
+#[derive(Clone)]
+pub struct EventBus {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    tx: broadcast::Sender<Arc<Event>>,
+    subscribers: RwLock<Vec<Arc<dyn Subscriber>>>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(STREAM_CAPACITY);
+        Self {
+            inner: Arc::new(Inner {
+                tx,
+                subscribers: RwLock::new(Vec::new()),
+            }),
+        }
+    }
+
+    /// Register a callback for every event committed from now on.
+    pub fn add_subscriber(&self, subscriber: Arc<dyn Subscriber>) {
+        self.inner
+            .subscribers
+            .write()
+            .expect("subscriber list poisoned")
+            .push(subscriber);
+    }
+
+    /// Receive every event committed from now on.
+    pub fn stream(&self) -> EventStream {
+        EventStream {
+            rx: self.inner.tx.subscribe(),
+            missed: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// True if anything would receive a published event.
+    pub fn has_subscribers(&self) -> bool {
+        self.inner.tx.receiver_count() > 0
+            || !self
+                .inner
+                .subscribers
+                .read()
+                .expect("subscriber list poisoned")
+                .is_empty()
+    }
+
+    /// Deliver a committed batch, in chain order.
+    pub(crate) fn publish(&self, events: &[Event]) {
+        if !self.has_subscribers() {
+            return;
+        }
+        let subscribers = self
+            .inner
+            .subscribers
+            .read()
+            .expect("subscriber list poisoned")
+            .clone();
+        for event in events {
+            for subscriber in &subscribers {
+                subscriber.on_event(event);
+            }
+            if self.inner.tx.receiver_count() > 0 {
+                // Fails only if every stream was dropped meanwhile
+                let _ = self.inner.tx.send(Arc::new(event.clone()));
+            }
+        }
+    }
+}
+
+/// Committed events in chain order. A reader that falls more than
+/// [`STREAM_CAPACITY`] events behind skips the oldest ones; [`missed`]
+/// counts them.
+///
+/// [`missed`]: EventStream::missed
+pub struct EventStream {
+    rx: broadcast::Receiver<Arc<Event>>,
+    missed: Arc<AtomicU64>,
+}
+
+impl EventStream {
+    /// Next committed event, or `None` once the bus and all its writers are gone.
+    pub async fn recv(&mut self) -> Option<Arc<Event>> {
+        loop {
+            match self.rx.recv().await {
+                Ok(event) => return Some(event),
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!("Event stream fell behind; skipped {} events", n);
+                    self.missed.fetch_add(n, Ordering::Relaxed);
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    }
+
+    /// Events skipped because this reader fell behind.
+    pub fn missed(&self) -> u64 {
+        self.missed.load(Ordering::Relaxed)
+    }
+
+    /// Adapt into a [`Stream`] for use with `StreamExt` combinators.
+    pub fn into_stream(self) -> impl Stream<Item = Arc<Event>> + Send {
+        futures::stream::unfold(self, |mut stream| async move {
+            stream.recv().await.map(|event| (event, stream))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::Ledger;
+    use crate::{EventId, EventKind, RunId};
+    use futures::StreamExt;
+    use std::sync::Mutex;
+
+    fn event(n: u64) -> Event {
+        Event::new(
+            RunId("daemon".into()),
+            EventId(0),
+            EventKind::AgentEvent,
+            serde_json::json!({ "n": n }),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_subscriber_sees_only_committed_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ledger = Ledger::open(dir.path(), 2).unwrap();
+        let bus = EventBus::new();
+        ledger.set_events(bus.clone());
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = seen.clone();
+        bus.add_subscriber(Arc::new(move |event: &Event| {
+            sink.lock().unwrap().push(event.event_id.0);
+        }));
+
+        ledger.append_event(event(1)).unwrap();
+        assert!(seen.lock().unwrap().is_empty(), "not committed yet");
+        ledger.append_event(event(2)).unwrap();
+        ledger.append_event(event(3)).unwrap();
+        ledger.flush().unwrap();
+        assert_eq!(*seen.lock().unwrap(), [1, 2, 3]);
+    }
+
+    #[tokio::test]
+    async fn test_stream_yields_chained_events() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut ledger = Ledger::open(dir.path(), 10).unwrap();
+        let bus = EventBus::new();
+        ledger.set_events(bus.clone());
+        let mut stream = bus.stream().into_stream().boxed();
+
+        ledger.append_event(event(1)).unwrap();
+        ledger.append_event(event(2)).unwrap();
+        ledger.flush().unwrap();
+
+        let first = stream.next().await.unwrap();
+        let second = stream.next().await.unwrap();
+        assert_eq!(first.event_id, EventId(1));
+        assert_eq!(second.hash_prev.as_deref(), Some(first.hash_self.as_str()));
+        assert_eq!(second.hash_self, second.compute_hash());
+    }
+
+    #[test]
+    fn test_has_subscribers() {
+        let bus = EventBus::new();
+        assert!(!bus.has_subscribers());
+
+        let stream = bus.stream();
+        assert!(bus.has_subscribers());
+        drop(stream);
+        assert!(!bus.has_subscribers());
+
+        bus.add_subscriber(Arc::new(|_: &Event| {}));
+        assert!(bus.has_subscribers());
+    }
+
+    #[tokio::test]
+    async fn test_lagging_stream_counts_missed_events() {
+        let bus = EventBus::new();
+        let mut stream = bus.stream();
+        let events: Vec<Event> = (1..=STREAM_CAPACITY as u64 + 10).map(event).collect();
+        bus.publish(&events);
+
+        let next = stream.recv().await.unwrap();
+        assert_eq!(next.payload["n"], 11);
+        assert_eq!(stream.missed(), 10);
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_when_bus_dropped() {
+        let bus = EventBus::new();
+        let mut stream = bus.stream();
+        bus.publish(&[event(1)]);
+        drop(bus);
+
+        assert!(stream.recv().await.is_some());
+        assert!(stream.recv().await.is_none());
+    }
+}
@@ -0,0 +1,196 @@
+//! systemd service notifications (`sd_notify`)
+//!
+//! Under a `Type=notify` unit, systemd waits for `READY=1` before it treats
+//! the service as started and shows the latest `STATUS=` line in
+//! `systemctl status`. With `WatchdogSec=` set, it restarts the service when
+//! the `WATCHDOG=1` pings stop. The daemon pings from its recording loop, so
+//! a wedged recorder gets restarted even though the process is still alive.
+//!
+//! Everything here is a no-op unless `NOTIFY_SOCKET` is set.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+/// Minimum time between heartbeats (STATUS updates and watchdog pings)
+const BEAT_EVERY: Duration = Duration::from_secs(1);
+
+#[cfg(unix)]
+type Target = (
+    std::os::unix::net::UnixDatagram,
+    std::os::unix::net::SocketAddr,
+);
+#[cfg(not(unix))]
+type Target = std::convert::Infallible;
+
+/// Sends state changes to the service manager.
+pub struct Notifier {
+    target: Option<Target>,
+    watchdog: Option<Duration>,
+    last_beat: Mutex<Option<Instant>>,
+}
+
+impl Notifier {
+    /// Notifier for `NOTIFY_SOCKET` and `WATCHDOG_USEC` from the environment.
+    pub fn from_env() -> Self {
+        let watchdog = watchdog_from_env();
+        match std::env::var("NOTIFY_SOCKET") {
+            Ok(socket) if !socket.is_empty() => Self::connect(&socket, watchdog),
+            _ => Self::disabled(),
+        }
+    }
+
+    /// Notifier that sends nothing.
+    pub fn disabled() -> Self {
+        Self {
+            target: None,
+            watchdog: None,
+            last_beat: Mutex::new(None),
+        }
+    }
+
+    /// Notifier for a socket path, or an abstract socket if it starts with `@`.
+    #[cfg(unix)]
+    pub fn connect(socket: &str, watchdog: Option<Duration>) -> Self {
+        use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+        let addr = match socket.strip_prefix('@') {
+            #[cfg(target_os = "linux")]
+            Some(name) => {
+                use std::os::linux::net::SocketAddrExt;
+                SocketAddr::from_abstract_name(name.as_bytes())
+            }
+            #[cfg(not(target_os = "linux"))]
+            Some(_) => Err(std::io::Error::other("abstract sockets need Linux")),
+            None => SocketAddr::from_pathname(socket),
+        };
+        let target = addr.and_then(|addr| Ok((UnixDatagram::unbound()?, addr)));
+        match target {
+            Ok(target) => Self {
+                target: Some(target),
+                watchdog,
+                last_beat: Mutex::new(None),
+            },
+            Err(e) => {
+                tracing::warn!("Ignoring NOTIFY_SOCKET {}: {}", socket, e);
+                Self::disabled()
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    pub fn connect(_socket: &str, _watchdog: Option<Duration>) -> Self {
+        Self::disabled()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.target.is_some()
+    }
+
+    /// Watchdog timeout requested by the unit (`WatchdogSec=`), if any.
+    pub fn watchdog(&self) -> Option<Duration> {
+        self.watchdog
+    }
+
+    /// Startup finished.
+    pub fn ready(&self, status: &str) {
+        self.send(&format!("READY=1\nSTATUS={}", status));
+    }
+
+    pub fn status(&self, status: &str) {
+        self.send(&format!("STATUS={}", status));
+    }
+
+    /// Update the status and ping the watchdog, at most once per second
+    /// (or twice per watchdog timeout if that is shorter).
+    pub fn heartbeat(&self, status: &str) {
+        let every = match self.watchdog {
+            Some(w) => BEAT_EVERY.min(w / 2),
+            None => BEAT_EVERY,
+        };
+        {
+            let mut last = self.last_beat.lock().unwrap();
+            let now = Instant::now();
+            if last.is_some_and(|t| now.duration_since(t) < every) {
+                return;
+            }
+            *last = Some(now);
+        }
+        if self.watchdog.is_some() {
+            self.send(&format!("WATCHDOG=1\nSTATUS={}", status));
+        } else {
+            self.status(status);
+        }
+    }
+
+    pub fn stopping(&self) {
+        self.send("STOPPING=1\nSTATUS=Shutting down");
+    }
+
+    fn send(&self, message: &str) {
+        #[cfg(unix)]
+        if let Some((socket, addr)) = &self.target
+            && let Err(e) = socket.send_to_addr(message.as_bytes(), addr)
+        {
+            debug!("sd_notify failed: {}", e);
+        }
+        #[cfg(not(unix))]
+        let _ = message;
+    }
+}
+
+/// `WATCHDOG_USEC`, if set for this process (`WATCHDOG_PID` unset or ours).
+fn watchdog_from_env() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID")
+        && pid.parse::<u32>().ok() != Some(std::process::id())
+    {
+        debug!("WATCHDOG_PID is {}, not us", pid);
+        return None;
+    }
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixDatagram;
+
+    #[test]
+    fn test_notify_messages_and_heartbeat_throttle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify.sock");
+        let server = UnixDatagram::bind(&path).unwrap();
+        server.set_nonblocking(true).unwrap();
+        let recv = || {
+            let mut buf = [0u8; 256];
+            server
+                .recv(&mut buf)
+                .ok()
+                .map(|n| String::from_utf8_lossy(&buf[..n]).into_owned())
+        };
+
+        let notifier = Notifier::connect(path.to_str().unwrap(), Some(Duration::from_secs(30)));
+        assert!(notifier.is_enabled());
+
+        notifier.ready("Connecting to gateway...");
+        assert_eq!(recv().unwrap(), "READY=1\nSTATUS=Connecting to gateway...");
+
+        notifier.heartbeat("Connected | 3 events recorded");
+        assert_eq!(
+            recv().unwrap(),
+            "WATCHDOG=1\nSTATUS=Connected | 3 events recorded"
+        );
+        // Throttled: a second beat within the interval sends nothing
+        notifier.heartbeat("Connected | 4 events recorded");
+        assert_eq!(recv(), None);
+
+        notifier.stopping();
+        assert!(recv().unwrap().starts_with("STOPPING=1"));
+
+        // Without NOTIFY_SOCKET nothing is sent and nothing fails
+        let off = Notifier::disabled();
+        assert!(!off.is_enabled());
+        off.ready("ignored");
+    }
+}
@@ -0,0 +1,891 @@
+//! Reversible redaction vault
+//!
+//! In `pseudonymize` mode with `[redaction.vault]` set, each original value
+//! behind a pseudonym token is sealed to an investigator's X25519 public key
+//! and kept in `vault.sqlite` next to the ledger. The ledger itself stays
+//! redacted and its hash chain is untouched.
+//!
+//! A sealed value is `ephemeral public key || ChaCha20-Poly1305 ciphertext`,
+//! the key derived with HKDF-SHA256 from the X25519 shared secret. Only the
+//! holder of the private key can open it. [`reveal`] does so for the tokens
+//! in one ledger event and first writes a `REVEAL` event to the chain, so
+//! every disclosure is itself on the record. While a daemon holds the
+//! ledger, [`reveal_via_admin`] asks it to write that event through the
+//! admin API and opens the values locally; the private key never leaves
+//! the investigator's machine.
+//!
+//! Tokens are only 8 hex digits, so two values can share one. Entries are
+//! also keyed by the value's full keyed digest, which the event's redaction
+//! manifest lists, and a reveal opens only the entries the event names.
+
+use anyhow::{Result, anyhow, bail};
+use chrono::Utc;
+use regex::Regex;
+use ring::aead::{Aad, CHACHA20_POLY1305, LessSafeKey, Nonce, UnboundKey};
+use ring::hkdf;
+use ring::rand::{SecureRandom, SystemRandom};
+use rusqlite::{Connection, params};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::ledger::Ledger;
+use crate::redact::RedactionManifest;
+use crate::reredact::Target;
+use crate::storage::RunStorage;
+use crate::{Event, EventId, EventKind, RunId, RunMeta};
+
+/// Vault database inside the ledger directory
+pub const FILE_NAME: &str = "vault.sqlite";
+
+const HKDF_INFO: &[u8] = b"clawprint vault v1";
+
+/// Timeout for a reveal sent to a running daemon
+const ADMIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+lazy_static::lazy_static! {
+    /// Tokens written by the redactor: `[SECRET:rule:hash]`, `[EMAIL:hash]`
+    static ref TOKEN: Regex = Regex::new(r"\[(?:SECRET:[a-z0-9_]+|[A-Z]+):[0-9a-f]{8}\]").unwrap();
+}
+
+/// `[redaction.vault]` — investigator key the originals are sealed to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct VaultSettings {
+    /// Hex X25519 public key, as printed by `clawprint vault keygen`
+    pub public_key: String,
+}
+
+impl VaultSettings {
+    pub fn recipient(&self) -> Result<PublicKey> {
+        parse_key(&self.public_key)
+            .map(PublicKey::from)
+            .map_err(|e| anyhow!("redaction vault public_key: {}", e))
+    }
+}
+
+/// An investigator's private key.
+pub struct Identity {
+    secret: StaticSecret,
+}
+
+impl Identity {
+    /// A new random key pair.
+    pub fn generate() -> Result<Self> {
+        let mut bytes = [0u8; 32];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| anyhow!("no system randomness"))?;
+        Ok(Self {
+            secret: StaticSecret::from(bytes),
+        })
+    }
+
+    /// Read a hex private key file written by `clawprint vault keygen`.
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read vault key {:?}: {}", path, e))?;
+        let bytes =
+            parse_key(&content).map_err(|e| anyhow!("Invalid vault key {:?}: {}", path, e))?;
+        Ok(Self {
+            secret: StaticSecret::from(bytes),
+        })
+    }
+
+    /// Write the private key as hex to a new file, readable by the owner only.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        use std::io::Write;
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options
+            .open(path)
+            .map_err(|e| anyhow!("Cannot create {:?}: {}", path, e))?;
+        writeln!(file, "{}", hex::encode(self.secret.to_bytes()))?;
+        Ok(())
+    }
+
+    pub fn public_key(&self) -> PublicKey {
+        PublicKey::from(&self.secret)
+    }
+
+    /// Hex public key, for `[redaction.vault] public_key`
+    pub fn public_key_hex(&self) -> String {
+        hex::encode(self.public_key().as_bytes())
+    }
+
+    /// Open a value sealed to this identity.
+    pub fn open(&self, sealed: &[u8]) -> Result<String> {
+        if sealed.len() < 32 + CHACHA20_POLY1305.tag_len() {
+            bail!("sealed value is truncated");
+        }
+        let (ephemeral, ciphertext) = sealed.split_at(32);
+        let ephemeral = PublicKey::from(<[u8; 32]>::try_from(ephemeral)?);
+        let shared = self.secret.diffie_hellman(&ephemeral);
+        let key = derive_key(shared.as_bytes(), &ephemeral, &self.public_key());
+        let mut buf = ciphertext.to_vec();
+        let plain = key
+            .open_in_place(
+                Nonce::assume_unique_for_key([0; 12]),
+                Aad::empty(),
+                &mut buf,
+            )
+            .map_err(|_| anyhow!("value was not sealed to this key"))?;
+        Ok(String::from_utf8(plain.to_vec())?)
+    }
+}
+
+/// Seal `value` to `recipient` with a fresh ephemeral key.
+pub fn seal(recipient: &PublicKey, value: &str) -> Result<Vec<u8>> {
+    let ephemeral = Identity::generate()?;
+    let shared = ephemeral.secret.diffie_hellman(recipient);
+    let ephemeral_public = ephemeral.public_key();
+    let key = derive_key(shared.as_bytes(), &ephemeral_public, recipient);
+    let mut buf = value.as_bytes().to_vec();
+    // Each key seals exactly one message, so a fixed nonce is safe
+    key.seal_in_place_append_tag(
+        Nonce::assume_unique_for_key([0; 12]),
+        Aad::empty(),
+        &mut buf,
+    )
+    .map_err(|_| anyhow!("sealing failed"))?;
+    let mut sealed = ephemeral_public.as_bytes().to_vec();
+    sealed.extend(buf);
+    Ok(sealed)
+}
+
+fn derive_key(shared: &[u8], ephemeral: &PublicKey, recipient: &PublicKey) -> LessSafeKey {
+    let mut salt = ephemeral.as_bytes().to_vec();
+    salt.extend_from_slice(recipient.as_bytes());
+    let prk = hkdf::Salt::new(hkdf::HKDF_SHA256, &salt).extract(shared);
+    let okm = prk
+        .expand(&[HKDF_INFO], &CHACHA20_POLY1305)
+        .expect("ChaCha20 key length is valid for HKDF");
+    LessSafeKey::new(UnboundKey::from(okm))
+}
+
+fn parse_key(s: &str) -> Result<[u8; 32]> {
+    let bytes = hex::decode(s.trim())?;
+    <[u8; 32]>::try_from(bytes).map_err(|_| anyhow!("expected 32 bytes of hex"))
+}
+
+/// A sealed original as stored in the vault.
+#[derive(Debug, Clone)]
+pub struct VaultEntry {
+    pub token: String,
+    /// Keyed digest of the original
+    pub digest: String,
+    pub rule: String,
+    pub sealed: Vec<u8>,
+}
+
+/// The vault database.
+pub struct Vault {
+    db: Mutex<Connection>,
+    recipient: Option<PublicKey>,
+    /// Digests already stored by this process
+    stored: Mutex<HashSet<String>>,
+}
+
+impl Vault {
+    /// Open or create the vault in `dir`, sealing new entries to `recipient`.
+    pub fn create(dir: &Path, recipient: PublicKey) -> Result<Self> {
+        let mut vault = Self::open(dir)?;
+        vault.recipient = Some(recipient);
+        Ok(vault)
+    }
+
+    /// Open the vault in `dir` for lookups.
+    pub fn open(dir: &Path) -> Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let db = Connection::open(dir.join(FILE_NAME))?;
+        db.execute_batch(
+            "PRAGMA journal_mode=WAL;
+             CREATE TABLE IF NOT EXISTS vault (
+                 token TEXT NOT NULL,
+                 digest TEXT NOT NULL,
+                 rule TEXT NOT NULL,
+                 recipient TEXT NOT NULL,
+                 sealed BLOB NOT NULL,
+                 stored_at TEXT NOT NULL,
+                 PRIMARY KEY (token, digest, recipient)
+             );",
+        )?;
+        Ok(Self {
+            db: Mutex::new(db),
+            recipient: None,
+            stored: Mutex::new(HashSet::new()),
+        })
+    }
+
+    /// Seal and store the original behind `token`. `digest` (the full keyed
+    /// hash) tells apart values whose tokens collide; a value already in the
+    /// vault is not stored again.
+    pub fn store(&self, token: &str, digest: &str, rule: &str, original: &str) -> Result<()> {
+        let recipient = self
+            .recipient
+            .as_ref()
+            .ok_or_else(|| anyhow!("vault opened without a recipient key"))?;
+        if !self.stored.lock().unwrap().insert(digest.to_string()) {
+            return Ok(());
+        }
+        let sealed = seal(recipient, original)?;
+        self.db.lock().unwrap().execute(
+            "INSERT OR IGNORE INTO vault (token, digest, rule, recipient, sealed, stored_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                token,
+                digest,
+                rule,
+                hex::encode(recipient.as_bytes()),
+                sealed,
+                Utc::now().to_rfc3339()
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Entries for `token` sealed to `recipient`.
+    pub fn entries(&self, token: &str, recipient: &PublicKey) -> Result<Vec<VaultEntry>> {
+        let db = self.db.lock().unwrap();
+        let mut stmt = db.prepare(
+            "SELECT token, digest, rule, sealed FROM vault WHERE token = ?1 AND recipient = ?2
+             ORDER BY stored_at",
+        )?;
+        let entries = stmt
+            .query_map(params![token, hex::encode(recipient.as_bytes())], |row| {
+                Ok(VaultEntry {
+                    token: row.get(0)?,
+                    digest: row.get(1)?,
+                    rule: row.get(2)?,
+                    sealed: row.get(3)?,
+                })
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(entries)
+    }
+}
+
+/// Pseudonym tokens in an event payload, in order of appearance.
+pub fn tokens_in(payload: &serde_json::Value) -> Vec<String> {
+    let text = payload.to_string();
+    let mut seen = HashSet::new();
+    TOKEN
+        .find_iter(&text)
+        .map(|m| m.as_str().to_string())
+        .filter(|t| seen.insert(t.clone()))
+        .collect()
+}
+
+/// One revealed original.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revealed {
+    pub token: String,
+    pub rule: String,
+    pub value: String,
+}
+
+/// What [`reveal`] found for an event.
+#[derive(Debug, Clone)]
+pub struct Revelation {
+    pub event: Event,
+    pub revealed: Vec<Revealed>,
+    /// Tokens with no entry sealed to this identity
+    pub missing: Vec<String>,
+}
+
+/// Reveal the originals behind the tokens in event `event_id` of the
+/// daemon ledger or of a run's case file.
+///
+/// Appends a `REVEAL` event to the same chain before anything is decrypted.
+/// For the daemon ledger that takes the writer lock, so it fails with
+/// [`crate::lock::LedgerLocked`] while a daemon is recording (use [`reveal_via_admin`]
+/// then); a run must be finalized, and its `meta.json` gets the new root
+/// hash.
+pub fn reveal(
+    target: &Target,
+    event_id: u64,
+    identity: &Identity,
+    actor: &str,
+) -> Result<Revelation> {
+    let dir = target.dir();
+    if !dir.join("ledger.sqlite").exists() {
+        bail!("Ledger not found at {:?}", dir);
+    }
+    let investigator = identity.public_key_hex();
+    let (event, vault_dir) = match target {
+        Target::Ledger(dir) => {
+            let mut ledger = Ledger::open(dir, 1)?;
+            let event = record_reveal(&mut ledger, event_id, &investigator, actor)?;
+            (event, dir.clone())
+        }
+        // `record` keeps the vault in the output directory, above `runs/`
+        Target::Run { out, run_id } => {
+            let meta_path = dir.join("meta.json");
+            if !meta_path.exists() {
+                bail!(
+                    "Run {} was never finalized; run `clawprint recover` first",
+                    run_id.0
+                );
+            }
+            let mut meta: RunMeta = serde_json::from_str(&std::fs::read_to_string(&meta_path)?)?;
+            let mut storage = RunStorage::recover(run_id.clone(), out)?;
+            let event = storage
+                .get_event(event_id)?
+                .ok_or_else(|| anyhow!("No event {} in run {}", event_id, run_id.0))?;
+            let tokens = tokens_in(&event.payload);
+            let next_id = storage.last_event()?.map_or(1, |e| e.event_id.0 + 1);
+            let mut reveal = reveal_event(run_id.clone(), &event, &tokens, &investigator, actor);
+            reveal.event_id = EventId(next_id);
+            storage.write_event(reveal)?;
+            storage.flush()?;
+            meta.root_hash = storage.root_hash().unwrap_or_default();
+            meta.event_count += 1;
+            storage.finalize(&meta)?;
+            (event, out.clone())
+        }
+    };
+    open_revealed(event, &vault_dir, identity)
+}
+
+/// Write the `REVEAL` event for event `event_id` to the daemon ledger and
+/// return the event. Run by [`reveal`], and by the daemon for reveals sent
+/// through the admin API.
+pub fn record_reveal(
+    ledger: &mut Ledger,
+    event_id: u64,
+    investigator: &str,
+    actor: &str,
+) -> Result<Event> {
+    let event = ledger
+        .get_event(event_id)?
+        .ok_or_else(|| anyhow!("No event {} in the ledger", event_id))?;
+    let tokens = tokens_in(&event.payload);
+    let reveal = reveal_event(
+        RunId("daemon".to_string()),
+        &event,
+        &tokens,
+        investigator,
+        actor,
+    );
+    ledger.append_event(reveal)?;
+    ledger.flush()?;
+    Ok(event)
+}
+
+/// Open the vault entries behind the tokens in `event`, whose `REVEAL`
+/// event is already on the record.
+pub fn open_revealed(event: Event, vault_dir: &Path, identity: &Identity) -> Result<Revelation> {
+    let tokens = tokens_in(&event.payload);
+
+    // Digests of the values sealed for this event; older events don't list
+    // them, and fall back to matching on the token alone
+    let digests: Option<HashSet<String>> = event
+        .payload
+        .get("redactions")
+        .and_then(|m| serde_json::from_value::<RedactionManifest>(m.clone()).ok())
+        .map(|m| m.entries.into_iter().flat_map(|e| e.sealed).collect())
+        .filter(|d: &HashSet<String>| !d.is_empty());
+
+    let vault = Vault::open(vault_dir)?;
+    let recipient = identity.public_key();
+    let mut revealed = Vec::new();
+    let mut missing = Vec::new();
+    for token in tokens {
+        let mut entries = vault.entries(&token, &recipient)?;
+        if let Some(digests) = &digests {
+            entries.retain(|e| digests.contains(&e.digest));
+        }
+        if entries.is_empty() {
+            missing.push(token);
+            continue;
+        }
+        for entry in entries {
+            revealed.push(Revealed {
+                value: identity.open(&entry.sealed)?,
+                token: entry.token,
+                rule: entry.rule,
+            });
+        }
+    }
+    Ok(Revelation {
+        event,
+        revealed,
+        missing,
+    })
+}
+
+/// Body of `POST /admin/reveal`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RevealParams {
+    pub event_id: u64,
+    /// Hex public key of the investigator, recorded in the `REVEAL` event
+    pub investigator_key: String,
+    pub actor: String,
+}
+
+/// A reveal delivered to the daemon, which owns the ledger.
+pub struct RevealRequest {
+    pub params: RevealParams,
+    /// Receives the revealed event, or why it could not be recorded
+    pub reply: oneshot::Sender<Result<Event>>,
+}
+
+/// Cloneable handle the admin API uses to have the daemon record reveals.
+#[derive(Clone)]
+pub struct RevealHandle {
+    tx: mpsc::Sender<RevealRequest>,
+}
+
+impl RevealHandle {
+    /// Create a handle and the receiver to pass to the daemon.
+    pub fn new() -> (Self, mpsc::Receiver<RevealRequest>) {
+        let (tx, rx) = mpsc::channel(4);
+        (Self { tx }, rx)
+    }
+
+    /// Have the daemon write the `REVEAL` event and return the event it covers.
+    pub async fn reveal(&self, params: RevealParams) -> Result<Event> {
+        let (reply, outcome) = oneshot::channel();
+        self.tx
+            .send(RevealRequest { params, reply })
+            .await
+            .map_err(|_| anyhow!("Daemon is not running"))?;
+        outcome
+            .await
+            .map_err(|_| anyhow!("Daemon stopped before recording the reveal"))?
+    }
+}
+
+/// Reveal through a running daemon's admin API at `admin_url`: the daemon
+/// records the `REVEAL` event, and the values are opened here from the
+/// vault in `vault_dir`.
+pub async fn reveal_via_admin(
+    admin_url: &str,
+    token: Option<&str>,
+    vault_dir: &Path,
+    event_id: u64,
+    identity: &Identity,
+    actor: &str,
+) -> Result<Revelation> {
+    let url = format!("{}/admin/reveal", admin_url.trim_end_matches('/'));
+    let client = reqwest::Client::builder().timeout(ADMIN_TIMEOUT).build()?;
+    let params = RevealParams {
+        event_id,
+        investigator_key: identity.public_key_hex(),
+        actor: actor.to_string(),
+    };
+    let mut request = client
+        .post(&url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(serde_json::to_vec(&params)?);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| anyhow!("Cannot reach the daemon admin API at {}: {}", url, e))?;
+    let status = response.status();
+    let body: serde_json::Value =
+        serde_json::from_slice(&response.bytes().await?).unwrap_or_default();
+    if !status.is_success() {
+        let reason = body
+            .get("error")
+            .and_then(|e| e.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| status.to_string());
+        bail!("Daemon refused the reveal: {}", reason);
+    }
+    let event: Event = serde_json::from_value(body)?;
+    open_revealed(event, vault_dir, identity)
+}
+
+/// The `REVEAL` event recording who asked to see which tokens of an event.
+fn reveal_event(
+    run_id: RunId,
+    event: &Event,
+    tokens: &[String],
+    investigator: &str,
+    actor: &str,
+) -> Event {
+    let payload = serde_json::json!({
+        "event_id": event.event_id.0,
+        "event_hash": event.hash_self,
+        "tokens": tokens,
+        "investigator_key": investigator,
+        "actor": actor,
+        "at": Utc::now(),
+    });
+    let mut reveal = Event::new(run_id, EventId(0), EventKind::Reveal, payload, None);
+    reveal.actor = Some(actor.to_string());
+    reveal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lock::LedgerLocked;
+    use crate::redact::{RedactionMode, RedactionRules, Redactor};
+    use std::path::PathBuf;
+
+    /// Redact `payload` the way the recorder does, manifest included.
+    fn redact_with_manifest(
+        redactor: &Redactor,
+        mut payload: serde_json::Value,
+    ) -> serde_json::Value {
+        let (_, manifest) = redactor.redact_json_manifest(&mut payload);
+        payload["redactions"] = serde_json::to_value(manifest.unwrap()).unwrap();
+        payload
+    }
+
+    fn vault_rules(dir: &Path, investigator: &Identity) -> RedactionRules {
+        let key_file = dir.join("pseudonym.key");
+        std::fs::write(&key_file, "11".repeat(32)).unwrap();
+        RedactionRules {
+            mode: RedactionMode::Pseudonymize,
+            key_file: Some(key_file),
+            vault: Some(VaultSettings {
+                public_key: investigator.public_key_hex(),
+            }),
+            ..RedactionRules::default()
+        }
+    }
+
+    /// A ledger holding one event whose two secrets were sealed to `investigator`.
+    fn ledger_with_secrets(dir: &Path, investigator: &Identity) -> PathBuf {
+        let ledger_dir = dir.join("ledger");
+        let redactor = Redactor::new(&vault_rules(dir, investigator))
+            .unwrap()
+            .with_vault(&ledger_dir)
+            .unwrap();
+        let payload = redact_with_manifest(
+            &redactor,
+            serde_json::json!({
+                "cmd": "curl -H 'x: sk-ant-REDACTED'",
+                "password": "hunter2",
+            }),
+        );
+        assert!(!payload.to_string().contains("hunter2"));
+        assert_eq!(tokens_in(&payload).len(), 2);
+
+        let mut ledger = Ledger::open(&ledger_dir, 1).unwrap();
+        ledger
+            .append_event(Event::new(
+                RunId("r".into()),
+                EventId(1),
+                EventKind::ToolCall,
+                payload,
+                None,
+            ))
+            .unwrap();
+        ledger.flush().unwrap();
+        ledger_dir
+    }
+
+    #[test]
+    fn test_identity_file_roundtrip_never_overwrites() {
+        let dir = tempfile::tempdir().unwrap();
+        let id_file = dir.path().join("investigator.key");
+        let investigator = Identity::generate().unwrap();
+        investigator.write(&id_file).unwrap();
+        assert!(investigator.write(&id_file).is_err());
+
+        let loaded = Identity::from_file(&id_file).unwrap();
+        assert_eq!(loaded.public_key_hex(), investigator.public_key_hex());
+    }
+
+    #[test]
+    fn test_seal_and_open() {
+        let identity = Identity::generate().unwrap();
+        let sealed = seal(&identity.public_key(), "hunter2").unwrap();
+        assert!(!sealed.windows(7).any(|w| w == b"hunter2"));
+        assert_eq!(identity.open(&sealed).unwrap(), "hunter2");
+    }
+
+    #[test]
+    fn test_open_with_wrong_key_fails() {
+        let identity = Identity::generate().unwrap();
+        let sealed = seal(&identity.public_key(), "hunter2").unwrap();
+        let other = Identity::generate().unwrap();
+        let err = other.open(&sealed).unwrap_err();
+        assert!(err.to_string().contains("not sealed to this key"));
+    }
+
+    #[test]
+    fn test_open_truncated_sealed_value_fails() {
+        let identity = Identity::generate().unwrap();
+        let sealed = seal(&identity.public_key(), "hunter2").unwrap();
+        let err = identity.open(&sealed[..40]).unwrap_err();
+        assert!(err.to_string().contains("truncated"));
+
+        // Long enough to parse, but a clipped tag no longer authenticates
+        assert!(identity.open(&sealed[..sealed.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_reveal_decrypts_and_records_reveal() {
+        let dir = tempfile::tempdir().unwrap();
+        let investigator = Identity::generate().unwrap();
+        let ledger_dir = ledger_with_secrets(dir.path(), &investigator);
+
+        let result = reveal(&Target::Ledger(ledger_dir.clone()), 1, &investigator, "cli").unwrap();
+        let mut values: Vec<_> = result.revealed.iter().map(|r| r.value.as_str()).collect();
+        values.sort();
+        assert_eq!(
+            values,
+            [
+                "hunter2",
+                "sk-ant-REDACTED"
+            ]
+        );
+        assert!(result.missing.is_empty());
+
+        let ledger = Ledger::open_readonly(&ledger_dir).unwrap();
+        let reveals = ledger
+            .search_events("", Some("REVEAL"), None, None, 10)
+            .unwrap();
+        assert_eq!(reveals.len(), 1);
+        assert_eq!(reveals[0].payload["event_id"], 1);
+        assert_eq!(reveals[0].payload["tokens"].as_array().unwrap().len(), 2);
+        assert!(ledger.verify_chain().unwrap().0);
+        assert!(
+            !std::fs::read(ledger_dir.join("ledger.sqlite"))
+                .unwrap()
+                .windows(7)
+                .any(|w| w == b"hunter2")
+        );
+    }
+
+    #[test]
+    fn test_reveal_with_other_key_reports_missing() {
+        let dir = tempfile::tempdir().unwrap();
+        let investigator = Identity::generate().unwrap();
+        let ledger_dir = ledger_with_secrets(dir.path(), &investigator);
+
+        let other = Identity::generate().unwrap();
+        let result = reveal(&Target::Ledger(ledger_dir.clone()), 1, &other, "cli").unwrap();
+        assert!(result.revealed.is_empty());
+        assert_eq!(result.missing.len(), 2);
+
+        // The attempt is still recorded
+        let ledger = Ledger::open_readonly(&ledger_dir).unwrap();
+        let reveals = ledger
+            .search_events("", Some("REVEAL"), None, None, 10)
+            .unwrap();
+        assert_eq!(reveals.len(), 1);
+    }
+
+    #[test]
+    fn test_reveal_missing_event_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let investigator = Identity::generate().unwrap();
+        let ledger_dir = ledger_with_secrets(dir.path(), &investigator);
+
+        let err = reveal(&Target::Ledger(ledger_dir), 99, &investigator, "cli").unwrap_err();
+        assert!(err.to_string().contains("No event 99"));
+    }
+
+    #[test]
+    fn test_reveal_rejects_locked_ledger() {
+        let dir = tempfile::tempdir().unwrap();
+        let investigator = Identity::generate().unwrap();
+        let ledger_dir = ledger_with_secrets(dir.path(), &investigator);
+
+        let _daemon = Ledger::open(&ledger_dir, 1).unwrap();
+        let err = reveal(&Target::Ledger(ledger_dir), 1, &investigator, "cli").unwrap_err();
+        assert!(err.downcast_ref::<LedgerLocked>().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_reveal_through_daemon_while_locked() {
+        let dir = tempfile::tempdir().unwrap();
+        let investigator = Identity::generate().unwrap();
+        let ledger_dir = ledger_with_secrets(dir.path(), &investigator);
+
+        // The "daemon" holds the writer lock and serves reveal requests
+        let mut daemon = Ledger::open(&ledger_dir, 1).unwrap();
+        let (handle, mut rx) = RevealHandle::new();
+        let server = tokio::spawn(async move {
+            let req = rx.recv().await.unwrap();
+            let p = &req.params;
+            let outcome = record_reveal(&mut daemon, p.event_id, &p.investigator_key, &p.actor);
+            req.reply.send(outcome).ok();
+        });
+
+        let event = handle
+            .reveal(RevealParams {
+                event_id: 1,
+                investigator_key: investigator.public_key_hex(),
+                actor: "cli alice".to_string(),
+            })
+            .await
+            .unwrap();
+        server.await.unwrap();
+        let result = open_revealed(event, &ledger_dir, &investigator).unwrap();
+        assert_eq!(result.revealed.len(), 2);
+
+        let ledger = Ledger::open_readonly(&ledger_dir).unwrap();
+        let reveals = ledger
+            .search_events("", Some("REVEAL"), None, None, 10)
+            .unwrap();
+        assert_eq!(reveals.len(), 1);
+        assert_eq!(reveals[0].payload["actor"], "cli alice");
+        assert!(ledger.verify_chain().unwrap().0);
+
+        // With the daemon gone the request fails instead of hanging
+        let err = handle
+            .reveal(RevealParams {
+                event_id: 1,
+                investigator_key: investigator.public_key_hex(),
+                actor: "cli".to_string(),
+            })
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("not running"));
+    }
+
+    #[tokio::test]
+    async fn test_reveal_via_admin_api() {
+        let dir = tempfile::tempdir().unwrap();
+        let investigator = Identity::generate().unwrap();
+        let ledger_dir = ledger_with_secrets(dir.path(), &investigator);
+        let mut daemon = Ledger::open(&ledger_dir, 1).unwrap();
+
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let loader: crate::reload::ConfigLoader = std::sync::Arc::new(|| bail!("no config"));
+        let (reload, _reload_rx) = crate::reload::ReloadHandle::new(loader);
+        let (reveal, mut rx) = RevealHandle::new();
+        let ct = tokio_util::sync::CancellationToken::new();
+        tokio::spawn(crate::admin::start_admin_with_shutdown(
+            [127, 0, 0, 1],
+            port,
+            Some("secret".to_string()),
+            reload,
+            reveal,
+            Default::default(),
+            ct.clone(),
+        ));
+        tokio::spawn(async move {
+            while let Some(req) = rx.recv().await {
+                let p = &req.params;
+                let outcome = record_reveal(&mut daemon, p.event_id, &p.investigator_key, &p.actor);
+                req.reply.send(outcome).ok();
+            }
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let url = format!("http://127.0.0.1:{}", port);
+        let err = reveal_via_admin(&url, None, &ledger_dir, 1, &investigator, "cli")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("401"), "{}", err);
+
+        let err = reveal_via_admin(&url, Some("secret"), &ledger_dir, 99, &investigator, "cli")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("No event 99"), "{}", err);
+
+        let result = reveal_via_admin(&url, Some("secret"), &ledger_dir, 1, &investigator, "cli")
+            .await
+            .unwrap();
+        assert_eq!(result.revealed.len(), 2);
+        ct.cancel();
+
+        let ledger = Ledger::open_readonly(&ledger_dir).unwrap();
+        let reveals = ledger
+            .search_events("", Some("REVEAL"), None, None, 10)
+            .unwrap();
+        assert_eq!(reveals.len(), 1);
+    }
+
+    #[test]
+    fn test_reveal_skips_colliding_tokens() {
+        let dir = tempfile::tempdir().unwrap();
+        let investigator = Identity::generate().unwrap();
+        let ledger_dir = dir.path().join("ledger");
+        let redactor = Redactor::new(&vault_rules(dir.path(), &investigator))
+            .unwrap()
+            .with_vault(&ledger_dir)
+            .unwrap();
+        let payload = redact_with_manifest(&redactor, serde_json::json!({"password": "hunter2"}));
+        let token = tokens_in(&payload).remove(0);
+
+        // Another value that happens to share the 8-hex token
+        let vault = Vault::create(&ledger_dir, investigator.public_key()).unwrap();
+        vault
+            .store(&token, &"ff".repeat(32), "sensitive_fields", "someone-else")
+            .unwrap();
+
+        let mut ledger = Ledger::open(&ledger_dir, 1).unwrap();
+        ledger
+            .append_event(Event::new(
+                RunId("r".into()),
+                EventId(1),
+                EventKind::ToolCall,
+                payload,
+                None,
+            ))
+            .unwrap();
+        drop(ledger);
+
+        let result = reveal(&Target::Ledger(ledger_dir), 1, &investigator, "cli").unwrap();
+        let values: Vec<_> = result.revealed.iter().map(|r| r.value.as_str()).collect();
+        assert_eq!(values, ["hunter2"]);
+    }
+
+    #[test]
+    fn test_reveal_from_run() {
+        let out = tempfile::tempdir().unwrap();
+        let investigator = Identity::generate().unwrap();
+        let redactor = Redactor::new(&vault_rules(out.path(), &investigator))
+            .unwrap()
+            .with_vault(out.path())
+            .unwrap();
+        let run_id = RunId("rec".into());
+        let mut storage = RunStorage::new(run_id.clone(), out.path(), 10).unwrap();
+        storage
+            .write_event(Event::new(
+                run_id.clone(),
+                EventId(1),
+                EventKind::ToolCall,
+                redact_with_manifest(&redactor, serde_json::json!({"password": "hunter2"})),
+                None,
+            ))
+            .unwrap();
+        storage.flush().unwrap();
+        let target = Target::Run {
+            out: out.path().to_path_buf(),
+            run_id: run_id.clone(),
+        };
+        let unsealed = reveal(&target, 1, &investigator, "cli").unwrap_err();
+        assert!(unsealed.to_string().contains("never finalized"));
+
+        let mut meta = RunMeta::new(run_id.clone(), "ws://gw".into());
+        meta.event_count = 1;
+        meta.root_hash = storage.root_hash().unwrap();
+        storage.finalize(&meta).unwrap();
+        drop(storage);
+
+        let result = reveal(&target, 1, &investigator, "cli").unwrap();
+        assert_eq!(result.revealed[0].value, "hunter2");
+
+        // The REVEAL event extends the run's chain and its meta.json
+        let storage = RunStorage::open(run_id.clone(), out.path()).unwrap();
+        assert!(storage.verify_chain().unwrap());
+        let last = storage.last_event().unwrap().unwrap();
+        assert_eq!(last.kind, EventKind::Reveal);
+        let runs = crate::storage::list_runs(out.path()).unwrap();
+        assert_eq!(runs[0].1.event_count, 2);
+        assert_eq!(runs[0].1.root_hash, last.hash_self);
+    }
+}
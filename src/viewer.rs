@@ -1,11 +1,12 @@
 //! Web viewer — dashboard for Clawprint recordings
 //!
-//! Serves an interactive web interface for browsing traces.
+//! Serves an interactive web interface for browsing traces. Every request is
+//! passed to the [`AuditLog`] with the client address and response status.
 
 use anyhow::Result;
 use axum::{
     Router,
-    extract::{Path, Query, Request, State},
+    extract::{ConnectInfo, Path, Query, Request, State},
     http::StatusCode,
     middleware::{self, Next},
     response::{Html, IntoResponse, Json},
@@ -17,8 +18,11 @@ use std::path::PathBuf;
 use std::sync::Arc;
 use tracing::info;
 
-use crate::RunId;
+use crate::admin;
+use crate::audit::AuditLog;
+use crate::metrics::DaemonMetrics;
 use crate::storage::{RunStorage, list_runs_with_stats};
+use crate::{RunId, RunStatus};
 
 #[derive(Clone)]
 struct ViewerState {
@@ -40,12 +44,38 @@ pub async fn bearer_auth(
     }
 }
 
-pub async fn start_viewer(
+/// Record each request (including rejected ones) in the audit log.
+async fn audit_access(
+    State(audit): State<AuditLog>,
+    req: Request,
+    next: Next,
+) -> impl IntoResponse {
+    let client = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let method = req.method().to_string();
+    let path = req.uri().to_string();
+    let response = next.run(req).await;
+    audit.record(
+        "viewer_access",
+        format!("viewer {}", client),
+        serde_json::json!({
+            "method": method,
+            "path": path,
+            "status": response.status().as_u16(),
+        }),
+    );
+    response
+}
+
+fn build_router(
     base_path: PathBuf,
-    host: [u8; 4],
-    port: u16,
     token: Option<String>,
-) -> Result<()> {
+    audit: AuditLog,
+    metrics: Option<Arc<DaemonMetrics>>,
+) -> Router {
     let state = ViewerState { base_path };
 
     let app = Router::new()
@@ -62,49 +92,59 @@ pub async fn start_viewer(
     } else {
         app
     };
+    // Probes are neither token-protected nor audited, as on the admin port
+    app.layer(middleware::from_fn_with_state(audit, audit_access))
+        .merge(admin::probe_routes(metrics))
+}
+
+/// Start the viewer. Accesses are only logged: a standalone viewer can't
+/// write to a ledger the daemon holds.
+pub async fn start_viewer(
+    base_path: PathBuf,
+    host: [u8; 4],
+    port: u16,
+    token: Option<String>,
+) -> Result<()> {
+    let app = build_router(base_path, token, AuditLog::default(), None);
 
     let addr = SocketAddr::from((host, port));
     info!("Viewer starting on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app.into_make_service()).await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await?;
     Ok(())
 }
 
-/// Start the viewer with an external cancellation token for coordinated shutdown.
+/// Start the viewer with an external cancellation token for coordinated
+/// shutdown, recording accesses through `audit`. `/readyz` reflects the
+/// daemon's `metrics` when one runs in the same process.
 pub async fn start_viewer_with_shutdown(
     base_path: PathBuf,
     host: [u8; 4],
     port: u16,
     token: Option<String>,
+    audit: AuditLog,
+    metrics: Option<Arc<DaemonMetrics>>,
     ct: tokio_util::sync::CancellationToken,
 ) -> Result<()> {
-    let state = ViewerState { base_path };
-
-    let app = Router::new()
-        .route("/", get(index_handler))
-        .route("/view/{run_id}", get(view_run_handler))
-        .route("/api/runs", get(list_runs_handler))
-        .route("/api/runs/{run_id}", get(get_run_handler))
-        .route("/api/runs/{run_id}/events", get(get_events_handler))
-        .route("/api/runs/{run_id}/stats", get(get_run_stats_handler))
-        .with_state(state);
-
-    let app = if let Some(tok) = token {
-        app.layer(middleware::from_fn_with_state(Arc::new(tok), bearer_auth))
-    } else {
-        app
-    };
+    let app = build_router(base_path, token, audit, metrics);
 
     let addr = SocketAddr::from((host, port));
     info!("Viewer starting on http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app.into_make_service())
-        .with_graceful_shutdown(async move {
-            ct.cancelled().await;
-        })
-        .await?;
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
+        ct.cancelled().await;
+    })
+    .await?;
     Ok(())
 }
 
@@ -178,7 +218,9 @@ async fn index_handler(State(state): State<ViewerState>) -> impl IntoResponse {
                 id_esc.clone()
             };
             let dur = format_duration_html(meta.started_at, meta.ended_at);
-            let status = if meta.ended_at.is_some() {
+            let status = if meta.status == RunStatus::Incomplete {
+                r#"<span class="badge progress">Incomplete</span>"#
+            } else if meta.ended_at.is_some() {
                 r#"<span class="badge complete">Sealed</span>"#
             } else {
                 r#"<span class="badge progress">Tracking</span>"#
@@ -236,6 +278,8 @@ async fn list_runs_handler(State(state): State<ViewerState>) -> impl IntoRespons
                         "started_at": meta.started_at,
                         "ended_at": meta.ended_at,
                         "event_count": meta.event_count,
+                        "status": meta.status,
+                        "ledger_range": meta.ledger_range,
                         "size": size,
                     })
                 })
@@ -614,9 +658,11 @@ mod tests {
 
         let ct = CancellationToken::new();
         let ct_clone = ct.clone();
+        let (audit, mut audit_rx) = AuditLog::channel();
 
         let handle = tokio::spawn(async move {
-            start_viewer_with_shutdown(base_path, [127, 0, 0, 1], port, None, ct_clone).await
+            start_viewer_with_shutdown(base_path, [127, 0, 0, 1], port, None, audit, None, ct_clone)
+                .await
         });
 
         // Wait for server to start
@@ -642,6 +688,13 @@ mod tests {
             &response[..response.len().min(200)]
         );
 
+        // The access is audited with the client address
+        let entry = audit_rx.recv().await.unwrap();
+        assert_eq!(entry.action, "viewer_access");
+        assert_eq!(entry.actor, "viewer 127.0.0.1");
+        assert_eq!(entry.detail["path"], "/");
+        assert_eq!(entry.detail["status"], 200);
+
         // Shut down gracefully
         ct.cancel();
 
@@ -652,4 +705,51 @@ mod tests {
 
         result.expect("viewer returned an error");
     }
+
+    #[tokio::test]
+    async fn test_probes_on_viewer_port() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let tmp = tempfile::tempdir().unwrap();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        drop(listener);
+
+        let ct = tokio_util::sync::CancellationToken::new();
+        let (audit, mut audit_rx) = AuditLog::channel();
+        let metrics = Arc::new(DaemonMetrics::default());
+        tokio::spawn(start_viewer_with_shutdown(
+            tmp.path().to_path_buf(),
+            [127, 0, 0, 1],
+            port,
+            Some("secret".into()),
+            audit,
+            Some(metrics),
+            ct.clone(),
+        ));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+
+        let status = |path: &'static str| async move {
+            let mut stream = tokio::net::TcpStream::connect(("127.0.0.1", port))
+                .await
+                .unwrap();
+            let request = format!(
+                "GET {} HTTP/1.1\r\nHost: x\r\nConnection: close\r\n\r\n",
+                path
+            );
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response[9..12].to_string()
+        };
+        // Open without the token; not ready until the daemon connects
+        assert_eq!(status("/healthz").await, "200");
+        assert_eq!(status("/readyz").await, "503");
+        assert_eq!(status("/api/runs").await, "401");
+
+        // Only the API request is audited
+        assert_eq!(audit_rx.recv().await.unwrap().detail["path"], "/api/runs");
+        assert!(audit_rx.try_recv().is_err());
+        ct.cancel();
+    }
 }